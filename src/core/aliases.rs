@@ -0,0 +1,130 @@
+//! Names defined by the user with `alias`, which expand to one or
+//! more commands separated by `;`.
+
+use std::collections::HashMap;
+
+use core::{Command, ParseCommandError};
+
+#[derive(Debug, Clone, Default)]
+pub struct Aliases(HashMap<String, String>);
+
+impl Aliases {
+    /// Make `name` expand to `expansion`, unless that would make it
+    /// expand to itself, directly or through other aliases, or through
+    /// `.` among other commands.
+    pub fn define(&mut self, name: &str, expansion: &str) -> Result<(), ParseCommandError> {
+        let mut aliases = self.clone();
+        aliases.0.insert(name.to_owned(), expansion.to_owned());
+        // The expansion may use commands or aliases that are not valid
+        // yet, which only fail when the alias is used.
+        match aliases.parse(name) {
+            Err(e @ ParseCommandError::RecursiveAlias(_))
+            | Err(e @ ParseCommandError::RepeatInAlias(_)) => return Err(e),
+            _ => {}
+        }
+        *self = aliases;
+        Ok(())
+    }
+
+    /// Parse `input` like the prompt does, expanding the alias it
+    /// starts with first, if any. The arguments following the alias are
+    /// given to the last command it expands to.
+    pub fn parse(&self, input: &str) -> Result<Command, ParseCommandError> {
+        self.expand(input, &mut Vec::new())
+    }
+
+    /// Like `parse`, where `expanding` holds the aliases being expanded
+    /// already, which must not appear again.
+    fn expand(
+        &self,
+        input: &str,
+        expanding: &mut Vec<String>,
+    ) -> Result<Command, ParseCommandError> {
+        let input = input.trim();
+        let (name, args) = match input.find(' ') {
+            Some(idx) => input.split_at(idx),
+            None => (input, ""),
+        };
+        let expansion = match self.0.get(name) {
+            Some(expansion) => expansion,
+            None => return input.parse(),
+        };
+        if expanding.iter().any(|alias| alias == name) {
+            return Err(ParseCommandError::RecursiveAlias(name.to_owned()));
+        }
+        expanding.push(name.to_owned());
+        let parts: Vec<&str> = expansion
+            .split(';')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect();
+        let mut commands = Vec::with_capacity(parts.len());
+        for (idx, part) in parts.iter().enumerate() {
+            let cmd = if idx + 1 == parts.len() {
+                self.expand(&format!("{}{}", part, args), expanding)?
+            } else {
+                self.expand(part, expanding)?
+            };
+            commands.push(cmd);
+        }
+        expanding.pop();
+        // Running a sequence makes it the last command, so `.` in it
+        // would run it again, forever
+        if commands.len() > 1 && commands.contains(&Command::RepeatLast) {
+            return Err(ParseCommandError::RepeatInAlias(name.to_owned()));
+        }
+        if commands.len() == 1 {
+            Ok(commands.remove(0))
+        } else {
+            Ok(Command::Sequence(commands))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_to_a_sequence() {
+        let mut aliases = Aliases::default();
+        aliases.define("wq", "save; quit").unwrap();
+        assert_eq!(
+            aliases.parse("wq").unwrap(),
+            Command::Sequence(vec![Command::Save(None), Command::Quit])
+        );
+    }
+
+    #[test]
+    fn rejects_recursive_aliases() {
+        let mut aliases = Aliases::default();
+        aliases.define("a", "b").unwrap();
+        assert!(matches!(
+            aliases.define("b", "save; a"),
+            Err(ParseCommandError::RecursiveAlias(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_repeat_among_other_commands() {
+        let mut aliases = Aliases::default();
+        assert!(matches!(
+            aliases.define("x", ".; save"),
+            Err(ParseCommandError::RepeatInAlias(_))
+        ));
+        // Through another alias too
+        aliases.define("again", ".").unwrap();
+        assert!(matches!(
+            aliases.define("y", "again; save"),
+            Err(ParseCommandError::RepeatInAlias(_))
+        ));
+        assert!(aliases.parse("x").is_err());
+    }
+
+    #[test]
+    fn repeat_alone_is_allowed() {
+        let mut aliases = Aliases::default();
+        aliases.define("r", ".").unwrap();
+        assert_eq!(aliases.parse("r").unwrap(), Command::RepeatLast);
+    }
+}
@@ -0,0 +1,67 @@
+//! Access to the clipboard of the desktop, through the command line
+//! tools that ship with most systems.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Programs that copy their standard input to the clipboard, and the
+/// matching programs that print the clipboard, in the order they are
+/// tried.
+const TOOLS: &[(&[&str], &[&str])] = &[
+    (&["wl-copy"], &["wl-paste", "--no-newline"]),
+    (
+        &["xclip", "-selection", "clipboard"],
+        &["xclip", "-selection", "clipboard", "-out"],
+    ),
+    (
+        &["xsel", "--clipboard", "--input"],
+        &["xsel", "--clipboard", "--output"],
+    ),
+    (&["pbcopy"], &["pbpaste"]),
+];
+
+/// Put `text` in the system clipboard. Return `false` if no tool could
+/// do it, for instance because there is no display.
+pub fn copy_to_system_clipboard(text: &str) -> bool {
+    TOOLS.iter().any(|(copy, _)| {
+        run_copy(copy, text).unwrap_or_else(|e| {
+            debug!("cannot copy with {}: {}", copy[0], e);
+            false
+        })
+    })
+}
+
+fn run_copy(argv: &[&str], text: &str) -> Result<bool, ::std::io::Error> {
+    // The output is discarded so that it is not printed over the
+    // editor.
+    let mut child = Command::new(argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    Ok(child.wait()?.success())
+}
+
+/// Return the content of the system clipboard, or `None` if no tool
+/// could read it.
+pub fn read_system_clipboard() -> Option<String> {
+    TOOLS.iter().find_map(|(_, paste)| {
+        let output = Command::new(paste[0])
+            .args(&paste[1..])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        match output {
+            Ok(output) if output.status.success() => String::from_utf8(output.stdout).ok(),
+            Ok(_) => None,
+            Err(e) => {
+                debug!("cannot paste with {}: {}", paste[0], e);
+                None
+            }
+        }
+    })
+}
@@ -1,28 +1,470 @@
 //! Command system for xi-term. A command represents
 //! a task the user wants the editor to preform,
 /// currently commands can only be input through the CommandPrompt. Vim style.
+use chrono::format::{Item, StrftimeItems};
+use termion::event::Key;
 use xrl::ViewId;
 
+use std::error;
+use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug)]
+use core::parse_keys;
+
+/// How `Command::SortLines` orders the lines. By default, lines are
+/// sorted in ascending lexical order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SortConfig {
+    /// Sort in descending order (flag `r`).
+    pub reverse: bool,
+    /// Ignore case when comparing lines (flag `i`).
+    pub case_insensitive: bool,
+    /// Only keep the first of the lines that compare equal (flag `u`).
+    pub unique: bool,
+}
+
+impl SortConfig {
+    /// Parse flags such as `ri`. Return the first unknown flag, if
+    /// any.
+    fn from_flags(flags: &str) -> Result<SortConfig, char> {
+        let mut config = SortConfig::default();
+        for flag in flags.chars() {
+            match flag {
+                'r' => config.reverse = true,
+                'i' => config.case_insensitive = true,
+                'u' => config.unique = true,
+                c => return Err(c),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// What `Command::Find` searches for.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FindConfig {
+    /// The string searched for.
+    pub term: String,
+    /// Stop counting matches after this many (control `l` followed by
+    /// the number), so that a broad search of a huge buffer does not
+    /// stall rendering.
+    pub max_results: Option<usize>,
+}
+
+impl FindConfig {
+    /// Parse the arguments of `find`: optional controls, then the
+    /// search term. A leading word is only taken as a control if more
+    /// words follow it, so that `find l10` still searches for `l10`.
+    fn from_args(parts: &[&str]) -> FindConfig {
+        let mut config = FindConfig::default();
+        let mut words = parts;
+        if words.len() > 1 && words[0].starts_with('l') {
+            if let Ok(max) = words[0][1..].parse::<usize>() {
+                config.max_results = Some(max);
+                words = &words[1..];
+            }
+        }
+        config.term = words.join(" ");
+        config
+    }
+}
+
+/// A line to move the cursor to, as typed in the prompt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineTarget {
+    /// A line number, starting at 1 (`42`).
+    Absolute(u64),
+    /// A number of lines after the cursor line, or before it if
+    /// negative (`+10`, `-10`).
+    Relative(i64),
+    /// The last line of the buffer (`$` or `-0`).
+    Last,
+    /// The line at a percentage of the buffer, from 0 for the first
+    /// line to 100 for the last one (`50%`).
+    Percent(u8),
+}
+
+impl LineTarget {
+    fn parse(s: &str) -> Option<LineTarget> {
+        let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        match s {
+            "$" | "-0" => Some(LineTarget::Last),
+            _ if s.starts_with('+') || s.starts_with('-') => {
+                let (sign, digits) = s.split_at(1);
+                if !is_number(digits) {
+                    return None;
+                }
+                let lines = digits.parse::<i64>().ok()?;
+                let lines = if sign == "-" { -lines } else { lines };
+                Some(LineTarget::Relative(lines))
+            }
+            _ if is_number(s) => s.parse().ok().map(LineTarget::Absolute),
+            _ if s.ends_with('%') && is_number(&s[..s.len() - 1]) => {
+                // Percentages above 100 go to the last line
+                let percent = s[..s.len() - 1].parse::<u64>().unwrap_or(100).min(100);
+                Some(LineTarget::Percent(percent as u8))
+            }
+            _ => None,
+        }
+    }
+
+    /// Parse a line as typed in the prompt, optionally followed by a
+    /// colon and a column starting at 1 (`42:7`).
+    pub fn parse_position(s: &str) -> Option<(LineTarget, Option<u64>)> {
+        match s.rfind(':') {
+            Some(idx) => {
+                let column = &s[idx + 1..];
+                if column.is_empty() || !column.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                let target = LineTarget::parse(&s[..idx])?;
+                Some((target, Some(column.parse().ok()?)))
+            }
+            None => LineTarget::parse(s).map(|target| (target, None)),
+        }
+    }
+}
+
+/// How line numbers are displayed in the gutter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineNumberMode {
+    /// No line numbers.
+    Off,
+    /// The number of each line.
+    Absolute,
+    /// The distance of each line to the cursor line.
+    Relative,
+    /// The number of the cursor line, and the distance to it for the
+    /// other lines.
+    Hybrid,
+}
+
+impl FromStr for LineNumberMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<LineNumberMode, ()> {
+        match s {
+            "off" => Ok(LineNumberMode::Off),
+            "absolute" => Ok(LineNumberMode::Absolute),
+            "relative" => Ok(LineNumberMode::Relative),
+            "hybrid" => Ok(LineNumberMode::Hybrid),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The line ending `Command::SetLineEnding` converts a buffer to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    /// `\n`, as on Unix.
+    Lf,
+    /// `\r\n`, as on Windows.
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+impl FromStr for LineEnding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<LineEnding, ()> {
+        match s {
+            "lf" => Ok(LineEnding::Lf),
+            "crlf" => Ok(LineEnding::Crlf),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Where `Command::MoveTo` moves the cursor, in its line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MovePoint {
+    /// The start of the line.
+    Bol,
+    /// The end of the line.
+    Eol,
+    /// The start of the screen row of a wrapped line.
+    VisualBol,
+    /// The end of the screen row of a wrapped line.
+    VisualEol,
+}
+
+impl FromStr for MovePoint {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<MovePoint, ()> {
+        match s {
+            "bol" => Ok(MovePoint::Bol),
+            "eol" => Ok(MovePoint::Eol),
+            "vbol" => Ok(MovePoint::VisualBol),
+            "veol" => Ok(MovePoint::VisualEol),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How a command that can't run is signaled, set with
+/// `Command::SetBell`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BellMode {
+    /// Nothing but the status message, if any.
+    #[default]
+    Off,
+    /// Invert the status line briefly.
+    Visual,
+    /// Ring the terminal bell.
+    Audible,
+}
+
+impl FromStr for BellMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<BellMode, ()> {
+        match s {
+            "off" => Ok(BellMode::Off),
+            "visual" => Ok(BellMode::Visual),
+            "audible" => Ok(BellMode::Audible),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What happens when the file of a view changes on disk, set with
+/// `Command::SetAutoReload`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AutoReload {
+    /// Nothing, the change is not even noticed.
+    Off,
+    /// Ask whether to reload the view.
+    #[default]
+    Prompt,
+    /// Reload the view if it has no unsaved changes, and ask
+    /// otherwise.
+    Auto,
+}
+
+impl FromStr for AutoReload {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<AutoReload, ()> {
+        match s {
+            "off" => Ok(AutoReload::Off),
+            "prompt" => Ok(AutoReload::Prompt),
+            "auto" => Ok(AutoReload::Auto),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The order in which `Command::NextBuffer` and `Command::PrevBuffer`
+/// go through the buffers, set with `Command::SetBufferCycleOrder`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BufferCycleOrder {
+    /// The order in which the buffers were opened.
+    #[default]
+    Created,
+    /// The most recently used buffers first, so that the next buffer
+    /// is the one used before the current one.
+    Mru,
+}
+
+impl FromStr for BufferCycleOrder {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<BufferCycleOrder, ()> {
+        match s {
+            "created" => Ok(BufferCycleOrder::Created),
+            "mru" => Ok(BufferCycleOrder::Mru),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The shape of the cursor in the terminal, set with
+/// `Command::SetCursorShape`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CursorShape {
+    /// The shape the terminal uses by default.
+    #[default]
+    Default,
+    Block,
+    Underline,
+    Bar,
+}
+
+impl FromStr for CursorShape {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<CursorShape, ()> {
+        match s {
+            "default" => Ok(CursorShape::Default),
+            "block" => Ok(CursorShape::Block),
+            "underline" => Ok(CursorShape::Underline),
+            "bar" => Ok(CursorShape::Bar),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How `Command::SplitView` divides the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitDirection {
+    /// One pane above the other.
+    Horizontal,
+    /// One pane beside the other.
+    Vertical,
+}
+
+/// A setting changed with `set <key> <value>`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Setting {
+    /// The width of the tab stops of the current view.
+    TabWidth(u16),
+    /// Whether long lines of the current view are wrapped.
+    Wrap(bool),
+    /// How line numbers are shown in the current view.
+    LineNumbers(LineNumberMode),
+    /// How many lines are kept visible above and below the cursor.
+    ScrollOff(u16),
+    /// The theme of all the views.
+    Theme(String),
+    /// Whether the core inserts spaces rather than a tab character.
+    TranslateTabsToSpaces(bool),
+    /// Whether moving to the start of the line stops at its first
+    /// non-blank character first.
+    SmartHome(bool),
+}
+
+/// The keys accepted by `set`.
+const SETTING_KEYS: &[&str] = &[
+    "tab_width",
+    "wrap",
+    "line_numbers",
+    "scrolloff",
+    "theme",
+    "translate_tabs_to_spaces",
+    "smart_home",
+];
+
+/// Parse a boolean setting: `on`, `off`, `true` or `false`.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "on" | "true" => Some(true),
+        "off" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+impl Setting {
+    /// Parse the value of the setting named `key`. The value may
+    /// contain spaces, for theme names.
+    fn parse(key: &str, value: &str) -> Result<Setting, ParseCommandError> {
+        let setting = match key {
+            "tab_width" => value
+                .parse::<u16>()
+                .ok()
+                .filter(|width| (1..=MAX_TAB_WIDTH).contains(width))
+                .map(Setting::TabWidth),
+            "wrap" => parse_bool(value).map(Setting::Wrap),
+            "line_numbers" => match parse_bool(value) {
+                Some(true) => Some(Setting::LineNumbers(LineNumberMode::Absolute)),
+                Some(false) => Some(Setting::LineNumbers(LineNumberMode::Off)),
+                None => value.parse().ok().map(Setting::LineNumbers),
+            },
+            "scrolloff" => value.parse::<u16>().ok().map(Setting::ScrollOff),
+            "theme" => Some(Setting::Theme(value.to_owned())),
+            "translate_tabs_to_spaces" => parse_bool(value).map(Setting::TranslateTabsToSpaces),
+            "smart_home" => parse_bool(value).map(Setting::SmartHome),
+            _ => return Err(ParseCommandError::UnknownSetting(key.to_owned())),
+        };
+        setting.ok_or_else(|| ParseCommandError::InvalidArgument {
+            cmd: format!("set {}", key),
+            arg: value.to_owned(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Command {
     /// Close the CommandPrompt.
     Cancel,
-    /// Quit editor.
+    /// Dismiss the innermost thing that is active: the prompt, the
+    /// extra cursors, the selection, or the pending count and search
+    /// highlights.
+    Escape,
+    /// Show the list of all commands, to pick one to run.
+    OpenPalette,
+    /// Do nothing. Useful to bind a key that should be ignored.
+    Noop,
+    /// Quit editor, asking first if some buffers have unsaved changes.
     Quit,
+    /// Quit editor, even if some buffers have unsaved changes.
+    ForceQuit,
     /// Save the current file buffer.
     Save(Option<ViewId>),
+    /// Save all the file buffers.
+    SaveAll,
+    /// Save all the file buffers, and quit if they could all be
+    /// saved.
+    SaveAllAndQuit,
     /// Backspace
     Back,
     /// Delete
     Delete,
     /// Open A new file.
     Open(Option<String>),
-    /// Cycle to the next View.
+    /// Move the cursor to a line.
+    GotoLine(LineTarget),
+    /// Move the cursor to a line and a column, starting at 1.
+    GotoPosition(LineTarget, u64),
+    /// Open the prompt to type a line to move to, previewing it while
+    /// it is typed.
+    OpenGotoPrompt,
+    /// List the key bindings with the commands they run.
+    ShowKeybindings,
+    /// Open an empty buffer that is not backed by a file yet.
+    NewBuffer,
+    /// Save the current buffer to the given file, which then backs
+    /// the buffer.
+    SaveAs(String),
+    /// Make a view read-only, or writable again.
+    ToggleReadOnly(Option<ViewId>),
+    /// Enable or disable soft wrapping of long lines in a view.
+    ToggleWrap(Option<ViewId>),
+    /// Re-open the file backing a view from disk, asking first if the
+    /// view has unsaved changes.
+    Reload(Option<ViewId>),
+    /// Re-open the file backing a view from disk, discarding any
+    /// unsaved changes.
+    ForceReload(Option<ViewId>),
+    /// Close the current view, asking first if it has unsaved changes.
+    CloseCurrentView,
+    /// Close the current view, discarding any unsaved changes.
+    ForceCloseCurrentView,
+    /// Open again the file of the view closed last, at the position
+    /// its cursor was.
+    ReopenClosed,
+    /// Cycle to the next View, going back to the first one after the
+    /// last one.
     NextBuffer,
-    /// Cycle to the previous buffer.
+    /// Cycle to the previous buffer, going to the last one before the
+    /// first one.
     PrevBuffer,
+    /// Choose the order in which `NextBuffer` and `PrevBuffer` go
+    /// through the buffers.
+    SetBufferCycleOrder(BufferCycleOrder),
+    /// Show the current buffer in a second pane, with its own cursor
+    /// and scroll position.
+    SplitView(SplitDirection),
+    /// Move the focus to the other pane.
+    FocusNextPane,
     /// Move cursor left.
     MoveLeft,
     /// Move cursor right.
@@ -31,16 +473,328 @@ pub enum Command {
     MoveUp,
     /// Move cursor down.
     MoveDown,
+    /// Move the cursor to the start or end of its line, or of its
+    /// screen row if the line is wrapped. With `extend`, the selection
+    /// is extended to there.
+    MoveTo { point: MovePoint, extend: bool },
     /// Page down
     PageDown,
     /// Page up
     PageUp,
     /// Change the syntax theme.
     SetTheme(String),
+    /// Highlight the current view with the given syntax, instead of
+    /// the one picked from the file extension.
+    SetSyntax(String),
+    /// Show the syntaxes available.
+    ListSyntaxes,
     /// Toggle displaying line numbers.
     ToggleLineNumbers,
+    /// Show or hide spaces and tabs in the current view, and highlight
+    /// trailing whitespace.
+    ToggleWhitespace,
+    /// Choose how a command that can't run is signaled, such as a
+    /// search without matches or a move past the end of the buffer.
+    SetBell(BellMode),
+    /// Choose what happens when the file of a view changes on disk.
+    SetAutoReload(AutoReload),
+    /// Set the shape of the cursor while editing text, and while typing
+    /// in the prompt or in a list. A shape that isn't given is left as
+    /// is.
+    SetCursorShape {
+        insert: Option<CursorShape>,
+        command: Option<CursorShape>,
+    },
+    /// Set how line numbers are displayed.
+    SetLineNumberMode(LineNumberMode),
+    /// Convert the line endings of the current buffer, and end the new
+    /// lines with the same.
+    SetLineEnding(LineEnding),
+    /// Move to the next or previous markdown heading, optionally
+    /// only considering headings of the given level.
+    MarkdownHeadingMove { forward: bool, level: Option<u8> },
+    /// Fold the section under each markdown heading.
+    FoldAllSections,
+    /// Fold the lines under the cursor line that are more indented
+    /// than it, or unfold them if they are folded already.
+    ToggleFold,
+    /// Fold every block of lines more indented than the line above
+    /// them.
+    FoldAll,
+    /// Unfold all the folded lines.
+    UnfoldAll,
+    /// Switch to the view whose file name contains the given string.
+    SwitchBuffer(String),
+    /// Show the open views.
+    ListBuffers,
+    /// Duplicate the line(s) under the cursor(s).
+    DuplicateLine,
+    /// Delete from each cursor to the end of its line, or join the
+    /// line with the next one if the cursor is at its end.
+    DeleteToEol,
+    /// Delete from each cursor to the start of its line.
+    DeleteToBol,
+    /// Delete from each cursor to the next word boundary, or the
+    /// previous one if `forward` is false. Selections are deleted
+    /// instead.
+    DeleteWord { forward: bool },
+    /// Insert the current local time at each cursor, formatted with
+    /// the given strftime format, or as ISO 8601 if there is none.
+    InsertDateTime(Option<String>),
+    /// Swap the characters before and under each cursor, and move
+    /// past them. At the end of a line, swap the two characters before
+    /// the cursor.
+    TransposeChars,
+    /// Undo the last group of edits.
+    Undo,
+    /// Redo the last undone group of edits.
+    Redo,
+    /// Move the cursor down by half the height of the view, extending
+    /// the selection if `extend` is true.
+    HalfPageDown { extend: bool },
+    /// Move the cursor up by half the height of the view, extending
+    /// the selection if `extend` is true.
+    HalfPageUp { extend: bool },
+    /// Scroll so that the cursor line is in the middle of the view,
+    /// without moving the cursor.
+    ScrollCenter,
+    /// Scroll so that the cursor line is at the top of the view.
+    ScrollTop,
+    /// Scroll so that the cursor line is at the bottom of the view.
+    ScrollBottom,
+    /// Move the cursor to the bracket matching the one under it,
+    /// selecting the text in between if `extend` is true.
+    GotoMatchingBracket { extend: bool },
+    /// Move the cursor to the next line, or the previous one if
+    /// `forward` is false, indented like the cursor line, extending the
+    /// selection if `extend` is true.
+    MoveToMatchingIndent { forward: bool, extend: bool },
+    /// Search for a string in the current buffer.
+    Find(FindConfig),
+    /// Select the next match of the current search.
+    FindNext,
+    /// Select the previous match of the current search.
+    FindPrev,
+    /// Place a cursor at every match of the current search, or of the
+    /// word under the cursor if there is no search.
+    FindAll,
+    /// Search every open buffer, and list the matching lines in the
+    /// quickfix panel.
+    FindInFiles(FindConfig),
+    /// Show or hide the quickfix panel, which lists the results of
+    /// the last search.
+    ToggleQuickfix,
+    /// Go to the next location of the quickfix list.
+    QuickfixNext,
+    /// Go to the previous location of the quickfix list.
+    QuickfixPrev,
+    /// Go back to the position the cursor was at before the last
+    /// jump.
+    JumpBack,
+    /// Go back to where the current buffer was last edited.
+    GotoLastEdit,
+    /// Go forward in the jump list, after going back.
+    JumpForward,
+    /// Move the cursor to the other end of the current selection.
+    SwapSelectionEnds,
+    /// Display the number of lines, words and characters of the
+    /// current buffer.
+    BufferStats,
+    /// Set how long to wait for the next key of a key sequence, in
+    /// milliseconds.
+    SetKeyTimeout(u64),
+    /// Change one of the settings that have a key. `tabwidth`,
+    /// `expandtabs` and `scrolloff` are shorthands for it.
+    Set(Setting),
+    /// Comment the selected lines with the given token, or uncomment
+    /// them if they're all commented already.
+    ToggleComment(String),
+    /// Join the selected lines, or the current line with the next one.
+    JoinLines,
+    /// Swap the case of the selected letters, or of the character under
+    /// the cursor and move past it.
+    ToggleCase,
+    /// Put the selected letters, or the character under the cursor, in
+    /// upper case.
+    UpperCase,
+    /// Put the selected letters, or the character under the cursor, in
+    /// lower case.
+    LowerCase,
+    /// Insert the first string before and the second one after each
+    /// selection. Without selection, insert both at each cursor and
+    /// put the cursor between them.
+    SurroundSelection(String, String),
+    /// Add the given number to the number under or after each cursor,
+    /// on its line.
+    IncrementNumber(i64),
+    /// Sort the selected lines, or the whole buffer if nothing is
+    /// selected.
+    SortLines(SortConfig),
+    /// Select the line of each cursor, with its line ending.
+    SelectLine,
+    /// Select the word under each cursor, or the whitespace around it.
+    SelectWord,
+    /// Copy the lines that have a cursor or a selection.
+    CopyLine,
+    /// Cut the lines that have a cursor or a selection.
+    CutLine,
+    /// Insert the text last copied or cut.
+    Paste,
+    /// Copy the lines that have a cursor or a selection to the named
+    /// register, from `a` to `z`.
+    CopyToRegister(char),
+    /// Cut the lines that have a cursor or a selection to the named
+    /// register.
+    CutToRegister(char),
+    /// Insert the text of the named register.
+    PasteRegister(char),
+    /// Start or stop sharing copied text with the system clipboard.
+    ToggleSystemClipboard,
+    /// Start recording a macro under the given name, or stop recording
+    /// if a macro is being recorded.
+    RecordMacro(char),
+    /// Replay the macro recorded under the given name, the given number
+    /// of times.
+    PlayMacro { name: char, count: u32 },
+    /// Go to the definition of the symbol under the cursor, as found
+    /// by a language plugin.
+    GotoDefinition,
+    /// List the symbols of the current view, as reported by a language
+    /// plugin or found by their keywords, to move to one of them.
+    GotoSymbol,
+    /// Open the file whose name is under the cursor, at the line
+    /// following it if any, like Vim's `gf`.
+    OpenFileUnderCursor,
+    /// Remember the cursor position of the current view under the
+    /// given name.
+    SetMark(char),
+    /// Move to the position remembered under the given name.
+    GotoMark(char),
+    /// Remove the trailing whitespace of every line in the current
+    /// view.
+    TrimTrailingWhitespace,
+    /// Enable or disable trimming trailing whitespace before saving.
+    ToggleTrimOnSave,
+    /// Make saving ensure the buffer ends with exactly one newline,
+    /// adding one if it's missing and removing extra ones.
+    SetFinalNewline(bool),
+    /// Indent the selected lines by one tab stop.
+    Indent,
+    /// Remove up to one tab stop of indentation from the selected
+    /// lines.
+    Unindent,
+    /// Write the indentation of the selected lines with tabs or with
+    /// spaces, as Tab inserts them, without changing its width.
+    Reindent,
+    /// Show the themes available.
+    ListThemes,
+    /// Switch to the next available theme.
+    CycleTheme,
+    /// Add a cursor at the start of the given line (starting at 1),
+    /// keeping the existing ones.
+    AddCursorAtLine(u64),
+    /// Run the last command again.
+    RepeatLast,
+    /// Make a name expand to one or more commands, separated by `;`.
+    DefineAlias(String, String),
+    /// Bind a key sequence to the command written after it.
+    BindKeys(Vec<Key>, String),
+    /// Run several commands in a row, as an alias can expand to.
+    Sequence(Vec<Command>),
 }
 
+impl Command {
+    /// Whether this command is remembered to be run again by
+    /// `RepeatLast`.
+    pub fn is_repeatable(&self) -> bool {
+        !matches!(
+            *self,
+            Command::RepeatLast
+                | Command::Cancel
+                | Command::Escape
+                | Command::RecordMacro(_)
+                | Command::OpenPalette
+                | Command::OpenGotoPrompt
+                | Command::GotoSymbol
+                | Command::ShowKeybindings
+                | Command::Noop
+        )
+    }
+
+    /// Parse the command a key sequence is bound to. If `lenient` is
+    /// set, an unknown command is logged and bound as `Noop` instead
+    /// of failing, so that one binding to a command this frontend
+    /// lacks leaves the other bindings working.
+    pub fn from_keymap_entry(entry: &str, lenient: bool) -> Result<Command, ParseCommandError> {
+        match entry.parse() {
+            Err(ParseCommandError::UnknownCommand(ref name)) if lenient => {
+                warn!("unknown command \"{}\" bound to nothing", name);
+                Ok(Command::Noop)
+            }
+            result => result,
+        }
+    }
+
+    /// The command moving the cursor to `target`, and to `column` if
+    /// given.
+    pub fn goto(target: LineTarget, column: Option<u64>) -> Command {
+        match column {
+            Some(column) => Command::GotoPosition(target, column),
+            None => Command::GotoLine(target),
+        }
+    }
+
+    /// Return the same command, applying to the current view instead
+    /// of the view it originally targeted.
+    pub fn for_current_view(self) -> Command {
+        match self {
+            Command::Save(Some(_)) => Command::Save(None),
+            Command::Reload(Some(_)) => Command::Reload(None),
+            Command::ForceReload(Some(_)) => Command::ForceReload(None),
+            Command::ToggleReadOnly(Some(_)) => Command::ToggleReadOnly(None),
+            Command::ToggleWrap(Some(_)) => Command::ToggleWrap(None),
+            Command::Sequence(commands) => Command::Sequence(
+                commands
+                    .into_iter()
+                    .map(Command::for_current_view)
+                    .collect(),
+            ),
+            cmd => cmd,
+        }
+    }
+}
+
+/// The brackets and quotes `surround` pairs automatically, when
+/// given only one of them.
+const SURROUND_PAIRS: &[(&str, &str)] = &[
+    ("(", ")"),
+    ("[", "]"),
+    ("{", "}"),
+    ("<", ">"),
+    ("\"", "\""),
+    ("'", "'"),
+    ("`", "`"),
+];
+
+/// Return the opening and closing strings `surround` wraps with when
+/// given only `delimiter`: its pair if it is a bracket or a quote, and
+/// else the same string on both sides.
+fn surround_pair(delimiter: &str) -> (String, String) {
+    let pair = SURROUND_PAIRS
+        .iter()
+        .find(|(open, close)| delimiter == *open || delimiter == *close);
+    match pair {
+        Some((open, close)) => ((*open).to_owned(), (*close).to_owned()),
+        None => (delimiter.to_owned(), delimiter.to_owned()),
+    }
+}
+
+/// Comment token used by `comment` when none is given.
+const DEFAULT_COMMENT_TOKEN: &str = "#";
+
+/// Largest tab width accepted by `tabwidth`.
+const MAX_TAB_WIDTH: u16 = 32;
+
 #[derive(Debug)]
 pub enum ParseCommandError {
     /// Didnt expect a command to take an argument.
@@ -57,21 +811,922 @@ pub enum ParseCommandError {
         expected: usize,
         found: usize,
     },
+    /// The given command received an argument it could not parse.
+    InvalidArgument { cmd: String, arg: String },
     /// Invalid input was received.
     UnknownCommand(String),
+    /// `set` was given a key it does not know.
+    UnknownSetting(String),
+    /// An alias expands to itself, directly or through other aliases.
+    RecursiveAlias(String),
+    /// An alias expanding to several commands repeats the last
+    /// command, which would be the alias itself.
+    RepeatInAlias(String),
+}
+
+impl fmt::Display for ParseCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseCommandError::UnexpectedArgument => write!(f, "unexpected argument"),
+            ParseCommandError::ExpectedArgument {
+                ref cmd,
+                expected: 1,
+                ..
+            } => write!(f, "{}: expected an argument", cmd),
+            ParseCommandError::ExpectedArgument {
+                ref cmd,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: expected {} arguments, found {}",
+                cmd, expected, found
+            ),
+            ParseCommandError::TooManyArguments {
+                ref cmd,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: too many arguments (expected {}, found {})",
+                cmd, expected, found
+            ),
+            ParseCommandError::InvalidArgument { ref cmd, ref arg } => {
+                write!(f, "{}: invalid argument \"{}\"", cmd, arg)
+            }
+            ParseCommandError::UnknownCommand(ref cmd) => write!(f, "unknown command \"{}\"", cmd),
+            ParseCommandError::UnknownSetting(ref key) => write!(
+                f,
+                "unknown setting \"{}\" (expected one of {})",
+                key,
+                SETTING_KEYS.join(", ")
+            ),
+            ParseCommandError::RecursiveAlias(ref name) => {
+                write!(f, "alias \"{}\" expands to itself", name)
+            }
+            ParseCommandError::RepeatInAlias(ref name) => write!(
+                f,
+                "alias \"{}\" cannot repeat the last command among others",
+                name
+            ),
+        }
+    }
 }
 
+impl error::Error for ParseCommandError {}
+
+/// A command that can be typed in the prompt, as listed in the command
+/// palette and completed with Tab.
+#[derive(Debug)]
+pub struct CommandInfo {
+    pub name: &'static str,
+    /// Other names of the command, usually shorter.
+    pub aliases: &'static [&'static str],
+    /// The arguments of the command, with optional ones in brackets.
+    pub args: &'static str,
+    pub description: &'static str,
+}
+
+impl CommandInfo {
+    /// Return the entry of `COMMANDS` whose name, typed without
+    /// arguments, runs `cmd`.
+    pub fn for_command(cmd: &Command) -> Option<&'static CommandInfo> {
+        COMMANDS
+            .iter()
+            .find(|info| info.name.parse::<Command>().ok().as_ref() == Some(cmd))
+    }
+}
+
+/// The commands that can be typed in the prompt, by long name, used
+/// for completion and by the command palette. This is kept in sync
+/// with `Command::from_str` by hand.
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "quit",
+        aliases: &["q"],
+        args: "",
+        description: "Quits xi-term, asking first if some buffers have unsaved changes",
+    },
+    CommandInfo {
+        name: "quit!",
+        aliases: &["q!"],
+        args: "",
+        description: "Quits xi-term, discarding unsaved changes",
+    },
+    CommandInfo {
+        name: "palette",
+        aliases: &[],
+        args: "",
+        description: "List all the commands, to pick one to run",
+    },
+    CommandInfo {
+        name: "goto-line",
+        aliases: &[],
+        args: "",
+        description: "Prompt for a line (and :column) to move to, previewing it",
+    },
+    CommandInfo {
+        name: "help",
+        aliases: &["keys"],
+        args: "",
+        description: "List the key bindings with the commands they run",
+    },
+    CommandInfo {
+        name: "noop",
+        aliases: &[],
+        args: "",
+        description: "Do nothing",
+    },
+    CommandInfo {
+        name: "alias",
+        aliases: &[],
+        args: "name command[; command...]",
+        description: "Make name run the given commands",
+    },
+    CommandInfo {
+        name: "bind",
+        aliases: &[],
+        args: "keys = command",
+        description: "Bind a key sequence to a command",
+    },
+    CommandInfo {
+        name: "repeat",
+        aliases: &["."],
+        args: "",
+        description: "Run the last command again",
+    },
+    CommandInfo {
+        name: "save",
+        aliases: &["s"],
+        args: "",
+        description: "Saves the current file",
+    },
+    CommandInfo {
+        name: "save-all",
+        aliases: &["wa"],
+        args: "",
+        description: "Saves all the files",
+    },
+    CommandInfo {
+        name: "save-all-quit",
+        aliases: &["wqa"],
+        args: "",
+        description: "Saves all the files and quits, unless some buffers cannot be saved",
+    },
+    CommandInfo {
+        name: "back",
+        aliases: &["b"],
+        args: "",
+        description: "Delete the previous character and move the cursor one position back",
+    },
+    CommandInfo {
+        name: "delete",
+        aliases: &["d"],
+        args: "",
+        description: "Delete the character under the cursor",
+    },
+    CommandInfo {
+        name: "open",
+        aliases: &["o"],
+        args: "filename",
+        description: "Open filename for editing",
+    },
+    CommandInfo {
+        name: "new",
+        aliases: &["enew"],
+        args: "",
+        description: "Open an empty buffer, to be saved with save-as",
+    },
+    CommandInfo {
+        name: "save-as",
+        aliases: &["saveas"],
+        args: "filename",
+        description: "Save the current buffer to filename, which is then the buffer's file",
+    },
+    CommandInfo {
+        name: "reload",
+        aliases: &["e"],
+        args: "",
+        description: "Reload the current file from disk, asking first if it has unsaved changes",
+    },
+    CommandInfo {
+        name: "reload!",
+        aliases: &["e!"],
+        args: "",
+        description: "Reload the current file from disk, discarding unsaved changes",
+    },
+    CommandInfo {
+        name: "close",
+        aliases: &[],
+        args: "",
+        description: "Close the current buffer, asking first if it has unsaved changes",
+    },
+    CommandInfo {
+        name: "close!",
+        aliases: &[],
+        args: "",
+        description: "Close the current buffer, discarding unsaved changes",
+    },
+    CommandInfo {
+        name: "reopen",
+        aliases: &[],
+        args: "",
+        description: "Open the buffer closed last again, where its cursor was",
+    },
+    CommandInfo {
+        name: "readonly",
+        aliases: &["ro"],
+        args: "",
+        description: "Make the current view read-only, or writable again",
+    },
+    CommandInfo {
+        name: "wrap",
+        aliases: &[],
+        args: "",
+        description: "Wrap long lines at the width of the terminal, or stop wrapping them",
+    },
+    CommandInfo {
+        name: "theme",
+        aliases: &["t"],
+        args: "theme",
+        description: "Set the theme",
+    },
+    CommandInfo {
+        name: "themes",
+        aliases: &[],
+        args: "",
+        description: "List the available themes",
+    },
+    CommandInfo {
+        name: "syntax",
+        aliases: &[],
+        args: "name",
+        description: "Highlight the current view with the given syntax",
+    },
+    CommandInfo {
+        name: "syntaxes",
+        aliases: &[],
+        args: "",
+        description: "List the available syntaxes",
+    },
+    CommandInfo {
+        name: "themecycle",
+        aliases: &[],
+        args: "",
+        description: "Switch to the next available theme",
+    },
+    CommandInfo {
+        name: "next-buffer",
+        aliases: &["bn"],
+        args: "",
+        description: "Switch to the next buffer",
+    },
+    CommandInfo {
+        name: "buffer",
+        aliases: &["buf"],
+        args: "name",
+        description: "Switch to the buffer whose file name contains name, or whose view id is name",
+    },
+    CommandInfo {
+        name: "split",
+        aliases: &[],
+        args: "",
+        description: "Show the current buffer in a second pane, above the current one",
+    },
+    CommandInfo {
+        name: "vsplit",
+        aliases: &[],
+        args: "",
+        description: "Show the current buffer in a second pane, left of the current one",
+    },
+    CommandInfo {
+        name: "next-pane",
+        aliases: &[],
+        args: "",
+        description: "Move the focus to the other pane",
+    },
+    CommandInfo {
+        name: "buffers",
+        aliases: &["ls"],
+        args: "",
+        description: "List the open buffers with their view ids",
+    },
+    CommandInfo {
+        name: "prev-buffer",
+        aliases: &["bp"],
+        args: "",
+        description: "Switch to the previous buffer",
+    },
+    CommandInfo {
+        name: "page-down",
+        aliases: &["pd"],
+        args: "",
+        description: "Advance the current view by one page",
+    },
+    CommandInfo {
+        name: "page-up",
+        aliases: &["pu"],
+        args: "",
+        description: "Move the current view back by one page",
+    },
+    CommandInfo {
+        name: "half-page-down",
+        aliases: &["hpd"],
+        args: "",
+        description: "Move the cursor down by half a page",
+    },
+    CommandInfo {
+        name: "half-page-up",
+        aliases: &["hpu"],
+        args: "",
+        description: "Move the cursor up by half a page",
+    },
+    CommandInfo {
+        name: "center",
+        aliases: &["zz"],
+        args: "",
+        description: "Scroll to put the cursor line in the middle of the view",
+    },
+    CommandInfo {
+        name: "scroll-top",
+        aliases: &["zt"],
+        args: "",
+        description: "Scroll to put the cursor line at the top of the view",
+    },
+    CommandInfo {
+        name: "scroll-bottom",
+        aliases: &["zb"],
+        args: "",
+        description: "Scroll to put the cursor line at the bottom of the view",
+    },
+    CommandInfo {
+        name: "move-left",
+        aliases: &["ml"],
+        args: "",
+        description: "Move the cursor one position left",
+    },
+    CommandInfo {
+        name: "move-right",
+        aliases: &["mr"],
+        args: "",
+        description: "Move the cursor one position right",
+    },
+    CommandInfo {
+        name: "move-up",
+        aliases: &["mu"],
+        args: "",
+        description: "Move the cursor one line up",
+    },
+    CommandInfo {
+        name: "move-down",
+        aliases: &["md"],
+        args: "",
+        description: "Move the cursor one line down",
+    },
+    CommandInfo {
+        name: "move-to",
+        aliases: &[],
+        args: "bol|eol|vbol|veol [extend]",
+        description: "Move the cursor to the start or end of the line or screen row",
+    },
+    CommandInfo {
+        name: "line-numbers",
+        aliases: &["ln"],
+        args: "",
+        description: "Toggle displaying line numbers",
+    },
+    CommandInfo {
+        name: "whitespace",
+        aliases: &[],
+        args: "",
+        description: "Show or hide spaces, tabs and trailing whitespace",
+    },
+    CommandInfo {
+        name: "bell",
+        aliases: &[],
+        args: "off|visual|audible",
+        description: "Choose how commands that can't run are signaled",
+    },
+    CommandInfo {
+        name: "autoreload",
+        aliases: &[],
+        args: "off|prompt|auto",
+        description: "Choose what happens when a file changes on disk",
+    },
+    CommandInfo {
+        name: "buffer-order",
+        aliases: &[],
+        args: "created|mru",
+        description: "Choose the order in which next-buffer and prev-buffer cycle",
+    },
+    CommandInfo {
+        name: "cursor-shape",
+        aliases: &[],
+        args: "insert=shape command=shape",
+        description: "Set the shape of the cursor while editing and in the prompt",
+    },
+    CommandInfo {
+        name: "lineending",
+        aliases: &[],
+        args: "lf|crlf",
+        description: "Convert the line endings of the buffer, and use them for new lines",
+    },
+    CommandInfo {
+        name: "linenumbers",
+        aliases: &[],
+        args: "mode",
+        description: "Set how line numbers are shown: off, absolute, relative or hybrid",
+    },
+    CommandInfo {
+        name: "fold-sections",
+        aliases: &[],
+        args: "",
+        description: "Fold the section under each markdown heading",
+    },
+    CommandInfo {
+        name: "toggle-fold",
+        aliases: &[],
+        args: "",
+        description: "Fold or unfold the lines more indented than the cursor line",
+    },
+    CommandInfo {
+        name: "fold-all",
+        aliases: &[],
+        args: "",
+        description: "Fold every block of more indented lines",
+    },
+    CommandInfo {
+        name: "unfold-all",
+        aliases: &[],
+        args: "",
+        description: "Unfold all the folded lines",
+    },
+    CommandInfo {
+        name: "duplicate-line",
+        aliases: &["dup"],
+        args: "",
+        description: "Duplicate the line under each cursor",
+    },
+    CommandInfo {
+        name: "delete-to-eol",
+        aliases: &[],
+        args: "",
+        description: "Delete from each cursor to the end of its line",
+    },
+    CommandInfo {
+        name: "delete-to-bol",
+        aliases: &[],
+        args: "",
+        description: "Delete from each cursor to the start of its line",
+    },
+    CommandInfo {
+        name: "delete-word",
+        aliases: &[],
+        args: "",
+        description: "Delete from each cursor to the end of the word",
+    },
+    CommandInfo {
+        name: "delete-word-backward",
+        aliases: &[],
+        args: "",
+        description: "Delete from each cursor to the start of the word",
+    },
+    CommandInfo {
+        name: "date",
+        aliases: &[],
+        args: "[format]",
+        description: "Insert the current date and time at each cursor",
+    },
+    CommandInfo {
+        name: "transpose",
+        aliases: &[],
+        args: "",
+        description: "Swap the characters before and under each cursor",
+    },
+    CommandInfo {
+        name: "undo",
+        aliases: &["u"],
+        args: "",
+        description: "Undo the last group of edits",
+    },
+    CommandInfo {
+        name: "redo",
+        aliases: &[],
+        args: "",
+        description: "Redo the last undone group of edits",
+    },
+    CommandInfo {
+        name: "select-line",
+        aliases: &[],
+        args: "",
+        description: "Select the line of each cursor, with its line ending",
+    },
+    CommandInfo {
+        name: "select-word",
+        aliases: &[],
+        args: "",
+        description: "Select the word under each cursor",
+    },
+    CommandInfo {
+        name: "copy-line",
+        aliases: &[],
+        args: "[r]",
+        description: "Copy the lines of each cursor and selection, to register r if given",
+    },
+    CommandInfo {
+        name: "cut-line",
+        aliases: &[],
+        args: "[r]",
+        description: "Cut the lines of each cursor and selection, to register r if given",
+    },
+    CommandInfo {
+        name: "paste",
+        aliases: &[],
+        args: "[r]",
+        description: "Insert the text last copied or cut, or register r, at the cursor",
+    },
+    CommandInfo {
+        name: "system-clipboard",
+        aliases: &[],
+        args: "",
+        description: "Start or stop sharing copied text with the system clipboard",
+    },
+    CommandInfo {
+        name: "comment",
+        aliases: &[],
+        args: "[token]",
+        description: "Comment or uncomment the selected lines with token (# by default)",
+    },
+    CommandInfo {
+        name: "join",
+        aliases: &["J"],
+        args: "",
+        description: "Join the current line with the next one, or the selected lines together",
+    },
+    CommandInfo {
+        name: "sort",
+        aliases: &[],
+        args: "[flags]",
+        description: "Sort the selected lines, or the whole buffer",
+    },
+    CommandInfo {
+        name: "case",
+        aliases: &[],
+        args: "mode",
+        description: "Change the case of the selected letters: toggle, upper or lower",
+    },
+    CommandInfo {
+        name: "surround",
+        aliases: &[],
+        args: "open [close]",
+        description: "Wrap each selection between open and close",
+    },
+    CommandInfo {
+        name: "increment",
+        aliases: &[],
+        args: "[n]",
+        description: "Add n (or 1) to the number under or after the cursor",
+    },
+    CommandInfo {
+        name: "decrement",
+        aliases: &[],
+        args: "[n]",
+        description: "Subtract n (or 1) from the number under or after the cursor",
+    },
+    CommandInfo {
+        name: "trim",
+        aliases: &[],
+        args: "",
+        description: "Remove the trailing spaces and tabs of every line",
+    },
+    CommandInfo {
+        name: "trim-on-save",
+        aliases: &[],
+        args: "",
+        description: "Enable or disable trimming trailing whitespace before saving",
+    },
+    CommandInfo {
+        name: "finalnewline",
+        aliases: &[],
+        args: "on|off",
+        description: "Make saving end the buffer with exactly one newline",
+    },
+    CommandInfo {
+        name: "indent",
+        aliases: &[],
+        args: "",
+        description: "Indent the selected lines (or the current line) by one tab stop",
+    },
+    CommandInfo {
+        name: "unindent",
+        aliases: &[],
+        args: "",
+        description: "Remove up to one tab stop of indentation from the selected lines",
+    },
+    CommandInfo {
+        name: "reindent",
+        aliases: &[],
+        args: "",
+        description: "Write the indentation of the selected lines with tabs or spaces",
+    },
+    CommandInfo {
+        name: "stats",
+        aliases: &["wc"],
+        args: "",
+        description: "Show the number of lines, words and characters of the current file",
+    },
+    CommandInfo {
+        name: "swap-ends",
+        aliases: &[],
+        args: "",
+        description: "Move the cursor to the other end of the selection",
+    },
+    CommandInfo {
+        name: "jumpback",
+        aliases: &[],
+        args: "",
+        description: "Go back to where the cursor was before the last jump",
+    },
+    CommandInfo {
+        name: "last-edit",
+        aliases: &[],
+        args: "",
+        description: "Go back to where the buffer was last edited",
+    },
+    CommandInfo {
+        name: "jumpfwd",
+        aliases: &[],
+        args: "",
+        description: "Go forward in the jump list",
+    },
+    CommandInfo {
+        name: "mark",
+        aliases: &[],
+        args: "c",
+        description: "Remember the cursor position as the mark named c",
+    },
+    CommandInfo {
+        name: "goto",
+        aliases: &[],
+        args: "c",
+        description: "Go to the position of the mark named c",
+    },
+    CommandInfo {
+        name: "goto-definition",
+        aliases: &["def"],
+        args: "",
+        description: "Go to the definition of the symbol under the cursor",
+    },
+    CommandInfo {
+        name: "goto-file",
+        aliases: &["gf"],
+        args: "",
+        description: "Open the file whose name is under the cursor",
+    },
+    CommandInfo {
+        name: "goto-symbol",
+        aliases: &["outline"],
+        args: "",
+        description: "List the symbols of the buffer to go to one",
+    },
+    CommandInfo {
+        name: "record",
+        aliases: &[],
+        args: "c",
+        description: "Start recording the macro named c, or stop recording",
+    },
+    CommandInfo {
+        name: "play",
+        aliases: &[],
+        args: "c [n]",
+        description: "Replay the macro named c, n times (once by default)",
+    },
+    CommandInfo {
+        name: "quickfix",
+        aliases: &["copen"],
+        args: "",
+        description: "Show or hide the list of lines matching the last search",
+    },
+    CommandInfo {
+        name: "next-qf",
+        aliases: &[],
+        args: "",
+        description: "Go to the next line of the quickfix list",
+    },
+    CommandInfo {
+        name: "prev-qf",
+        aliases: &[],
+        args: "",
+        description: "Go to the previous line of the quickfix list",
+    },
+    CommandInfo {
+        name: "brackets",
+        aliases: &["match"],
+        args: "",
+        description: "Move the cursor to the matching bracket",
+    },
+    CommandInfo {
+        name: "indent-next",
+        aliases: &[],
+        args: "[extend]",
+        description: "Move to the next line with the same indentation",
+    },
+    CommandInfo {
+        name: "indent-prev",
+        aliases: &[],
+        args: "[extend]",
+        description: "Move to the previous line with the same indentation",
+    },
+    CommandInfo {
+        name: "find",
+        aliases: &[],
+        args: "[lN] string",
+        description: "Search for string",
+    },
+    CommandInfo {
+        name: "find-next",
+        aliases: &[],
+        args: "",
+        description: "Select the next match of the search",
+    },
+    CommandInfo {
+        name: "find-prev",
+        aliases: &[],
+        args: "",
+        description: "Select the previous match of the search",
+    },
+    CommandInfo {
+        name: "findall",
+        aliases: &[],
+        args: "",
+        description: "Place a cursor at every match of the search",
+    },
+    CommandInfo {
+        name: "grep",
+        aliases: &["findall-buffers"],
+        args: "[lN] string",
+        description: "Search all the open buffers and list the matches",
+    },
+    CommandInfo {
+        name: "timeoutlen",
+        aliases: &[],
+        args: "ms",
+        description: "Set how long to wait for the next key of a key sequence",
+    },
+    CommandInfo {
+        name: "set",
+        aliases: &[],
+        args: "key value",
+        description: "Change a setting: tab_width, wrap, line_numbers, scrolloff, theme, translate_tabs_to_spaces or smart_home",
+    },
+    CommandInfo {
+        name: "tabwidth",
+        aliases: &["tw"],
+        args: "n",
+        description: "Set the tab stops of the current view every n columns",
+    },
+    CommandInfo {
+        name: "expandtabs",
+        aliases: &[],
+        args: "on|off",
+        description: "Make Tab insert spaces instead of a tab character",
+    },
+    CommandInfo {
+        name: "scrolloff",
+        aliases: &[],
+        args: "n",
+        description: "Keep at least n lines visible above and below the cursor",
+    },
+    CommandInfo {
+        name: "addcursor",
+        aliases: &[],
+        args: "line",
+        description: "Add a cursor at the start of line, keeping the existing cursors",
+    },
+    CommandInfo {
+        name: "next-heading",
+        aliases: &[],
+        args: "[level]",
+        description: "Move to the next markdown heading, optionally of the given level",
+    },
+    CommandInfo {
+        name: "prev-heading",
+        aliases: &[],
+        args: "[level]",
+        description: "Move to the previous markdown heading, optionally of the given level",
+    },
+];
+
+/// Parse the optional `extend` argument of a movement command.
+fn parse_extend(cmd: &str, parts: &[&str]) -> Result<bool, ParseCommandError> {
+    match parts {
+        [] => Ok(false),
+        ["extend"] => Ok(true),
+        [arg] => Err(ParseCommandError::InvalidArgument {
+            cmd: cmd.to_owned(),
+            arg: (*arg).to_owned(),
+        }),
+        _ => Err(ParseCommandError::TooManyArguments {
+            cmd: cmd.to_owned(),
+            expected: 1,
+            found: parts.len(),
+        }),
+    }
+}
+
+/// Parse the name of a mark, which is a single character.
+fn parse_mark(cmd: &str, parts: &[&str]) -> Result<char, ParseCommandError> {
+    match parts {
+        [] => Err(ParseCommandError::ExpectedArgument {
+            cmd: cmd.to_owned(),
+            expected: 1,
+            found: 0,
+        }),
+        [arg] => {
+            let mut chars = arg.chars();
+            match (chars.next(), chars.next()) {
+                (Some(name), None) => Ok(name),
+                _ => Err(ParseCommandError::InvalidArgument {
+                    cmd: cmd.to_owned(),
+                    arg: (*arg).to_owned(),
+                }),
+            }
+        }
+        _ => Err(ParseCommandError::TooManyArguments {
+            cmd: cmd.to_owned(),
+            expected: 1,
+            found: parts.len(),
+        }),
+    }
+}
+
+/// Parse the name of a register, a letter from `a` to `z`.
+fn parse_register(cmd: &str, parts: &[&str]) -> Result<char, ParseCommandError> {
+    match parse_mark(cmd, parts)? {
+        name @ 'a'..='z' => Ok(name),
+        _ => Err(ParseCommandError::InvalidArgument {
+            cmd: cmd.to_owned(),
+            arg: parts[0].to_owned(),
+        }),
+    }
+}
+
+/// Parse the arguments of `cursor-shape`, like `insert=bar
+/// command=block`. At least one shape must be given.
+fn parse_cursor_shapes(cmd: &str, parts: &[&str]) -> Result<Command, ParseCommandError> {
+    if parts.is_empty() {
+        return Err(ParseCommandError::ExpectedArgument {
+            cmd: cmd.to_owned(),
+            expected: 1,
+            found: 0,
+        });
+    }
+    let mut insert = None;
+    let mut command = None;
+    for part in parts {
+        let invalid = || ParseCommandError::InvalidArgument {
+            cmd: cmd.to_owned(),
+            arg: (*part).to_owned(),
+        };
+        let (mode, shape) = part.split_once('=').ok_or_else(invalid)?;
+        let shape = shape.parse().map_err(|_| invalid())?;
+        match mode {
+            "insert" => insert = Some(shape),
+            "command" => command = Some(shape),
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(Command::SetCursorShape { insert, command })
+}
+
+/// The commands whose argument is text, which is passed on as typed,
+/// so that it can hold several spaces in a row or start with one. The
+/// arguments of the other commands are separated by any whitespace.
+const TEXT_ARGUMENT_COMMANDS: &[&str] = &["find", "grep", "findall-buffers", "o", "open", "date"];
+
 impl FromStr for Command {
     type Err = ParseCommandError;
 
     fn from_str(s: &str) -> Result<Command, Self::Err> {
-        match &s[..] {
+        match s.trim() {
             "s" | "save" => Ok(Command::Save(None)),
+            "new" | "enew" => Ok(Command::NewBuffer),
             "q" | "quit" => Ok(Command::Quit),
+            "palette" => Ok(Command::OpenPalette),
+            "goto-line" => Ok(Command::OpenGotoPrompt),
+            "help" | "keys" => Ok(Command::ShowKeybindings),
+            "noop" => Ok(Command::Noop),
+            "q!" | "quit!" => Ok(Command::ForceQuit),
+            "wa" | "save-all" => Ok(Command::SaveAll),
+            "wqa" | "save-all-quit" => Ok(Command::SaveAllAndQuit),
+            "." | "repeat" => Ok(Command::RepeatLast),
             "b" | "back" => Ok(Command::Back),
             "d" | "delete" => Ok(Command::Delete),
             "bn" | "next-buffer" => Ok(Command::NextBuffer),
             "bp" | "prev-buffer" => Ok(Command::PrevBuffer),
+            "split" => Ok(Command::SplitView(SplitDirection::Horizontal)),
+            "vsplit" => Ok(Command::SplitView(SplitDirection::Vertical)),
+            "next-pane" => Ok(Command::FocusNextPane),
             "pd" | "page-down" => Ok(Command::PageDown),
             "pu" | "page-up" => Ok(Command::PageUp),
             "ml" | "move-left" => Ok(Command::MoveLeft),
@@ -79,11 +1734,85 @@ impl FromStr for Command {
             "mu" | "move-up" => Ok(Command::MoveUp),
             "md" | "move-down" => Ok(Command::MoveDown),
             "ln" | "line-numbers" => Ok(Command::ToggleLineNumbers),
+            "whitespace" => Ok(Command::ToggleWhitespace),
+            "e" | "reload" => Ok(Command::Reload(None)),
+            "e!" | "reload!" => Ok(Command::ForceReload(None)),
+            "close" => Ok(Command::CloseCurrentView),
+            "close!" => Ok(Command::ForceCloseCurrentView),
+            "reopen" => Ok(Command::ReopenClosed),
+            "ro" | "readonly" => Ok(Command::ToggleReadOnly(None)),
+            "wrap" => Ok(Command::ToggleWrap(None)),
+            "fold-sections" => Ok(Command::FoldAllSections),
+            "toggle-fold" => Ok(Command::ToggleFold),
+            "fold-all" => Ok(Command::FoldAll),
+            "unfold-all" => Ok(Command::UnfoldAll),
+            "dup" | "duplicate-line" => Ok(Command::DuplicateLine),
+            "ls" | "buffers" => Ok(Command::ListBuffers),
+            "delete-to-eol" => Ok(Command::DeleteToEol),
+            "delete-to-bol" => Ok(Command::DeleteToBol),
+            "delete-word" => Ok(Command::DeleteWord { forward: true }),
+            "delete-word-backward" => Ok(Command::DeleteWord { forward: false }),
+            "transpose" => Ok(Command::TransposeChars),
+            "u" | "undo" => Ok(Command::Undo),
+            "redo" => Ok(Command::Redo),
+            "J" | "join" => Ok(Command::JoinLines),
+            "sort" => Ok(Command::SortLines(SortConfig::default())),
+            "def" | "goto-definition" => Ok(Command::GotoDefinition),
+            "outline" | "goto-symbol" => Ok(Command::GotoSymbol),
+            "gf" | "goto-file" => Ok(Command::OpenFileUnderCursor),
+            "select-line" => Ok(Command::SelectLine),
+            "select-word" => Ok(Command::SelectWord),
+            "copy-line" => Ok(Command::CopyLine),
+            "cut-line" => Ok(Command::CutLine),
+            "paste" => Ok(Command::Paste),
+            "system-clipboard" => Ok(Command::ToggleSystemClipboard),
+            "trim" => Ok(Command::TrimTrailingWhitespace),
+            "trim-on-save" => Ok(Command::ToggleTrimOnSave),
+            "indent" => Ok(Command::Indent),
+            "unindent" => Ok(Command::Unindent),
+            "reindent" => Ok(Command::Reindent),
+            "wc" | "stats" => Ok(Command::BufferStats),
+            "swap-ends" => Ok(Command::SwapSelectionEnds),
+            "jumpback" => Ok(Command::JumpBack),
+            "last-edit" => Ok(Command::GotoLastEdit),
+            "jumpfwd" => Ok(Command::JumpForward),
+            "zz" | "center" => Ok(Command::ScrollCenter),
+            "zt" | "scroll-top" => Ok(Command::ScrollTop),
+            "zb" | "scroll-bottom" => Ok(Command::ScrollBottom),
+            "find-next" => Ok(Command::FindNext),
+            "find-prev" => Ok(Command::FindPrev),
+            "findall" => Ok(Command::FindAll),
+            "copen" | "quickfix" => Ok(Command::ToggleQuickfix),
+            "next-qf" => Ok(Command::QuickfixNext),
+            "prev-qf" => Ok(Command::QuickfixPrev),
+            "themes" => Ok(Command::ListThemes),
+            "syntaxes" => Ok(Command::ListSyntaxes),
+            "themecycle" => Ok(Command::CycleTheme),
             command => {
-                let mut parts: Vec<&str> = command.split(' ').collect();
-
-                let cmd = parts.remove(0);
+                let (cmd, args) = match command.find(char::is_whitespace) {
+                    Some(idx) => (&command[..idx], command[idx + 1..].trim_start()),
+                    None => (command, ""),
+                };
+                let parts: Vec<&str> = if !TEXT_ARGUMENT_COMMANDS.contains(&cmd) {
+                    args.split_whitespace().collect()
+                } else if args.is_empty() {
+                    Vec::new()
+                } else {
+                    args.split(' ').collect()
+                };
                 match cmd {
+                    "syntax" => {
+                        if parts.is_empty() {
+                            Err(ParseCommandError::ExpectedArgument {
+                                cmd: cmd.to_owned(),
+                                expected: 1,
+                                found: 0,
+                            })
+                        } else {
+                            // Names like "Plain Text" have spaces
+                            Ok(Command::SetSyntax(parts.join(" ")))
+                        }
+                    }
                     "t" | "theme" => {
                         if parts.is_empty() {
                             Err(ParseCommandError::ExpectedArgument {
@@ -101,18 +1830,663 @@ impl FromStr for Command {
                             Ok(Command::SetTheme(parts[0].to_owned()))
                         }
                     }
-                    "o" | "open" => {
+                    "date" => {
                         if parts.is_empty() {
+                            Ok(Command::InsertDateTime(None))
+                        } else {
+                            let format = parts.join(" ");
+                            if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+                                Err(ParseCommandError::InvalidArgument {
+                                    cmd: cmd.to_owned(),
+                                    arg: format,
+                                })
+                            } else {
+                                Ok(Command::InsertDateTime(Some(format)))
+                            }
+                        }
+                    }
+                    "alias" => match parts.as_slice() {
+                        [] | [_] => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 2,
+                            found: parts.len(),
+                        }),
+                        [name, expansion @ ..] => Ok(Command::DefineAlias(
+                            (*name).to_owned(),
+                            expansion.join(" "),
+                        )),
+                    },
+                    "bind" => match parts.iter().skip(1).position(|part| *part == "=") {
+                        Some(idx) if idx + 2 < parts.len() => {
+                            let keys = parts[..=idx].join(" ");
+                            match parse_keys(&keys) {
+                                Some(keys) => {
+                                    Ok(Command::BindKeys(keys, parts[idx + 2..].join(" ")))
+                                }
+                                None => Err(ParseCommandError::InvalidArgument {
+                                    cmd: cmd.to_owned(),
+                                    arg: keys,
+                                }),
+                            }
+                        }
+                        _ => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 3,
+                            found: parts.len(),
+                        }),
+                    },
+                    "saveas" | "save-as" => match parts.as_slice() {
+                        [] => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: 0,
+                        }),
+                        [path] => Ok(Command::SaveAs((*path).to_owned())),
+                        _ => Err(ParseCommandError::TooManyArguments {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: parts.len(),
+                        }),
+                    },
+                    // The path is all the rest, spaces included
+                    "o" | "open" => {
+                        if args.is_empty() {
                             Ok(Command::Open(None))
+                        } else {
+                            Ok(Command::Open(Some(args.to_owned())))
+                        }
+                    }
+                    "brackets" | "match" => parse_extend(cmd, &parts)
+                        .map(|extend| Command::GotoMatchingBracket { extend }),
+                    "indent-next" => {
+                        parse_extend(cmd, &parts).map(|extend| Command::MoveToMatchingIndent {
+                            forward: true,
+                            extend,
+                        })
+                    }
+                    "indent-prev" => {
+                        parse_extend(cmd, &parts).map(|extend| Command::MoveToMatchingIndent {
+                            forward: false,
+                            extend,
+                        })
+                    }
+                    "hpd" | "half-page-down" => {
+                        parse_extend(cmd, &parts).map(|extend| Command::HalfPageDown { extend })
+                    }
+                    "hpu" | "half-page-up" => {
+                        parse_extend(cmd, &parts).map(|extend| Command::HalfPageUp { extend })
+                    }
+                    "copy-line" => parse_register(cmd, &parts).map(Command::CopyToRegister),
+                    "cut-line" => parse_register(cmd, &parts).map(Command::CutToRegister),
+                    "paste" => parse_register(cmd, &parts).map(Command::PasteRegister),
+                    "mark" => parse_mark(cmd, &parts).map(Command::SetMark),
+                    "goto" => parse_mark(cmd, &parts).map(Command::GotoMark),
+                    "record" => parse_mark(cmd, &parts).map(Command::RecordMacro),
+                    "play" => match parts.as_slice() {
+                        [] | [_] => parse_mark(cmd, &parts)
+                            .map(|name| Command::PlayMacro { name, count: 1 }),
+                        [name, count] => {
+                            let name = parse_mark(cmd, &[*name])?;
+                            count
+                                .parse::<u32>()
+                                .map(|count| Command::PlayMacro { name, count })
+                                .map_err(|_| ParseCommandError::InvalidArgument {
+                                    cmd: cmd.to_owned(),
+                                    arg: (*count).to_owned(),
+                                })
+                        }
+                        _ => Err(ParseCommandError::TooManyArguments {
+                            cmd: cmd.to_owned(),
+                            expected: 2,
+                            found: parts.len(),
+                        }),
+                    },
+                    "comment" => {
+                        if parts.len() > 1 {
+                            Err(ParseCommandError::TooManyArguments {
+                                cmd: cmd.to_owned(),
+                                expected: 1,
+                                found: parts.len(),
+                            })
+                        } else {
+                            let token = parts.first().unwrap_or(&DEFAULT_COMMENT_TOKEN);
+                            Ok(Command::ToggleComment((*token).to_owned()))
+                        }
+                    }
+                    "increment" | "decrement" => match parts.as_slice() {
+                        [] | [_] => {
+                            let delta = match parts.first() {
+                                Some(n) => n.parse::<i64>().map_err(|_| {
+                                    ParseCommandError::InvalidArgument {
+                                        cmd: cmd.to_owned(),
+                                        arg: (*n).to_owned(),
+                                    }
+                                })?,
+                                None => 1,
+                            };
+                            if cmd == "decrement" {
+                                Ok(Command::IncrementNumber(-delta))
+                            } else {
+                                Ok(Command::IncrementNumber(delta))
+                            }
+                        }
+                        _ => Err(ParseCommandError::TooManyArguments {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: parts.len(),
+                        }),
+                    },
+                    "surround" => match parts.as_slice() {
+                        [] => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: 0,
+                        }),
+                        [delimiter] => {
+                            let (open, close) = surround_pair(delimiter);
+                            Ok(Command::SurroundSelection(open, close))
+                        }
+                        [open, close] => Ok(Command::SurroundSelection(
+                            (*open).to_owned(),
+                            (*close).to_owned(),
+                        )),
+                        _ => Err(ParseCommandError::TooManyArguments {
+                            cmd: cmd.to_owned(),
+                            expected: 2,
+                            found: parts.len(),
+                        }),
+                    },
+                    "case" => match parts.as_slice() {
+                        ["toggle"] => Ok(Command::ToggleCase),
+                        ["upper"] => Ok(Command::UpperCase),
+                        ["lower"] => Ok(Command::LowerCase),
+                        [] => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: 0,
+                        }),
+                        [case] => Err(ParseCommandError::InvalidArgument {
+                            cmd: cmd.to_owned(),
+                            arg: (*case).to_owned(),
+                        }),
+                        _ => Err(ParseCommandError::TooManyArguments {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: parts.len(),
+                        }),
+                    },
+                    "sort" => match parts.as_slice() {
+                        [flags] => SortConfig::from_flags(flags)
+                            .map(Command::SortLines)
+                            .map_err(|_| ParseCommandError::InvalidArgument {
+                                cmd: cmd.to_owned(),
+                                arg: (*flags).to_owned(),
+                            }),
+                        _ => Err(ParseCommandError::TooManyArguments {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: parts.len(),
+                        }),
+                    },
+                    "buf" | "buffer" => {
+                        if parts.is_empty() {
+                            Err(ParseCommandError::ExpectedArgument {
+                                cmd: cmd.to_owned(),
+                                expected: 1,
+                                found: 0,
+                            })
+                        } else {
+                            Ok(Command::SwitchBuffer(parts.join(" ")))
+                        }
+                    }
+                    "lineending" => match parts.as_slice() {
+                        [] => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: 0,
+                        }),
+                        [ending] => ending.parse().map(Command::SetLineEnding).map_err(|_| {
+                            ParseCommandError::InvalidArgument {
+                                cmd: cmd.to_owned(),
+                                arg: (*ending).to_owned(),
+                            }
+                        }),
+                        _ => Err(ParseCommandError::TooManyArguments {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: parts.len(),
+                        }),
+                    },
+                    "bell" => match parts.as_slice() {
+                        [] => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: 0,
+                        }),
+                        [mode] => mode.parse().map(Command::SetBell).map_err(|_| {
+                            ParseCommandError::InvalidArgument {
+                                cmd: cmd.to_owned(),
+                                arg: (*mode).to_owned(),
+                            }
+                        }),
+                        _ => Err(ParseCommandError::TooManyArguments {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: parts.len(),
+                        }),
+                    },
+                    "autoreload" => match parts.as_slice() {
+                        [] => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: 0,
+                        }),
+                        [mode] => mode.parse().map(Command::SetAutoReload).map_err(|_| {
+                            ParseCommandError::InvalidArgument {
+                                cmd: cmd.to_owned(),
+                                arg: (*mode).to_owned(),
+                            }
+                        }),
+                        _ => Err(ParseCommandError::TooManyArguments {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: parts.len(),
+                        }),
+                    },
+                    "buffer-order" => match parts.as_slice() {
+                        [] => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: 0,
+                        }),
+                        [order] => order
+                            .parse()
+                            .map(Command::SetBufferCycleOrder)
+                            .map_err(|_| ParseCommandError::InvalidArgument {
+                                cmd: cmd.to_owned(),
+                                arg: (*order).to_owned(),
+                            }),
+                        _ => Err(ParseCommandError::TooManyArguments {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: parts.len(),
+                        }),
+                    },
+                    "cursor-shape" => parse_cursor_shapes(cmd, &parts),
+                    "move-to" => match parts.as_slice() {
+                        [] => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: 0,
+                        }),
+                        [point, rest @ ..] => {
+                            let point =
+                                point
+                                    .parse()
+                                    .map_err(|_| ParseCommandError::InvalidArgument {
+                                        cmd: cmd.to_owned(),
+                                        arg: (*point).to_owned(),
+                                    })?;
+                            parse_extend(cmd, rest).map(|extend| Command::MoveTo { point, extend })
+                        }
+                    },
+                    "linenumbers" => match parts.as_slice() {
+                        [] => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: 0,
+                        }),
+                        [mode] => mode.parse().map(Command::SetLineNumberMode).map_err(|_| {
+                            ParseCommandError::InvalidArgument {
+                                cmd: cmd.to_owned(),
+                                arg: (*mode).to_owned(),
+                            }
+                        }),
+                        _ => Err(ParseCommandError::TooManyArguments {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: parts.len(),
+                        }),
+                    },
+                    "find" => {
+                        if parts.is_empty() {
+                            Err(ParseCommandError::ExpectedArgument {
+                                cmd: cmd.to_owned(),
+                                expected: 1,
+                                found: 0,
+                            })
+                        } else {
+                            Ok(Command::Find(FindConfig::from_args(&parts)))
+                        }
+                    }
+                    "grep" | "findall-buffers" => {
+                        if parts.is_empty() {
+                            Err(ParseCommandError::ExpectedArgument {
+                                cmd: cmd.to_owned(),
+                                expected: 1,
+                                found: 0,
+                            })
+                        } else {
+                            Ok(Command::FindInFiles(FindConfig::from_args(&parts)))
+                        }
+                    }
+                    "timeoutlen" => {
+                        if parts.is_empty() {
+                            Err(ParseCommandError::ExpectedArgument {
+                                cmd: cmd.to_owned(),
+                                expected: 1,
+                                found: 0,
+                            })
                         } else if parts.len() > 1 {
-                            Err(ParseCommandError::UnexpectedArgument)
+                            Err(ParseCommandError::TooManyArguments {
+                                cmd: cmd.to_owned(),
+                                expected: 1,
+                                found: parts.len(),
+                            })
                         } else {
-                            Ok(Command::Open(Some(parts[0].to_owned())))
+                            match parts[0].parse::<u64>() {
+                                Ok(timeout) => Ok(Command::SetKeyTimeout(timeout)),
+                                Err(_) => Err(ParseCommandError::InvalidArgument {
+                                    cmd: cmd.to_owned(),
+                                    arg: parts[0].to_owned(),
+                                }),
+                            }
                         }
                     }
-                    _ => Err(ParseCommandError::UnknownCommand(command.into())),
+                    "addcursor" => {
+                        if parts.is_empty() {
+                            Err(ParseCommandError::ExpectedArgument {
+                                cmd: cmd.to_owned(),
+                                expected: 1,
+                                found: 0,
+                            })
+                        } else if parts.len() > 1 {
+                            Err(ParseCommandError::TooManyArguments {
+                                cmd: cmd.to_owned(),
+                                expected: 1,
+                                found: parts.len(),
+                            })
+                        } else {
+                            match parts[0].parse::<u64>() {
+                                Ok(line) => Ok(Command::AddCursorAtLine(line)),
+                                Err(_) => Err(ParseCommandError::InvalidArgument {
+                                    cmd: cmd.to_owned(),
+                                    arg: parts[0].to_owned(),
+                                }),
+                            }
+                        }
+                    }
+                    "set" => match parts.as_slice() {
+                        [] | [_] => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 2,
+                            found: parts.len(),
+                        }),
+                        [key, value @ ..] => {
+                            Setting::parse(key, &value.join(" ")).map(Command::Set)
+                        }
+                    },
+                    "scrolloff" | "tw" | "tabwidth" | "expandtabs" => {
+                        let key = match cmd {
+                            "scrolloff" => "scrolloff",
+                            "expandtabs" => "translate_tabs_to_spaces",
+                            _ => "tab_width",
+                        };
+                        match parts.as_slice() {
+                            [] => Err(ParseCommandError::ExpectedArgument {
+                                cmd: cmd.to_owned(),
+                                expected: 1,
+                                found: 0,
+                            }),
+                            [value] => Setting::parse(key, value).map(Command::Set).map_err(|_| {
+                                ParseCommandError::InvalidArgument {
+                                    cmd: cmd.to_owned(),
+                                    arg: (*value).to_owned(),
+                                }
+                            }),
+                            _ => Err(ParseCommandError::TooManyArguments {
+                                cmd: cmd.to_owned(),
+                                expected: 1,
+                                found: parts.len(),
+                            }),
+                        }
+                    }
+                    "finalnewline" => match parts.as_slice() {
+                        [] => Err(ParseCommandError::ExpectedArgument {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: 0,
+                        }),
+                        [value] => {
+                            parse_bool(value)
+                                .map(Command::SetFinalNewline)
+                                .ok_or_else(|| ParseCommandError::InvalidArgument {
+                                    cmd: cmd.to_owned(),
+                                    arg: (*value).to_owned(),
+                                })
+                        }
+                        _ => Err(ParseCommandError::TooManyArguments {
+                            cmd: cmd.to_owned(),
+                            expected: 1,
+                            found: parts.len(),
+                        }),
+                    },
+                    "next-heading" | "prev-heading" => {
+                        let forward = cmd == "next-heading";
+                        if parts.is_empty() {
+                            Ok(Command::MarkdownHeadingMove {
+                                forward,
+                                level: None,
+                            })
+                        } else if parts.len() > 1 {
+                            Err(ParseCommandError::TooManyArguments {
+                                cmd: cmd.to_owned(),
+                                expected: 1,
+                                found: parts.len(),
+                            })
+                        } else {
+                            match parts[0].parse::<u8>() {
+                                Ok(level) if (1..=6).contains(&level) => {
+                                    Ok(Command::MarkdownHeadingMove {
+                                        forward,
+                                        level: Some(level),
+                                    })
+                                }
+                                _ => Err(ParseCommandError::InvalidArgument {
+                                    cmd: cmd.to_owned(),
+                                    arg: parts[0].to_owned(),
+                                }),
+                            }
+                        }
+                    }
+                    // A command that takes no argument was given some
+                    _ if !parts.is_empty() && Command::from_str(cmd).is_ok() => {
+                        Err(ParseCommandError::UnexpectedArgument)
+                    }
+                    // A line number, like `42`, `+10` or `$`, and maybe
+                    // a column, like `42:7`
+                    _ => match LineTarget::parse_position(command) {
+                        Some((target, column)) => Ok(Command::goto(target, column)),
+                        None => Err(ParseCommandError::UnknownCommand(command.into())),
+                    },
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lenient_keymap_entries_ignore_unknown_commands() {
+        assert_eq!(
+            Command::from_keymap_entry("frobnicate", true).unwrap(),
+            Command::Noop
+        );
+        assert!(matches!(
+            Command::from_keymap_entry("frobnicate", false),
+            Err(ParseCommandError::UnknownCommand(_))
+        ));
+        // Only unknown commands are ignored
+        assert!(Command::from_keymap_entry("page-down 2", true).is_err());
+        assert_eq!(
+            Command::from_keymap_entry("save", false).unwrap(),
+            Command::Save(None)
+        );
+    }
+
+    #[test]
+    fn ignores_extra_spaces_around_commands() {
+        assert_eq!("  save  ".parse::<Command>().unwrap(), Command::Save(None));
+        assert_eq!(
+            "  move-to   eol  ".parse::<Command>().unwrap(),
+            Command::MoveTo {
+                point: MovePoint::Eol,
+                extend: false,
+            }
+        );
+        assert_eq!(
+            "open  foo.rs ".parse::<Command>().unwrap(),
+            Command::Open(Some("foo.rs".into()))
+        );
+    }
+
+    #[test]
+    fn keeps_the_spaces_inside_text_arguments() {
+        assert_eq!(
+            "open my  notes.txt".parse::<Command>().unwrap(),
+            Command::Open(Some("my  notes.txt".into()))
+        );
+        assert_eq!(
+            "find  a  b".parse::<Command>().unwrap(),
+            Command::Find(FindConfig {
+                term: "a  b".into(),
+                max_results: None,
+            })
+        );
+    }
+
+    #[test]
+    fn set_describes_every_setting() {
+        let set = COMMANDS.iter().find(|info| info.name == "set").unwrap();
+        for key in SETTING_KEYS {
+            assert!(set.description.contains(key), "{} is not described", key);
+        }
+    }
+
+    #[test]
+    fn rejects_arguments_to_commands_taking_none() {
+        for input in &["save foo", "quit now", "undo 2"] {
+            assert!(matches!(
+                input.parse::<Command>(),
+                Err(ParseCommandError::UnexpectedArgument)
+            ));
+        }
+        assert!(matches!(
+            "frobnicate foo".parse::<Command>(),
+            Err(ParseCommandError::UnknownCommand(_))
+        ));
+    }
+
+    #[test]
+    fn counts_missing_arguments() {
+        assert!(matches!(
+            "saveas".parse::<Command>(),
+            Err(ParseCommandError::ExpectedArgument {
+                expected: 1,
+                found: 0,
+                ..
+            })
+        ));
+        assert!(matches!(
+            "alias wq".parse::<Command>(),
+            Err(ParseCommandError::ExpectedArgument {
+                expected: 2,
+                found: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            "case".parse::<Command>(),
+            Err(ParseCommandError::ExpectedArgument {
+                expected: 1,
+                found: 0,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn counts_extra_arguments() {
+        assert!(matches!(
+            "saveas a.txt b.txt".parse::<Command>(),
+            Err(ParseCommandError::TooManyArguments {
+                expected: 1,
+                found: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            "case upper lower".parse::<Command>(),
+            Err(ParseCommandError::TooManyArguments {
+                expected: 1,
+                found: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            "sort r u".parse::<Command>(),
+            Err(ParseCommandError::TooManyArguments {
+                expected: 1,
+                found: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn parses_bindings() {
+        assert_eq!(
+            "bind ^x k = cut-line".parse::<Command>().unwrap(),
+            Command::BindKeys(vec![Key::Ctrl('x'), Key::Char('k')], "cut-line".into())
+        );
+        assert_eq!(
+            "bind = = find-next".parse::<Command>().unwrap(),
+            Command::BindKeys(vec![Key::Char('=')], "find-next".into())
+        );
+        assert!(matches!(
+            "bind ^x k cut-line".parse::<Command>(),
+            Err(ParseCommandError::ExpectedArgument { .. })
+        ));
+        assert!(matches!(
+            "bind Hyper-k = cut-line".parse::<Command>(),
+            Err(ParseCommandError::InvalidArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn setting_shorthands_are_settings() {
+        assert_eq!(
+            "tabwidth 4".parse::<Command>().unwrap(),
+            Command::Set(Setting::TabWidth(4))
+        );
+        assert_eq!(
+            "expandtabs on".parse::<Command>().unwrap(),
+            "set translate_tabs_to_spaces on"
+                .parse::<Command>()
+                .unwrap()
+        );
+        assert_eq!(
+            "scrolloff 3".parse::<Command>().unwrap(),
+            Command::Set(Setting::ScrollOff(3))
+        );
+        match "tw 0".parse::<Command>() {
+            Err(ParseCommandError::InvalidArgument { cmd, arg }) => {
+                assert_eq!((cmd.as_str(), arg.as_str()), ("tw", "0"))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
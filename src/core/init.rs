@@ -0,0 +1,79 @@
+//! The init file: commands run at startup, one per line, written as
+//! they would be typed in the command prompt.
+
+use std::path::PathBuf;
+
+use xdg::BaseDirectories;
+
+use core::{Aliases, Command, KeyBindings};
+
+/// Return the path of the init file, `$XDG_CONFIG_HOME/xi-tui/init`,
+/// if it exists.
+pub fn init_file_path() -> Option<PathBuf> {
+    BaseDirectories::with_prefix("xi-tui")
+        .ok()?
+        .find_config_file("init")
+}
+
+/// Parse the commands of an init file. Blank lines and lines starting
+/// with `#` are skipped. The lines that can't be parsed are skipped
+/// too, and described in the returned errors with their line number.
+///
+/// The aliases are defined in `aliases` as they are parsed, so that the
+/// lines after them can use them, and the key sequences are bound in
+/// `bindings`. A key sequence bound to an unknown command is bound to
+/// nothing, rather than reported.
+pub fn parse_init(
+    text: &str,
+    aliases: &mut Aliases,
+    bindings: &mut KeyBindings,
+) -> (Vec<Command>, Vec<String>) {
+    let mut commands = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match aliases.parse(line) {
+            Ok(Command::DefineAlias(name, expansion)) => {
+                if let Err(e) = aliases.define(&name, &expansion) {
+                    errors.push(format!("line {}: {}", idx + 1, e));
+                }
+            }
+            Ok(Command::BindKeys(keys, entry)) => match Command::from_keymap_entry(&entry, true) {
+                Ok(cmd) => bindings.bind(keys, cmd),
+                Err(e) => errors.push(format!("line {}: {}", idx + 1, e)),
+            },
+            Ok(cmd) => commands.push(cmd),
+            Err(e) => errors.push(format!("line {}: {}", idx + 1, e)),
+        }
+    }
+    (commands, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use termion::event::Key;
+
+    use core::Lookup;
+
+    #[test]
+    fn binds_unknown_commands_to_nothing() {
+        let mut aliases = Aliases::default();
+        let mut bindings = KeyBindings::default();
+        let text = "bind ^x k = frobnicate\nbind ^x j = cut-line\nfrobnicate\n";
+        let (commands, errors) = parse_init(text, &mut aliases, &mut bindings);
+        assert!(commands.is_empty());
+        assert_eq!(errors, vec!["line 3: unknown command \"frobnicate\""]);
+        assert!(matches!(
+            bindings.lookup(&[Key::Ctrl('x'), Key::Char('k')]),
+            Lookup::Command(Command::Noop)
+        ));
+        assert!(matches!(
+            bindings.lookup(&[Key::Ctrl('x'), Key::Char('j')]),
+            Lookup::Command(Command::CutLine)
+        ));
+    }
+}
@@ -0,0 +1,324 @@
+//! Multi-key bindings, like emacs' `^x ^s`. When a key that starts a
+//! sequence is pressed, the following keys are buffered until they
+//! either form a bound sequence, or the sequence times out.
+
+use std::time::{Duration, Instant};
+
+use termion::event::Key;
+
+use core::{Command, SplitDirection};
+
+/// Default time to wait for the next key of a sequence, in
+/// milliseconds.
+pub const DEFAULT_KEY_TIMEOUT: u64 = 1000;
+
+/// The keys of a sequence that is not complete yet.
+#[derive(Debug)]
+pub struct PendingKeys {
+    keys: Vec<Key>,
+    /// When the last key of the sequence was pressed.
+    last_input: Instant,
+    /// The longest bound sequence among the keys typed so far, if it
+    /// is also the beginning of a longer one, as its length and its
+    /// command.
+    matched: Option<(usize, Command)>,
+}
+
+impl PendingKeys {
+    pub fn new(key: Key) -> Self {
+        PendingKeys {
+            keys: vec![key],
+            last_input: Instant::now(),
+            matched: None,
+        }
+    }
+
+    /// Remember that the keys typed so far are bound to `command`, in
+    /// case the longer sequence is not completed.
+    pub fn set_match(&mut self, command: Command) {
+        self.matched = Some((self.keys.len(), command));
+    }
+
+    /// Return the longest bound sequence typed so far, as its length
+    /// and its command.
+    pub fn matched(&self) -> Option<&(usize, Command)> {
+        self.matched.as_ref()
+    }
+
+    pub fn push(&mut self, key: Key) {
+        self.keys.push(key);
+        self.last_input = Instant::now();
+    }
+
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    /// Whether the sequence is still waiting for its next key at the
+    /// time `now`, or if it timed out.
+    pub fn is_active(&self, now: Instant, timeout: Duration) -> bool {
+        now < self.last_input + timeout
+    }
+
+    /// How long the sequence keeps waiting for its next key after
+    /// `now`.
+    pub fn remaining(&self, now: Instant, timeout: Duration) -> Duration {
+        (self.last_input + timeout)
+            .checked_duration_since(now)
+            .unwrap_or_else(|| Duration::from_secs(0))
+    }
+}
+
+/// The result of looking up a key sequence in the bindings.
+pub enum Lookup {
+    /// The sequence is bound to a command.
+    Command(Command),
+    /// The sequence is bound to a command, but is also the beginning
+    /// of a longer binding.
+    Ambiguous(Command),
+    /// The sequence is the beginning of one or more bindings.
+    Prefix,
+    /// The sequence is not bound.
+    Unbound,
+}
+
+pub struct KeyBindings(Vec<(Vec<Key>, Command)>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings(vec![
+            (vec![Key::Ctrl('x'), Key::Ctrl('s')], Command::Save(None)),
+            (vec![Key::Ctrl('x'), Key::Ctrl('c')], Command::Quit),
+            (vec![Key::Ctrl('x'), Key::Char('.')], Command::RepeatLast),
+            (vec![Key::Ctrl('x'), Key::Char('u')], Command::Undo),
+            (vec![Key::Ctrl('x'), Key::Ctrl('p')], Command::OpenPalette),
+            (
+                vec![Key::Ctrl('x'), Key::Char('?')],
+                Command::ShowKeybindings,
+            ),
+            (
+                vec![Key::Ctrl('x'), Key::Char('g')],
+                Command::OpenGotoPrompt,
+            ),
+            (vec![Key::Ctrl('x'), Key::Char('i')], Command::GotoSymbol),
+            (
+                vec![Key::Ctrl('x'), Key::Ctrl('f')],
+                Command::OpenFileUnderCursor,
+            ),
+            (vec![Key::Ctrl('x'), Key::Char('n')], Command::NextBuffer),
+            (vec![Key::Ctrl('x'), Key::Char('p')], Command::PrevBuffer),
+            (
+                vec![Key::Ctrl('x'), Key::Char('2')],
+                Command::SplitView(SplitDirection::Horizontal),
+            ),
+            (
+                vec![Key::Ctrl('x'), Key::Char('3')],
+                Command::SplitView(SplitDirection::Vertical),
+            ),
+            (vec![Key::Ctrl('x'), Key::Char('o')], Command::FocusNextPane),
+            (vec![Key::Ctrl('x'), Key::Char('a')], Command::FindAll),
+            (vec![Key::Ctrl('x'), Key::Char('e')], Command::GotoLastEdit),
+            (vec![Key::Ctrl('x'), Key::Char('l')], Command::ScrollCenter),
+            (
+                vec![Key::Ctrl('x'), Key::Char('w')],
+                Command::ToggleWhitespace,
+            ),
+            (
+                vec![Key::Ctrl('x'), Key::Char(']')],
+                Command::MoveToMatchingIndent {
+                    forward: true,
+                    extend: false,
+                },
+            ),
+            (
+                vec![Key::Ctrl('x'), Key::Char('[')],
+                Command::MoveToMatchingIndent {
+                    forward: false,
+                    extend: false,
+                },
+            ),
+            (
+                vec![Key::Ctrl('x'), Key::Char(';')],
+                Command::ToggleComment("#".into()),
+            ),
+            (vec![Key::Ctrl('x'), Key::Char('f')], Command::ToggleFold),
+            (
+                vec![Key::Ctrl('x'), Key::Char('+')],
+                Command::IncrementNumber(1),
+            ),
+            (
+                vec![Key::Ctrl('x'), Key::Char('-')],
+                Command::IncrementNumber(-1),
+            ),
+            (vec![Key::Ctrl('x'), Key::Char('>')], Command::Indent),
+            (vec![Key::Ctrl('x'), Key::Char('<')], Command::Unindent),
+            (
+                vec![Key::Ctrl('x'), Key::Char('%')],
+                Command::GotoMatchingBracket { extend: false },
+            ),
+        ])
+    }
+}
+
+impl KeyBindings {
+    /// Return the bound sequences with their command, in the order
+    /// they were bound.
+    pub fn entries(&self) -> &[(Vec<Key>, Command)] {
+        &self.0
+    }
+
+    /// Return the first key sequence bound to `command`, if any.
+    pub fn keys_for(&self, command: &Command) -> Option<&[Key]> {
+        self.0
+            .iter()
+            .find(|(_, bound)| bound == command)
+            .map(|(keys, _)| keys.as_slice())
+    }
+
+    /// Bind `keys` to `command`, replacing the command they were bound
+    /// to if any.
+    pub fn bind(&mut self, keys: Vec<Key>, command: Command) {
+        match self.0.iter_mut().find(|(bound, _)| *bound == keys) {
+            Some(binding) => binding.1 = command,
+            None => self.0.push((keys, command)),
+        }
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        let mut is_prefix = false;
+        let mut bound = None;
+        for (sequence, command) in &self.0 {
+            if sequence.as_slice() == keys {
+                bound = Some(command.clone());
+            }
+            if sequence.len() > keys.len() && sequence.starts_with(keys) {
+                is_prefix = true;
+            }
+        }
+        match (bound, is_prefix) {
+            (Some(command), false) => Lookup::Command(command),
+            (Some(command), true) => Lookup::Ambiguous(command),
+            (None, true) => Lookup::Prefix,
+            (None, false) => Lookup::Unbound,
+        }
+    }
+}
+
+/// Return a short human readable representation of a key sequence,
+/// such as `^x ^s`.
+pub fn describe_keys(keys: &[Key]) -> String {
+    keys.iter()
+        .map(|key| match *key {
+            Key::Ctrl(c) => format!("^{}", c),
+            Key::Alt(c) => format!("M-{}", c),
+            Key::Char(c) => c.to_string(),
+            ref key => format!("{:?}", key),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Parse a key sequence written like `describe_keys` shows it, such as
+/// `^x ^s`. Return `None` if one of the keys is not recognized.
+pub fn parse_keys(s: &str) -> Option<Vec<Key>> {
+    s.split_whitespace().map(parse_key).collect()
+}
+
+fn parse_key(s: &str) -> Option<Key> {
+    let chars: Vec<char> = s.chars().collect();
+    let key = match *chars.as_slice() {
+        [c] => Key::Char(c),
+        ['^', c] => Key::Ctrl(c),
+        ['M', '-', c] => Key::Alt(c),
+        _ => match s {
+            "Backspace" => Key::Backspace,
+            "Left" => Key::Left,
+            "Right" => Key::Right,
+            "Up" => Key::Up,
+            "Down" => Key::Down,
+            "Home" => Key::Home,
+            "End" => Key::End,
+            "PageUp" => Key::PageUp,
+            "PageDown" => Key::PageDown,
+            "Delete" => Key::Delete,
+            "Insert" => Key::Insert,
+            "Esc" => Key::Esc,
+            // Function keys are shown as F(1), but F1 is easier to type
+            _ if s.starts_with('F') => {
+                Key::F(s[1..].trim_matches(|c| c == '(' || c == ')').parse().ok()?)
+            }
+            _ => return None,
+        },
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_described_keys() {
+        let keys = vec![
+            Key::Ctrl('x'),
+            Key::Alt('f'),
+            Key::Char('%'),
+            Key::PageDown,
+            Key::F(5),
+        ];
+        assert_eq!(parse_keys(&describe_keys(&keys)), Some(keys));
+        assert_eq!(parse_keys("F12"), Some(vec![Key::F(12)]));
+        assert_eq!(parse_keys("^x Hyper"), None);
+    }
+
+    #[test]
+    fn pending_keys_time_out() {
+        let timeout = Duration::from_millis(DEFAULT_KEY_TIMEOUT);
+        let pending = PendingKeys::new(Key::Ctrl('x'));
+        let now = Instant::now();
+        assert!(pending.is_active(now, timeout));
+        assert!(pending.remaining(now, timeout) <= timeout);
+        assert!(!pending.is_active(now + timeout, timeout));
+        assert_eq!(
+            pending.remaining(now + timeout * 2, timeout),
+            Duration::from_secs(0)
+        );
+    }
+
+    #[test]
+    fn pushed_keys_extend_the_sequence() {
+        let timeout = Duration::from_millis(DEFAULT_KEY_TIMEOUT);
+        let mut pending = PendingKeys::new(Key::Ctrl('x'));
+        pending.push(Key::Char('u'));
+        let now = Instant::now();
+        assert_eq!(pending.keys(), &[Key::Ctrl('x'), Key::Char('u')][..]);
+        assert!(pending.is_active(now, timeout));
+        assert!(!pending.is_active(now + timeout, timeout));
+    }
+
+    #[test]
+    fn a_prefix_waits_for_the_next_key() {
+        let bindings = KeyBindings::default();
+        assert!(matches!(bindings.lookup(&[Key::Ctrl('x')]), Lookup::Prefix));
+        assert!(matches!(
+            bindings.lookup(&[Key::Ctrl('x'), Key::Ctrl('s')]),
+            Lookup::Command(Command::Save(None))
+        ));
+        assert!(matches!(
+            bindings.lookup(&[Key::Ctrl('q')]),
+            Lookup::Unbound
+        ));
+    }
+
+    #[test]
+    fn bind_replaces_the_previous_command() {
+        let mut bindings = KeyBindings::default();
+        let keys = vec![Key::Ctrl('x'), Key::Char('u')];
+        bindings.bind(keys.clone(), Command::Redo);
+        assert!(matches!(
+            bindings.lookup(&keys),
+            Lookup::Command(Command::Redo)
+        ));
+        assert_eq!(bindings.keys_for(&Command::Undo), None);
+    }
+}
@@ -0,0 +1,147 @@
+//! Parsing of file locations such as `src/main.rs:120:5`, as accepted
+//! by most editors and printed by most compilers.
+
+use std::path::Path;
+
+/// A path, with an optional position in the file. Line and column
+/// start at 1, like in the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileLocation {
+    pub path: String,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+}
+
+impl FileLocation {
+    /// Split a trailing `:<line>` or `:<line>:<column>` suffix from
+    /// `input`. If there's no such suffix, the whole input is taken as
+    /// the path, even if it contains colons.
+    pub fn parse(input: &str) -> FileLocation {
+        let mut parts = input.rsplitn(3, ':');
+        let last = parts.next().and_then(|s| s.parse::<u64>().ok());
+        let second = parts.next();
+        let first = parts.next();
+
+        // The path must not be empty, so that a file named `:12` is
+        // still opened as is.
+        let location = match (first, second, last) {
+            (Some(path), Some(line), Some(column)) if !path.is_empty() => {
+                match line.parse::<u64>() {
+                    Ok(line) => Some((path.to_owned(), Some(line), Some(column))),
+                    Err(_) => Some((format!("{}:{}", path, line), Some(column), None)),
+                }
+            }
+            (None, Some(path), Some(line)) if !path.is_empty() => {
+                Some((path.to_owned(), Some(line), None))
+            }
+            _ => None,
+        };
+
+        match location {
+            Some((path, line, column)) => FileLocation { path, line, column },
+            None => FileLocation {
+                path: input.to_owned(),
+                line: None,
+                column: None,
+            },
+        }
+    }
+}
+
+/// Resolve a relative `path` against the directory of `current_file`.
+/// Absolute paths, paths starting with `~`, and paths given when there
+/// is no current file are returned unchanged, and thus resolved
+/// against the working directory.
+pub fn resolve_path(current_file: Option<&str>, path: &str) -> String {
+    if Path::new(path).is_absolute() || path.starts_with('~') {
+        return path.to_owned();
+    }
+    match current_file.and_then(|file| Path::new(file).parent()) {
+        Some(dir) if dir != Path::new("") => dir.join(path).to_string_lossy().into_owned(),
+        _ => path.to_owned(),
+    }
+}
+
+/// Whether `c` can be part of a file location found in text.
+fn is_location_char(c: char) -> bool {
+    c.is_alphanumeric() || "/._-+~:@%#=".contains(c)
+}
+
+/// Return the file location around byte `column` of `line`, like Vim's
+/// `gf`: the longest run of characters that can be part of a path, with
+/// the punctuation that usually follows a path in prose removed from
+/// its end. Return `None` if there is no such run there.
+pub fn location_at(line: &str, column: usize) -> Option<&str> {
+    let column = column.min(line.len());
+    if !line.is_char_boundary(column) {
+        return None;
+    }
+    let start = line[..column]
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !is_location_char(c))
+        .map_or(0, |(idx, c)| idx + c.len_utf8());
+    let end = line[column..]
+        .find(|c| !is_location_char(c))
+        .map_or(line.len(), |idx| column + idx);
+    let location = line[start..end].trim_end_matches(&['.', ',', ':', ';'][..]);
+    if location.is_empty() {
+        None
+    } else {
+        Some(location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_relative_paths_next_to_the_current_file() {
+        assert_eq!(
+            resolve_path(Some("/src/xi/main.rs"), "lib.rs"),
+            "/src/xi/lib.rs"
+        );
+        assert_eq!(
+            resolve_path(Some("src/main.rs"), "core/mod.rs"),
+            "src/core/mod.rs"
+        );
+    }
+
+    #[test]
+    fn keeps_parent_components_for_the_system_to_resolve() {
+        assert_eq!(
+            resolve_path(Some("/src/xi/main.rs"), "../README.md"),
+            "/src/xi/../README.md"
+        );
+    }
+
+    #[test]
+    fn keeps_absolute_and_home_paths() {
+        assert_eq!(
+            resolve_path(Some("/src/xi/main.rs"), "/etc/hosts"),
+            "/etc/hosts"
+        );
+        assert_eq!(resolve_path(Some("/src/xi/main.rs"), "~/notes"), "~/notes");
+    }
+
+    #[test]
+    fn uses_the_working_directory_without_a_current_directory() {
+        assert_eq!(resolve_path(None, "lib.rs"), "lib.rs");
+        assert_eq!(resolve_path(Some("main.rs"), "lib.rs"), "lib.rs");
+    }
+
+    #[test]
+    fn finds_the_location_around_the_column() {
+        let line = "see src/main.rs:120:5, or the README.";
+        assert_eq!(location_at(line, 8), Some("src/main.rs:120:5"));
+        assert_eq!(location_at(line, 32), Some("README"));
+        assert_eq!(location_at("  ", 1), None);
+    }
+
+    #[test]
+    fn variables_are_not_part_of_a_location() {
+        // They are not expanded, so the file would not be found
+        assert_eq!(location_at("cat $HOME/notes", 6), Some("HOME/notes"));
+    }
+}
@@ -0,0 +1,60 @@
+//! Recording and replaying sequences of commands.
+
+use std::collections::HashMap;
+
+use termion::event::Event;
+
+use core::Command;
+
+/// Something done while a macro was recorded.
+#[derive(Debug, Clone)]
+pub enum MacroStep {
+    /// A command, once resolved from the key bindings or the prompt.
+    Command(Command),
+    /// A key or mouse event sent to the current view as is, such as a
+    /// typed character.
+    Input(Event),
+    /// Text pasted in the terminal.
+    Paste(String),
+}
+
+#[derive(Debug, Default)]
+pub struct Macros {
+    /// The recorded macros, by name.
+    registers: HashMap<char, Vec<MacroStep>>,
+    /// The name and the steps of the macro being recorded, if any.
+    recording: Option<(char, Vec<MacroStep>)>,
+}
+
+impl Macros {
+    /// Return the name of the macro being recorded, if any.
+    pub fn recording(&self) -> Option<char> {
+        self.recording.as_ref().map(|&(name, _)| name)
+    }
+
+    /// Start recording the macro `name`. The steps it had are only
+    /// replaced when the recording stops.
+    pub fn start(&mut self, name: char) {
+        self.recording = Some((name, Vec::new()));
+    }
+
+    /// Stop recording, and return the name of the recorded macro and
+    /// its number of steps.
+    pub fn stop(&mut self) -> Option<(char, usize)> {
+        let (name, steps) = self.recording.take()?;
+        let len = steps.len();
+        self.registers.insert(name, steps);
+        Some((name, len))
+    }
+
+    /// Add a step to the macro being recorded, if any.
+    pub fn record(&mut self, step: MacroStep) {
+        if let Some((_, ref mut steps)) = self.recording {
+            steps.push(step);
+        }
+    }
+
+    pub fn get(&self, name: char) -> Option<&Vec<MacroStep>> {
+        self.registers.get(&name)
+    }
+}
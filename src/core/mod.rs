@@ -5,4 +5,29 @@ mod tui;
 pub use self::tui::{CoreEvent, Tui, TuiService, TuiServiceBuilder};
 
 mod cmd;
-pub use self::cmd::{Command, ParseCommandError};
+pub use self::cmd::{
+    AutoReload, BellMode, BufferCycleOrder, Command, CommandInfo, CursorShape, FindConfig,
+    LineEnding, LineNumberMode, LineTarget, MovePoint, ParseCommandError, Setting, SortConfig,
+    SplitDirection, COMMANDS,
+};
+
+mod macros;
+pub use self::macros::{MacroStep, Macros};
+
+mod location;
+pub use self::location::{location_at, resolve_path, FileLocation};
+
+mod keys;
+pub use self::keys::{describe_keys, parse_keys, KeyBindings, Lookup, PendingKeys, DEFAULT_KEY_TIMEOUT};
+
+mod aliases;
+pub use self::aliases::Aliases;
+
+mod init;
+pub use self::init::{init_file_path, parse_init};
+
+mod watcher;
+pub use self::watcher::FileWatcher;
+
+mod clipboard;
+pub use self::clipboard::{copy_to_system_clipboard, read_system_clipboard};
@@ -1,102 +1,198 @@
-use std::io::{self, Stdout};
-use std::thread::{sleep, spawn};
-use std::time::Duration;
+use std::io::{self, Stdout, Write};
+use std::thread::spawn;
 
 use futures::sync::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
-use futures::{Async, Poll, Sink, Stream};
+use futures::{stream, Async, Future, Poll, Sink, Stream};
 
 use failure::{Error, ResultExt};
 
 use termion::event::Event;
 use termion::input::MouseTerminal;
-use termion::input::TermRead;
+use termion::input::TermReadEventsAndRaw;
 use termion::raw::{IntoRawMode, RawTerminal};
 use termion::screen::AlternateScreen;
 use termion::terminal_size;
+use tokio_signal::unix::Signal;
 
-/// Simple type alias for the Write implementer we render to.
-pub type RenderTarget = MouseTerminal<AlternateScreen<RawTerminal<Stdout>>>;
+use core::CursorShape;
+
+/// Simple type alias for the Write implementer we render to. It is
+/// the real terminal, or any writer given to `Terminal::with_writer`.
+pub type RenderTarget = Box<dyn Write + Send>;
+
+/// The real terminal, in raw mode, on the alternate screen, and
+/// reporting mouse events.
+type TerminalOutput = MouseTerminal<AlternateScreen<RawTerminal<Stdout>>>;
+
+/// Escape sequences to enable and disable bracketed paste mode. In this
+/// mode, the terminal wraps pasted text between `PASTE_START` and
+/// `PASTE_END`.
+const ENABLE_BRACKETED_PASTE: &str = "\x1b[?2004h";
+const DISABLE_BRACKETED_PASTE: &str = "\x1b[?2004l";
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// The escape sequence (DECSCUSR) setting the shape of the cursor.
+/// Terminals that don't know it ignore it.
+fn cursor_shape_escape(shape: CursorShape) -> &'static str {
+    match shape {
+        CursorShape::Default => "\x1b[0 q",
+        CursorShape::Block => "\x1b[2 q",
+        CursorShape::Underline => "\x1b[4 q",
+        CursorShape::Bar => "\x1b[6 q",
+    }
+}
+
+/// A stream that yields each time the terminal may have been resized.
+type ResizeStream = Box<dyn Stream<Item = (), Error = ()> + Send>;
 
 pub struct Terminal {
-    size: UnboundedReceiver<(u16, u16)>,
-    stdin: UnboundedReceiver<Event>,
+    /// Fires on SIGWINCH, and once at startup to get the initial size.
+    resizes: ResizeStream,
+    size: (u16, u16),
+    stdin: UnboundedReceiver<TerminalEvent>,
     stdout: RenderTarget,
+    /// Whether `stdout` is the real terminal, whose modes must be
+    /// restored on exit.
+    is_tty: bool,
+    /// The shape the cursor was last given, restored to the default on
+    /// exit.
+    cursor_shape: CursorShape,
 }
 
 impl Terminal {
     pub fn new() -> Result<Self, Error> {
         let (stdin_tx, stdin_rx) = unbounded();
-        let (size_tx, size_rx) = unbounded();
-        let stdout = MouseTerminal::from(AlternateScreen::from(
+        let mut stdout: TerminalOutput = MouseTerminal::from(AlternateScreen::from(
             io::stdout()
                 .into_raw_mode()
                 .context("Failed to put terminal into raw mode")?,
         ));
+        write!(stdout, "{}", ENABLE_BRACKETED_PASTE)
+            .context("Failed to enable bracketed paste mode")?;
 
         let term = Terminal {
             stdin: stdin_rx,
-            size: size_rx,
-            stdout,
+            resizes: Terminal::resizes(),
+            size: (0, 0),
+            stdout: Box::new(stdout),
+            is_tty: true,
+            cursor_shape: CursorShape::Default,
         };
 
         Terminal::start_stdin_listening(stdin_tx);
-        Terminal::start_size_listening(size_tx);
         Ok(term)
     }
 
-    fn start_stdin_listening(tx: UnboundedSender<Event>) {
-        let mut tx = tx;
-        spawn(move || {
-            info!("waiting for input events");
-            for event_res in io::stdin().events() {
-                match event_res {
-                    // TODO: at least log the errors
-                    Ok(event) => {
-                        let _ = tx.start_send(event).unwrap();
-                        let _ = tx.poll_complete().unwrap();
-                    }
-                    Err(e) => error!("{}", e),
-                }
-            }
-            info!("stop waiting for input events");
-        });
+    /// Create a terminal rendering to `writer` instead of the real
+    /// terminal, for running the editor headless. The terminal neither
+    /// reads stdin nor listens for resizes: its events, starting with
+    /// its size, are the ones sent on the returned sender. Only tests
+    /// use it, the binary always uses the real terminal.
+    #[cfg(test)]
+    pub fn with_writer<W: Write + Send + 'static>(
+        writer: W,
+    ) -> (Self, UnboundedSender<TerminalEvent>) {
+        let (events_tx, events_rx) = unbounded();
+        let term = Terminal {
+            stdin: events_rx,
+            resizes: Box::new(stream::empty()),
+            size: (0, 0),
+            stdout: Box::new(writer),
+            is_tty: false,
+            cursor_shape: CursorShape::Default,
+        };
+        (term, events_tx)
     }
 
-    fn start_size_listening(tx: UnboundedSender<(u16, u16)>) {
+    fn resizes() -> ResizeStream {
+        let sigwinch = Signal::new(libc::SIGWINCH)
+            .flatten_stream()
+            .map(|_| ())
+            .map_err(|e| error!("failed to listen for resize signals: {}", e));
+        Box::new(stream::once(Ok(())).chain(sigwinch))
+    }
+
+    fn start_stdin_listening(tx: UnboundedSender<TerminalEvent>) {
         let mut tx = tx;
         spawn(move || {
-            let mut current_size = (0, 0);
-            info!("waiting for resize events");
-            loop {
-                match terminal_size() {
-                    Ok(new_size) => {
-                        if new_size != current_size {
-                            info!(
-                                "terminal resized (from {:?} to {:?})",
-                                current_size, new_size
-                            );
-                            current_size = new_size;
-                            let _ = tx.start_send(current_size).unwrap();
-                            let _ = tx.poll_complete().unwrap();
-                        }
-                    }
+            info!("waiting for input events");
+            // The bytes pasted so far, if we're in the middle of a paste
+            let mut paste: Option<Vec<u8>> = None;
+            for event_res in io::stdin().events_and_raw() {
+                let (event, raw) = match event_res {
+                    Ok(event) => event,
                     Err(e) => {
-                        error!("failed to get terminal size: {}", e);
+                        error!("{}", e);
+                        continue;
                     }
-                }
-                sleep(Duration::from_millis(10));
+                };
+                let event = match (paste.take(), event) {
+                    (None, Event::Unsupported(ref seq)) if seq.as_slice() == PASTE_START => {
+                        paste = Some(Vec::new());
+                        continue;
+                    }
+                    (None, event) => TerminalEvent::Input(event),
+                    (Some(bytes), Event::Unsupported(ref seq)) if seq.as_slice() == PASTE_END => {
+                        TerminalEvent::Paste(pasted_text(&bytes))
+                    }
+                    (Some(mut bytes), _) => {
+                        bytes.extend(raw);
+                        paste = Some(bytes);
+                        continue;
+                    }
+                };
+                let _ = tx.start_send(event).unwrap();
+                let _ = tx.poll_complete().unwrap();
             }
+            info!("stop waiting for input events");
         });
     }
 
     pub fn stdout(&mut self) -> &mut RenderTarget {
         &mut self.stdout
     }
+
+    /// Give the cursor `shape`, if it doesn't have it already.
+    pub fn set_cursor_shape(&mut self, shape: CursorShape) {
+        if shape == self.cursor_shape {
+            return;
+        }
+        match write!(self.stdout, "{}", cursor_shape_escape(shape)) {
+            Ok(()) => self.cursor_shape = shape,
+            Err(e) => error!("failed to set the cursor shape: {}", e),
+        }
+    }
 }
 
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        self.set_cursor_shape(CursorShape::Default);
+        if !self.is_tty {
+            return;
+        }
+        if let Err(e) = write!(self.stdout, "{}", DISABLE_BRACKETED_PASTE) {
+            error!("failed to disable bracketed paste mode: {}", e);
+        }
+    }
+}
+
+/// Turn the bytes pasted in the terminal back into text. termion
+/// would parse both `\r` and `\n` as an Enter key press, so the raw
+/// bytes are used instead, with `\r\n` and lone `\r` line endings made
+/// `\n`: each pasted line ending then inserts a single new line.
+fn pasted_text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+}
+
+#[derive(Debug)]
 pub enum TerminalEvent {
     Resize((u16, u16)),
     Input(Event),
+    /// Text pasted in the terminal.
+    Paste(String),
 }
 
 impl Stream for Terminal {
@@ -105,27 +201,33 @@ impl Stream for Terminal {
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
         debug!("polling for terminal size events");
-        match self.size.poll() {
-            Ok(Async::Ready(Some(size))) => {
-                debug!("size event: {:?}", size);
-                let event = TerminalEvent::Resize(size);
-                return Ok(Async::Ready(Some(event)));
-            }
-            Ok(Async::Ready(None)) => {
-                warn!("terminal size sender closed the channel");
-                return Ok(Async::Ready(None));
-            }
-            Ok(Async::NotReady) => {
-                debug!("done polling for terminal size events");
+        loop {
+            match self.resizes.poll() {
+                Ok(Async::Ready(Some(()))) => match terminal_size() {
+                    Ok(size) if size != self.size => {
+                        info!("terminal resized (from {:?} to {:?})", self.size, size);
+                        self.size = size;
+                        return Ok(Async::Ready(Some(TerminalEvent::Resize(size))));
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("failed to get terminal size: {}", e),
+                },
+                Ok(Async::Ready(None)) | Err(()) => {
+                    warn!("not listening for resize signals anymore");
+                    self.resizes = Box::new(stream::empty());
+                    break;
+                }
+                Ok(Async::NotReady) => {
+                    debug!("done polling for terminal size events");
+                    break;
+                }
             }
-            Err(()) => return Err(()),
         }
 
         debug!("polling for stdin events");
         match self.stdin.poll() {
             Ok(Async::Ready(Some(event))) => {
                 debug!("stdin event: {:?}", event);
-                let event = TerminalEvent::Input(event);
                 return Ok(Async::Ready(Some(event)));
             }
             Ok(Async::Ready(None)) => {
@@ -141,3 +243,22 @@ impl Stream for Terminal {
         Ok(Async::NotReady)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pasted_line_endings_make_single_new_lines() {
+        assert_eq!(
+            pasted_text(b"fn main() {\r\n    x();\r\n}\r\n"),
+            "fn main() {\n    x();\n}\n"
+        );
+        assert_eq!(pasted_text(b"a\rb\nc\n\nd"), "a\nb\nc\n\nd");
+    }
+
+    #[test]
+    fn pasted_text_is_kept_as_is() {
+        assert_eq!(pasted_text(b"\tx\x7f \xc3\xa9"), "\tx\x7f \u{e9}");
+    }
+}
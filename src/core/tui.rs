@@ -1,16 +1,31 @@
+use std::fs;
 use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 use futures::sync::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use futures::sync::oneshot::{self, Receiver, Sender};
 use futures::{Async, Future, Poll, Sink, Stream};
 
+use termion::cursor::{Goto, Restore, Save};
 use termion::event::{Event, Key};
+use tokio::timer::Interval;
 use xrl::{Client, Frontend, FrontendBuilder, MeasureWidth, XiNotification};
 
 use failure::Error;
 
-use core::{Command, Terminal, TerminalEvent};
-use widgets::{CommandPrompt, Editor};
+use core::{
+    describe_keys, parse_init, Aliases, Command, CursorShape, FindConfig, KeyBindings, LineTarget,
+    Lookup, MacroStep, Macros, PendingKeys, Terminal, TerminalEvent, DEFAULT_KEY_TIMEOUT,
+};
+use widgets::{
+    CommandPrompt, Commands, Confirm, Editor, FilePicker, FilePickerEvent, Help, ListEvent,
+    Outline, Palette, Symbols,
+};
+
+/// The largest count that can be typed before a key, so that a typo
+/// does not freeze the editor.
+const MAX_COUNT: u32 = 10_000;
 
 pub struct Tui {
     /// The editor holds the text buffers (named "views" in xi
@@ -20,6 +35,31 @@ pub struct Tui {
     /// The command prompt is where users can type commands.
     prompt: Option<CommandPrompt>,
 
+    /// The command palette, listing the commands to pick one.
+    palette: Option<Palette>,
+
+    /// The file picker, listing the entries of the directory given to
+    /// `open`.
+    picker: Option<FilePicker>,
+
+    /// The question asked before running a command that discards
+    /// changes.
+    confirm: Option<Confirm>,
+
+    /// The symbols of the current view, listed to move to one of them.
+    outline: Option<Outline>,
+
+    /// The key bindings, listed until a key is pressed.
+    help: Option<Help>,
+
+    /// The `find` command being typed in the prompt, as last previewed
+    /// in the current view.
+    search_preview: Option<FindConfig>,
+
+    /// The position typed in the goto prompt, as last previewed in the
+    /// current view.
+    goto_preview: Option<(LineTarget, Option<u64>)>,
+
     /// The terminal is used to draw on the screen a get inputs from
     /// the user.
     terminal: Terminal,
@@ -32,19 +72,84 @@ pub struct Tui {
 
     /// Stream of messages from Xi core.
     core_events: UnboundedReceiver<CoreEvent>,
+
+    /// The multi-key bindings.
+    bindings: KeyBindings,
+
+    /// The keys typed so far of an incomplete key sequence.
+    pending_keys: Option<PendingKeys>,
+
+    /// How long to wait for the next key of a key sequence.
+    key_timeout: Duration,
+
+    /// Wakes the TUI up while a key sequence is pending, so that its
+    /// countdown is refreshed and it times out.
+    key_timer: Option<Interval>,
+
+    /// The last command that was run, to be repeated by
+    /// `Command::RepeatLast`.
+    last_command: Option<Command>,
+
+    /// How many times to repeat the next key, typed with Alt and
+    /// digits before it.
+    count: Option<u32>,
+
+    /// The macros recorded with `Command::RecordMacro`.
+    macros: Macros,
+
+    /// The commands of the init file, waiting for the first view to be
+    /// open.
+    init_commands: Vec<Command>,
+
+    /// The aliases defined with `Command::DefineAlias`.
+    aliases: Aliases,
+
+    /// The shape of the cursor while editing text.
+    insert_cursor: CursorShape,
+
+    /// The shape of the cursor while typing in the prompt or in a list.
+    command_cursor: CursorShape,
 }
 
 impl Tui {
     /// Create a new Tui instance.
     pub fn new(client: Client, events: UnboundedReceiver<CoreEvent>) -> Result<Self, Error> {
-        Ok(Tui {
-            terminal: Terminal::new()?,
+        Ok(Tui::with_terminal(client, events, Terminal::new()?))
+    }
+
+    /// Create a Tui drawing on `terminal`, which can render to any
+    /// writer (see `Terminal::with_writer`).
+    pub fn with_terminal(
+        client: Client,
+        events: UnboundedReceiver<CoreEvent>,
+        terminal: Terminal,
+    ) -> Self {
+        Tui {
+            terminal,
             exit: false,
             term_size: (0, 0),
             editor: Editor::new(client),
             prompt: None,
+            palette: None,
+            picker: None,
+            confirm: None,
+            outline: None,
+            help: None,
+            search_preview: None,
+            goto_preview: None,
             core_events: events,
-        })
+            bindings: KeyBindings::default(),
+            pending_keys: None,
+            key_timeout: Duration::from_millis(DEFAULT_KEY_TIMEOUT),
+            key_timer: None,
+            last_command: None,
+            count: None,
+            macros: Macros::default(),
+            init_commands: Vec::new(),
+            aliases: Aliases::default(),
+            insert_cursor: CursorShape::default(),
+            command_cursor: CursorShape::default(),
+        }
     }
 
     fn handle_resize(&mut self, size: (u16, u16)) {
@@ -52,34 +157,422 @@ impl Tui {
         self.editor.handle_resize(size);
     }
 
+    /// Run the commands of the init file at `path`, once the first view
+    /// is open so that they apply to it. The lines that can't be parsed
+    /// are reported on the status line.
+    pub fn run_init_file(&mut self, path: &Path) {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                error!("failed to read {}: {}", path.display(), e);
+                self.editor
+                    .set_status(format!("failed to read {}: {}", path.display(), e));
+                return;
+            }
+        };
+        let (commands, errors) = parse_init(&text, &mut self.aliases, &mut self.bindings);
+        self.init_commands = commands;
+        if !errors.is_empty() {
+            for e in &errors {
+                error!("{}: {}", path.display(), e);
+            }
+            self.editor
+                .set_status(format!("{}: {}", path.display(), errors.join(", ")));
+        }
+    }
+
     pub fn run_command(&mut self, cmd: Command) {
+        if cmd.is_repeatable() {
+            self.last_command = Some(cmd.clone());
+        }
+        match cmd {
+            // The commands these run are recorded instead
+            Command::RepeatLast
+            | Command::RecordMacro(_)
+            | Command::PlayMacro { .. }
+            | Command::OpenPalette
+            | Command::OpenGotoPrompt
+            | Command::GotoSymbol
+            | Command::ShowKeybindings
+            | Command::Sequence(_)
+            | Command::Noop => {}
+            _ => self.macros.record(MacroStep::Command(cmd.clone())),
+        }
         match cmd {
+            Command::RepeatLast => match self.last_command.clone() {
+                Some(cmd) => self.run_command(cmd.for_current_view()),
+                None => self.editor.set_status("no command to repeat"),
+            },
             Command::Cancel => {
                 self.prompt = None;
+                self.cancel_preview();
+            }
+            Command::Noop => {}
+            Command::OpenPalette => {
+                self.prompt = None;
+                self.cancel_preview();
+                let bindings = &self.bindings;
+                let commands = Commands::new(|cmd| {
+                    let cmd = cmd.name.parse::<Command>().ok()?;
+                    bindings.keys_for(&cmd).map(describe_keys)
+                });
+                self.palette = Some(Palette::new(commands));
+            }
+            Command::ShowKeybindings => {
+                self.prompt = None;
+                self.cancel_preview();
+                self.help = Some(Help::new(&self.bindings));
+            }
+            Command::OpenGotoPrompt => {
+                self.count = None;
+                self.cancel_preview();
+                self.prompt = Some(CommandPrompt::goto());
+            }
+            Command::Escape => {
+                self.count = None;
+                if self.confirm.is_some() {
+                    self.confirm = None;
+                } else if self.help.is_some() {
+                    self.help = None;
+                } else if self.outline.is_some() {
+                    self.outline = None;
+                } else if self.palette.is_some() {
+                    self.palette = None;
+                } else if self.picker.is_some() {
+                    self.picker = None;
+                } else if self.prompt.take().is_some() {
+                    self.cancel_preview();
+                } else if !self.editor.escape() {
+                    self.editor.clear_search_highlight();
+                }
+            }
+            Command::Quit => {
+                let dirty = self.editor.dirty_buffers();
+                if dirty.is_empty() {
+                    self.exit = true;
+                } else {
+                    let question = format!("unsaved changes in {}, quit anyway?", dirty.join(", "));
+                    self.confirm = Some(Confirm::new(question, Command::ForceQuit));
+                }
+            }
+            Command::ForceQuit => self.exit = true,
+            Command::DefineAlias(name, expansion) => {
+                if let Err(e) = self.aliases.define(&name, &expansion) {
+                    self.editor.set_status(e.to_string());
+                }
+            }
+            Command::BindKeys(keys, entry) => match Command::from_keymap_entry(&entry, false) {
+                Ok(cmd) => self.bindings.bind(keys, cmd),
+                Err(e) => self.editor.set_status(e.to_string()),
+            },
+            Command::Sequence(commands) => {
+                let sequence = Command::Sequence(commands.clone());
+                for cmd in commands {
+                    self.run_command(cmd);
+                }
+                // Repeat the whole sequence rather than its last command
+                self.last_command = Some(sequence);
+            }
+            Command::RecordMacro(name) => match self.macros.stop() {
+                Some((name, len)) => self
+                    .editor
+                    .set_status(format!("recorded macro '{}' ({} step(s))", name, len)),
+                None => {
+                    self.macros.start(name);
+                    self.editor
+                        .set_status(format!("recording macro '{}'", name));
+                }
+            },
+            Command::PlayMacro { name, count } => {
+                let steps = match self.macros.get(name) {
+                    Some(steps) => steps.clone(),
+                    None => {
+                        self.editor
+                            .set_status(format!("macro '{}' is not recorded", name));
+                        return;
+                    }
+                };
+                for _ in 0..count.min(MAX_COUNT) {
+                    for step in &steps {
+                        self.play_step(step.clone());
+                    }
+                }
             }
-            Command::Quit => self.exit = true,
             Command::Save(view) => self.editor.save(view),
+            Command::SaveAll => {
+                self.editor.save_all();
+            }
+            Command::SaveAllAndQuit => {
+                if self.editor.save_all() {
+                    self.exit = true;
+                }
+            }
             Command::Back => self.editor.back(),
             Command::Delete => self.editor.delete(),
-            Command::Open(file) => self.editor.new_view(file),
+            Command::Open(Some(ref path))
+                if FilePicker::is_directory(self.editor.current_file().as_deref(), path) =>
+            {
+                self.prompt = None;
+                self.picker = Some(FilePicker::new(self.editor.current_file(), path));
+            }
+            Command::Open(file) => self.editor.open(file),
+            Command::GotoLine(target) => self.editor.goto_line(target),
+            Command::GotoPosition(target, column) => self.editor.goto_position(target, column),
+            Command::NewBuffer => self.editor.new_view(None),
+            Command::SaveAs(path) => self.editor.save_as(&path),
+            Command::Reload(view) if self.editor.is_dirty(view) => {
+                let question = "the buffer has unsaved changes, reload it anyway?";
+                self.confirm = Some(Confirm::new(question, Command::ForceReload(view)));
+            }
+            Command::Reload(view) => self.editor.reload(view, false),
+            Command::ForceReload(view) => self.editor.reload(view, true),
+            Command::CloseCurrentView if self.editor.is_dirty(None) => {
+                let question = "the buffer has unsaved changes, close it anyway?";
+                self.confirm = Some(Confirm::new(question, Command::ForceCloseCurrentView));
+            }
+            Command::CloseCurrentView => self.editor.close_current_view(false),
+            Command::ForceCloseCurrentView => self.editor.close_current_view(true),
+            Command::ReopenClosed => self.editor.reopen_closed(),
+            Command::ToggleReadOnly(view) => self.editor.toggle_read_only(view),
+            Command::ToggleWrap(view) => self.editor.toggle_wrap(view),
             Command::SetTheme(theme) => self.editor.set_theme(&theme),
+            Command::SetSyntax(name) => self.editor.set_syntax(&name),
+            Command::ListSyntaxes => self.editor.list_syntaxes(),
+            Command::ListThemes => self.editor.list_themes(),
+            Command::CycleTheme => self.editor.cycle_theme(),
             Command::NextBuffer => self.editor.next_buffer(),
             Command::PrevBuffer => self.editor.prev_buffer(),
+            Command::SetBufferCycleOrder(order) => self.editor.buffer_cycle_order = order,
+            Command::SplitView(direction) => self.editor.split_view(direction),
+            Command::FocusNextPane => self.editor.focus_next_pane(),
             Command::MoveLeft => self.editor.move_left(),
             Command::MoveRight => self.editor.move_right(),
             Command::MoveUp => self.editor.move_up(),
             Command::MoveDown => self.editor.move_down(),
+            Command::MoveTo { point, extend } => self.editor.move_to(point, extend),
             Command::PageDown => self.editor.page_down(),
             Command::PageUp => self.editor.page_up(),
+            Command::ScrollCenter => self.editor.scroll_center(),
+            Command::ScrollTop => self.editor.scroll_top(),
+            Command::ScrollBottom => self.editor.scroll_bottom(),
+            Command::HalfPageDown { extend } => self.editor.half_page(true, extend),
+            Command::HalfPageUp { extend } => self.editor.half_page(false, extend),
             Command::ToggleLineNumbers => self.editor.toggle_line_numbers(),
+            Command::ToggleWhitespace => self.editor.toggle_whitespace(),
+            Command::SetBell(mode) => self.editor.bell_mode = mode,
+            Command::SetAutoReload(policy) => self.editor.set_auto_reload(policy),
+            Command::SetCursorShape { insert, command } => {
+                self.insert_cursor = insert.unwrap_or(self.insert_cursor);
+                self.command_cursor = command.unwrap_or(self.command_cursor);
+            }
+            Command::SetLineNumberMode(mode) => self.editor.set_line_number_mode(mode),
+            Command::SetLineEnding(ending) => self.editor.set_line_ending(ending),
+            Command::MarkdownHeadingMove { forward, level } => {
+                self.editor.markdown_heading_move(forward, level)
+            }
+            Command::FoldAllSections => self.editor.fold_all_sections(),
+            Command::ToggleFold => self.editor.toggle_fold(),
+            Command::FoldAll => self.editor.fold_all(),
+            Command::UnfoldAll => self.editor.unfold_all(),
+            Command::DuplicateLine => self.editor.duplicate_line(),
+            Command::SwitchBuffer(name) => self.editor.switch_buffer(&name),
+            Command::ListBuffers => self.editor.list_buffers(),
+            Command::DeleteToEol => self.editor.delete_to_eol(),
+            Command::DeleteToBol => self.editor.delete_to_bol(),
+            Command::DeleteWord { forward } => self.editor.delete_word(forward),
+            Command::InsertDateTime(format) => self.editor.insert_date_time(format.as_deref()),
+            Command::TransposeChars => self.editor.transpose(),
+            Command::Undo => self.editor.undo(),
+            Command::Redo => self.editor.redo(),
+            Command::BufferStats => self.editor.buffer_stats(),
+            Command::SwapSelectionEnds => self.editor.swap_selection_ends(),
+            Command::MoveToMatchingIndent { forward, extend } => {
+                self.editor.move_to_matching_indent(forward, extend)
+            }
+            Command::GotoMatchingBracket { extend } => self.editor.goto_matching_bracket(extend),
+            Command::Find(config) => self.editor.find(&config),
+            Command::FindNext => self.editor.find_next(),
+            Command::FindPrev => self.editor.find_prev(),
+            Command::FindAll => self.editor.find_all(),
+            Command::FindInFiles(config) => self.editor.find_in_files(&config),
+            Command::ToggleQuickfix => self.editor.toggle_quickfix(),
+            Command::QuickfixNext => self.editor.quickfix_move(true),
+            Command::QuickfixPrev => self.editor.quickfix_move(false),
+            Command::JumpBack => self.editor.jump_back(),
+            Command::GotoLastEdit => self.editor.goto_last_edit(),
+            Command::JumpForward => self.editor.jump_forward(),
+            Command::ToggleComment(token) => self.editor.toggle_comment(&token),
+            Command::JoinLines => self.editor.join_lines(),
+            Command::SortLines(config) => self.editor.sort_lines(&config),
+            Command::ToggleCase => self.editor.toggle_case(),
+            Command::UpperCase => self.editor.upper_case(),
+            Command::SurroundSelection(open, close) => self.editor.surround(&open, &close),
+            Command::IncrementNumber(delta) => self.editor.increment_number(delta),
+            Command::LowerCase => self.editor.lower_case(),
+            Command::SelectLine => self.editor.select_line(),
+            Command::SelectWord => self.editor.select_word(),
+            Command::CopyLine => self.editor.copy_line(),
+            Command::CutLine => self.editor.cut_line(),
+            Command::Paste => self.editor.paste_clipboard(),
+            Command::CopyToRegister(name) => self.editor.copy_to_register(name),
+            Command::CutToRegister(name) => self.editor.cut_to_register(name),
+            Command::PasteRegister(name) => self.editor.paste_register(name),
+            Command::ToggleSystemClipboard => self.editor.toggle_system_clipboard(),
+            Command::GotoDefinition => self.editor.goto_definition(),
+            Command::GotoSymbol => self.editor.goto_symbol(),
+            Command::OpenFileUnderCursor => self.editor.open_file_under_cursor(),
+            Command::SetMark(name) => self.editor.set_mark(name),
+            Command::GotoMark(name) => self.editor.goto_mark(name),
+            Command::TrimTrailingWhitespace => self.editor.trim_trailing_whitespace(),
+            Command::ToggleTrimOnSave => self.editor.toggle_trim_on_save(),
+            Command::SetFinalNewline(enabled) => self.editor.set_final_newline(enabled),
+            Command::Indent => self.editor.indent(),
+            Command::Unindent => self.editor.unindent(),
+            Command::Reindent => self.editor.reindent(),
+            Command::AddCursorAtLine(line) => self.editor.add_cursor_at_line(line),
+            Command::Set(setting) => self.editor.set(setting),
+            Command::SetKeyTimeout(timeout) => self.key_timeout = Duration::from_millis(timeout),
+        }
+    }
+
+    /// Feed a key to the multi-key bindings. Return `false` if the
+    /// key is not part of a key sequence and should be handled
+    /// normally.
+    fn handle_key_sequence(&mut self, key: Key) -> bool {
+        let mut pending = match self.pending_keys.take() {
+            Some(mut pending) => {
+                pending.push(key);
+                pending
+            }
+            None => PendingKeys::new(key),
+        };
+        match self.bindings.lookup(pending.keys()) {
+            Lookup::Command(cmd) => {
+                self.key_timer = None;
+                self.run_counted(cmd);
+                true
+            }
+            Lookup::Ambiguous(cmd) => {
+                pending.set_match(cmd);
+                self.pending_keys = Some(pending);
+                self.key_timer = Some(Interval::new_interval(Duration::from_millis(100)));
+                true
+            }
+            Lookup::Prefix => {
+                self.pending_keys = Some(pending);
+                self.key_timer = Some(Interval::new_interval(Duration::from_millis(100)));
+                true
+            }
+            Lookup::Unbound => {
+                self.key_timer = None;
+                if pending.matched().is_some() {
+                    self.run_matched_keys(pending);
+                    true
+                } else if pending.keys().len() > 1 {
+                    self.count = None;
+                    warn!(
+                        "key sequence {} is not bound",
+                        describe_keys(pending.keys())
+                    );
+                    true
+                } else {
+                    false
+                }
+            }
         }
     }
 
+    /// Run the command of the shortest binding of a pending key
+    /// sequence whose longer binding was not completed, and handle the
+    /// keys typed after it as new input.
+    fn run_matched_keys(&mut self, pending: PendingKeys) {
+        let (len, cmd) = match pending.matched() {
+            Some(&(len, ref cmd)) => (len, cmd.clone()),
+            None => return,
+        };
+        self.run_counted(cmd);
+        for key in &pending.keys()[len..] {
+            self.handle_input(Event::Key(*key));
+        }
+    }
+
+    /// Run the command of a key binding as many times as the pending
+    /// count says. The count multiplies the number added by
+    /// `Command::IncrementNumber` instead, since repeated edits would
+    /// all start from the same text.
+    fn run_counted(&mut self, cmd: Command) {
+        let count = self.count.take().unwrap_or(1);
+        match cmd {
+            Command::IncrementNumber(delta) => {
+                let delta = delta.saturating_mul(i64::from(count));
+                self.run_command(Command::IncrementNumber(delta));
+            }
+            cmd => {
+                for _ in 0..count {
+                    self.run_command(cmd.clone());
+                }
+            }
+        }
+    }
+
+    /// Handle the keys that make up a count: Alt and a digit appends
+    /// the digit to the count. Return `false` if the key is not one of
+    /// them.
+    fn handle_count(&mut self, key: Key) -> bool {
+        match key {
+            Key::Alt(c) if c.is_ascii_digit() => {
+                let digit = c.to_digit(10).unwrap();
+                let count = self.count.unwrap_or(0).saturating_mul(10) + digit;
+                self.count = Some(count.min(MAX_COUNT));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn play_step(&mut self, step: MacroStep) {
+        match step {
+            MacroStep::Command(cmd) => self.run_command(cmd.for_current_view()),
+            MacroStep::Input(event) => self.send_input(event),
+            MacroStep::Paste(text) => self.send_paste(&text),
+        }
+    }
+
+    /// Send an event to the editor, recording it if a macro is being
+    /// recorded.
+    fn send_input(&mut self, event: Event) {
+        self.macros.record(MacroStep::Input(event.clone()));
+        self.editor.handle_input(event);
+    }
+
+    fn send_paste(&mut self, text: &str) {
+        self.macros.record(MacroStep::Paste(text.to_owned()));
+        self.editor.paste(text);
+    }
+
     /// Global keybindings can be parsed here
     fn handle_input(&mut self, event: Event) {
         debug!("handling input {:?}", event);
+        if let Event::Key(key) = event {
+            if key == Key::Esc && self.pending_keys.is_none() {
+                self.run_command(Command::Escape);
+                return;
+            }
+            let typing = self.is_typing();
+            if !typing && self.pending_keys.is_none() && self.handle_count(key) {
+                return;
+            }
+            if !typing && self.handle_key_sequence(key) {
+                return;
+            }
+        }
         match event {
             Event::Key(Key::Ctrl('c')) => self.exit = true,
+            event if self.palette.is_some() => self.handle_palette_input(&event),
+            event if self.picker.is_some() => self.handle_picker_input(&event),
+            event if self.confirm.is_some() => self.handle_confirm_input(&event),
+            event if self.outline.is_some() => self.handle_outline_input(&event),
+            event if self.help.is_some() => self.handle_help_input(&event),
             Event::Key(Key::Alt('x')) => {
                 if let Some(ref mut prompt) = self.prompt {
                     match prompt.handle_input(&event) {
@@ -88,13 +581,20 @@ impl Tui {
                         Err(_) => unreachable!(),
                     }
                 } else {
-                    self.prompt = Some(CommandPrompt::default());
+                    self.count = None;
+                    self.prompt = Some(CommandPrompt::new(
+                        self.editor.current_file(),
+                        self.aliases.clone(),
+                    ));
                 }
             }
             event => {
                 // No command prompt is active, process the event normally.
                 if self.prompt.is_none() {
-                    self.editor.handle_input(event);
+                    for _ in 1..self.count.take().unwrap_or(1) {
+                        self.send_input(event.clone());
+                    }
+                    self.send_input(event);
                     return;
                 }
 
@@ -103,28 +603,239 @@ impl Tui {
                 match prompt.handle_input(&event) {
                     Ok(None) => {
                         self.prompt = Some(prompt);
+                        self.update_preview();
+                    }
+                    Ok(Some(cmd)) => {
+                        // Submitting the search keeps the position of
+                        // its preview.
+                        if let Command::Find(_) = cmd {
+                            self.search_preview = None;
+                        }
+                        self.cancel_preview();
+                        self.run_command(cmd);
                     }
-                    Ok(Some(cmd)) => self.run_command(cmd),
                     Err(err) => {
                         error!("Failed to parse command: {:?}", err);
+                        self.cancel_preview();
+                        self.editor.set_status(err.to_string());
+                        self.editor.bell();
                     }
                 }
             }
         }
     }
 
+    /// Run the command waiting for confirmation if the answer is yes,
+    /// and drop it if the answer is no.
+    fn handle_confirm_input(&mut self, event: &Event) {
+        let answer = self.confirm.as_ref().and_then(|c| c.handle_input(event));
+        match answer {
+            Some(true) => {
+                if let Some(confirm) = self.confirm.take() {
+                    self.run_command(confirm.into_command());
+                }
+            }
+            Some(false) => self.confirm = None,
+            None => {}
+        }
+    }
+
+    fn handle_palette_input(&mut self, event: &Event) {
+        let selected = match self.palette.as_mut().and_then(|p| p.handle_input(event)) {
+            Some(ListEvent::Accept(cmd)) => cmd,
+            Some(ListEvent::Cancel) => {
+                self.palette = None;
+                return;
+            }
+            None => return,
+        };
+        self.palette = None;
+        // Commands that need arguments are completed in the prompt
+        match selected.name.parse::<Command>() {
+            Ok(cmd) => self.run_command(cmd),
+            Err(_) => {
+                let text = format!("{} ", selected.name);
+                self.prompt = Some(CommandPrompt::with_text(
+                    self.editor.current_file(),
+                    self.aliases.clone(),
+                    &text,
+                ));
+            }
+        }
+    }
+
+    fn handle_picker_input(&mut self, event: &Event) {
+        match self.picker.as_mut().and_then(|p| p.handle_input(event)) {
+            Some(FilePickerEvent::Open(path)) => {
+                self.picker = None;
+                self.run_command(Command::Open(Some(path)));
+            }
+            Some(FilePickerEvent::Cancel) => self.picker = None,
+            None => {}
+        }
+    }
+
+    fn handle_outline_input(&mut self, event: &Event) {
+        match self.outline.as_mut().and_then(|o| o.handle_input(event)) {
+            Some(ListEvent::Accept(symbol)) => {
+                self.outline = None;
+                self.editor.jump_to(symbol.line, symbol.column);
+            }
+            Some(ListEvent::Cancel) => self.outline = None,
+            None => {}
+        }
+    }
+
+    fn handle_help_input(&mut self, event: &Event) {
+        let close = match self.help {
+            Some(ref mut help) => help.handle_input(event, self.term_size.1),
+            None => return,
+        };
+        if close {
+            self.help = None;
+        }
+    }
+
+    /// Pasted text is inserted as is, without going through the key
+    /// bindings.
+    fn handle_paste(&mut self, text: String) {
+        debug!("handling paste of {} bytes", text.len());
+        self.pending_keys = None;
+        self.key_timer = None;
+        if self.confirm.is_some() || self.help.is_some() {
+            return;
+        }
+        if let Some(ref mut palette) = self.palette {
+            palette.paste(&text);
+            return;
+        }
+        if let Some(ref mut picker) = self.picker {
+            picker.paste(&text);
+            return;
+        }
+        if let Some(ref mut outline) = self.outline {
+            outline.paste(&text);
+            return;
+        }
+        match self.prompt {
+            Some(ref mut prompt) => {
+                prompt.paste(&text);
+                self.update_preview();
+            }
+            None => {
+                for _ in 0..self.count.take().unwrap_or(1) {
+                    self.send_paste(&text);
+                }
+            }
+        }
+    }
+
+    /// Preview the search or the position typed in the prompt, if it
+    /// changed since it was last previewed.
+    fn update_preview(&mut self) {
+        let term = self.prompt.as_ref().and_then(CommandPrompt::find_term);
+        if term != self.search_preview {
+            match term {
+                Some(ref config) => self.editor.preview_find(config),
+                None => self.editor.cancel_find_preview(),
+            }
+            self.search_preview = term;
+        }
+        let position = self.prompt.as_ref().and_then(CommandPrompt::goto_position);
+        if position != self.goto_preview {
+            match position {
+                Some((target, column)) => self.editor.preview_goto(target, column),
+                None => self.editor.cancel_goto_preview(),
+            }
+            self.goto_preview = position;
+        }
+    }
+
+    /// Put the cursor back where it was before the search or the
+    /// position typed in the prompt was previewed.
+    fn cancel_preview(&mut self) {
+        if self.search_preview.take().is_some() {
+            self.editor.cancel_find_preview();
+        }
+        if self.goto_preview.take().is_some() {
+            self.editor.cancel_goto_preview();
+        }
+    }
+
     fn render(&mut self) -> Result<(), Error> {
-        if let Some(ref mut prompt) = self.prompt {
+        if let Some(ref palette) = self.palette {
+            self.editor.render(self.terminal.stdout())?;
+            palette.render(self.terminal.stdout(), self.term_size)?;
+        } else if let Some(ref picker) = self.picker {
+            self.editor.render(self.terminal.stdout())?;
+            picker.render(self.terminal.stdout(), self.term_size)?;
+        } else if let Some(ref confirm) = self.confirm {
+            self.editor.render(self.terminal.stdout())?;
+            confirm.render(self.terminal.stdout(), self.term_size.1)?;
+        } else if let Some(ref help) = self.help {
+            help.render(self.terminal.stdout(), self.term_size)?;
+        } else if let Some(ref outline) = self.outline {
+            self.editor.render(self.terminal.stdout())?;
+            outline.render(self.terminal.stdout(), self.term_size)?;
+        } else if let Some(ref mut prompt) = self.prompt {
             prompt.render(self.terminal.stdout(), self.term_size.1)?;
         } else {
             self.editor.render(self.terminal.stdout())?;
+            self.render_pending_keys();
         }
+        let shape = if self.is_typing() {
+            self.command_cursor
+        } else {
+            self.insert_cursor
+        };
+        self.terminal.set_cursor_shape(shape);
         if let Err(e) = self.terminal.stdout().flush() {
             error!("failed to flush stdout: {}", e);
         }
         Ok(())
     }
 
+    /// Show the macro being recorded, the pending count, and the keys
+    /// of the pending key sequence with the time left before it times
+    /// out, in the bottom right corner.
+    fn render_pending_keys(&mut self) {
+        let count = match self.count {
+            Some(count) => format!("{} ", count),
+            None => String::new(),
+        };
+        let indicator = match self.pending_keys {
+            Some(ref pending) => {
+                let remaining = pending.remaining(Instant::now(), self.key_timeout);
+                Some(format!(
+                    "{}{}- ({}.{}s)",
+                    count,
+                    describe_keys(pending.keys()),
+                    remaining.as_secs(),
+                    remaining.subsec_millis() / 100
+                ))
+            }
+            None => self.count.map(|count| count.to_string()),
+        };
+        let indicator = match (self.macros.recording(), indicator) {
+            (Some(name), Some(indicator)) => Some(format!("recording '{}'  {}", name, indicator)),
+            (Some(name), None) => Some(format!("recording '{}'", name)),
+            (None, indicator) => indicator,
+        };
+        if let Some(indicator) = indicator {
+            let column = self.term_size.0.saturating_sub(indicator.len() as u16) + 1;
+            if let Err(e) = write!(
+                self.terminal.stdout(),
+                "{}{}{}{}",
+                Save,
+                Goto(column, self.term_size.1),
+                indicator,
+                Restore
+            ) {
+                error!("failed to render pending keys: {}", e);
+            }
+        }
+    }
+
     fn handle_core_event(&mut self, event: CoreEvent) {
         self.editor.handle_core_event(event)
     }
@@ -150,6 +861,74 @@ impl Tui {
         }
     }
 
+    /// Whether the keys go to the prompt or to a list or question
+    /// shown over the views, instead of the key bindings.
+    fn is_typing(&self) -> bool {
+        self.prompt.is_some()
+            || self.palette.is_some()
+            || self.picker.is_some()
+            || self.confirm.is_some()
+            || self.outline.is_some()
+            || self.help.is_some()
+    }
+
+    /// List the symbols of the current view once the editor found them.
+    fn open_outline(&mut self) {
+        if let Some(symbols) = self.editor.take_outline() {
+            self.prompt = None;
+            self.cancel_preview();
+            self.outline = Some(Outline::new(Symbols(symbols)));
+        }
+    }
+
+    /// Ask whether to reload the next view whose file changed on disk,
+    /// once nothing else is being typed.
+    fn ask_reload(&mut self) {
+        if self.is_typing() {
+            return;
+        }
+        if let Some((view_id, file)) = self.editor.next_changed_on_disk() {
+            let question = if self.editor.is_dirty(Some(view_id)) {
+                format!(
+                    "{} changed on disk, reload it and discard your changes?",
+                    file
+                )
+            } else {
+                format!("{} changed on disk, reload it?", file)
+            };
+            self.confirm = Some(Confirm::new(question, Command::ForceReload(Some(view_id))));
+        }
+    }
+
+    fn poll_key_timeout(&mut self) {
+        let timed_out = match self.pending_keys {
+            Some(ref pending) => !pending.is_active(Instant::now(), self.key_timeout),
+            None => return,
+        };
+        if timed_out {
+            info!("key sequence timed out");
+            self.key_timer = None;
+            if let Some(pending) = self.pending_keys.take() {
+                self.run_matched_keys(pending);
+            }
+            return;
+        }
+        if let Some(ref mut timer) = self.key_timer {
+            // Poll the timer until it's not ready, so that we're woken
+            // up for the next tick.
+            loop {
+                match timer.poll() {
+                    Ok(Async::Ready(Some(_))) => {}
+                    Ok(Async::Ready(None)) | Ok(Async::NotReady) => break,
+                    Err(e) => {
+                        error!("key sequence timer failed: {:?}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
     fn poll_terminal(&mut self) {
         debug!("polling the terminal");
         loop {
@@ -157,6 +936,7 @@ impl Tui {
                 Ok(Async::Ready(Some(event))) => match event {
                     TerminalEvent::Input(event) => self.handle_input(event),
                     TerminalEvent::Resize(event) => self.handle_resize(event),
+                    TerminalEvent::Paste(text) => self.handle_paste(text),
                 },
                 Ok(Async::Ready(None)) => {
                     info!("The terminal exited normally. Shutting down the TUI");
@@ -219,6 +999,15 @@ impl Future for Tui {
             info!("exiting the TUI");
             return Ok(Async::Ready(()));
         }
+        self.open_outline();
+        self.ask_reload();
+        if !self.init_commands.is_empty() && self.editor.has_views() {
+            info!("running the commands of the init file");
+            let commands: Vec<Command> = self.init_commands.drain(..).collect();
+            for cmd in commands {
+                self.run_command(cmd);
+            }
+        }
 
         self.poll_rpc();
         if self.exit {
@@ -226,6 +1015,8 @@ impl Future for Tui {
             return Ok(Async::Ready(()));
         }
 
+        self.poll_key_timeout();
+
         debug!("done polling the TUI components");
         debug!("rendering");
         self.render().expect("failed to render the TUI");
@@ -290,3 +1081,80 @@ impl FrontendBuilder for TuiServiceBuilder {
         TuiService(self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures::future;
+    use tokio::runtime::current_thread::Runtime;
+
+    use super::*;
+
+    /// A writer whose content can still be read once the terminal
+    /// writing to it is dropped.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedBuffer {
+        fn contents(&self) -> String {
+            String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Run `test` with a TUI rendering to a buffer, whose content is
+    /// returned once the TUI is dropped. The core is `cat`, which is
+    /// enough as long as nothing waits for its answers.
+    fn headless<F: FnOnce(&mut Tui, &UnboundedSender<TerminalEvent>)>(test: F) -> String {
+        let output = SharedBuffer::default();
+        let (terminal, events) = Terminal::with_writer(output.clone());
+        let mut runtime = Runtime::new().unwrap();
+        runtime
+            .block_on(future::lazy(move || {
+                let (builder, core_events) = TuiServiceBuilder::new();
+                let (client, _stderr) = xrl::spawn("cat", builder).unwrap();
+                let mut tui = Tui::with_terminal(client, core_events, terminal);
+                test(&mut tui, &events);
+                Ok::<(), ()>(())
+            }))
+            .unwrap();
+        output.contents()
+    }
+
+    #[test]
+    fn renders_the_help_to_the_writer() {
+        let output = headless(|tui, events| {
+            events
+                .unbounded_send(TerminalEvent::Resize((80, 30)))
+                .unwrap();
+            tui.run_command(Command::ShowKeybindings);
+            tui.poll().unwrap();
+        });
+        // The footer is on the last row, inverted
+        assert!(output.contains("\x1b[30;1H\x1b[2K\x1b[7mkey bindings 1-"));
+        assert!(output.contains("^x ^s"));
+        assert!(output.contains("save"));
+    }
+
+    #[test]
+    fn escape_always_clears_the_count() {
+        headless(|tui, _| {
+            tui.count = Some(3);
+            tui.run_command(Command::ShowKeybindings);
+            tui.run_command(Command::Escape);
+            assert!(tui.help.is_none());
+            assert_eq!(tui.count, None);
+        });
+    }
+}
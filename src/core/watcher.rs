@@ -0,0 +1,198 @@
+//! Noticing when the files of the views change on disk. Xi doesn't
+//! report it, so the modification time of the files is checked
+//! periodically.
+
+use std::collections::HashMap;
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::{Async, Stream};
+use tokio::timer::Interval;
+
+/// How often the files are checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+struct Watched {
+    /// The modification time of the file when it was last loaded,
+    /// saved, or reported as changed.
+    known: SystemTime,
+    /// The different modification time seen at the last check, if
+    /// any. A change is only reported once it was seen twice in a
+    /// row, so that a burst of writes is reported once.
+    seen: Option<SystemTime>,
+    /// Whether the file is being saved by the editor, so that the next
+    /// change is ours. This is cleared once the save completes, or
+    /// when the change is seen if that comes first.
+    saving: bool,
+}
+
+impl Watched {
+    fn new(known: SystemTime) -> Self {
+        Watched {
+            known,
+            seen: None,
+            saving: false,
+        }
+    }
+
+    /// Note that the file was modified at `mtime` when it was
+    /// checked. Return whether this is a change to report.
+    fn check(&mut self, mtime: SystemTime) -> bool {
+        if mtime == self.known {
+            self.seen = None;
+        } else if self.seen == Some(mtime) {
+            self.known = mtime;
+            self.seen = None;
+            if self.saving {
+                self.saving = false;
+            } else {
+                return true;
+            }
+        } else {
+            self.seen = Some(mtime);
+        }
+        false
+    }
+
+    /// Note that the save of the file completed, after which it was
+    /// modified at `mtime`.
+    fn saved(&mut self, mtime: Option<SystemTime>) {
+        if !self.saving {
+            return;
+        }
+        self.saving = false;
+        if let Some(mtime) = mtime {
+            self.known = mtime;
+            self.seen = None;
+        }
+    }
+}
+
+pub struct FileWatcher {
+    files: HashMap<String, Watched>,
+    interval: Interval,
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        FileWatcher {
+            files: HashMap::new(),
+            interval: Interval::new_interval(CHECK_INTERVAL),
+        }
+    }
+
+    /// Remember the modification time of `path`, which was just read
+    /// from disk, so that only the changes made after that are
+    /// reported.
+    pub fn loaded(&mut self, path: &str) {
+        if let Some(mtime) = modified(path) {
+            let watched = self
+                .files
+                .entry(path.to_owned())
+                .or_insert_with(|| Watched::new(mtime));
+            watched.known = mtime;
+            watched.seen = None;
+        }
+    }
+
+    /// Remember that the editor is saving `path`, so that the change
+    /// is not reported.
+    pub fn saving(&mut self, path: &str) {
+        let watched = self
+            .files
+            .entry(path.to_owned())
+            .or_insert_with(|| Watched::new(modified(path).unwrap_or(UNIX_EPOCH)));
+        watched.saving = true;
+    }
+
+    /// Note that the editor finished saving `path`. The file is not
+    /// necessarily modified by the save, so waiting for the change
+    /// could mistake the next external one for ours.
+    pub fn saved(&mut self, path: &str) {
+        if let Some(watched) = self.files.get_mut(path) {
+            watched.saved(modified(path));
+        }
+    }
+
+    /// If it's time for a check, return the files among `paths` that
+    /// changed on disk since the last time they were seen. Files are
+    /// watched from the first check where they are in `paths`, and
+    /// forgotten once they are not anymore. Files that can't be read,
+    /// for instance because they were deleted, are left alone.
+    pub fn poll_changes(&mut self, paths: &[&String]) -> Vec<String> {
+        let mut ticked = false;
+        loop {
+            match self.interval.poll() {
+                Ok(Async::Ready(Some(_))) => ticked = true,
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => break,
+                Err(e) => {
+                    error!("file watcher timer failed: {:?}", e);
+                    break;
+                }
+            }
+        }
+        if !ticked {
+            return Vec::new();
+        }
+
+        self.files.retain(|path, _| paths.contains(&path));
+        let mut changed = Vec::new();
+        for path in paths {
+            let mtime = match modified(path) {
+                Some(mtime) => mtime,
+                None => continue,
+            };
+            let watched = self
+                .files
+                .entry((*path).clone())
+                .or_insert_with(|| Watched::new(mtime));
+            if watched.check(mtime) {
+                info!("{} changed on disk", path);
+                changed.push((*path).clone());
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn a_change_is_reported_once_it_was_seen_twice() {
+        let mut watched = Watched::new(at(1));
+        assert!(!watched.check(at(2)));
+        assert!(watched.check(at(2)));
+        assert!(!watched.check(at(2)));
+    }
+
+    #[test]
+    fn the_change_made_by_a_save_is_not_reported() {
+        let mut watched = Watched::new(at(1));
+        watched.saving = true;
+        watched.saved(Some(at(2)));
+        assert!(!watched.check(at(2)));
+        assert!(!watched.check(at(2)));
+    }
+
+    #[test]
+    fn a_save_that_changed_nothing_does_not_hide_the_next_change() {
+        let mut watched = Watched::new(at(1));
+        watched.saving = true;
+        watched.saved(Some(at(1)));
+        assert!(!watched.check(at(3)));
+        assert!(watched.check(at(3)));
+    }
+}
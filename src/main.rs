@@ -5,6 +5,7 @@
 #[macro_use]
 extern crate clap;
 
+extern crate chrono;
 extern crate failure;
 
 #[macro_use]
@@ -13,8 +14,10 @@ extern crate log4rs;
 
 extern crate futures;
 extern crate indexmap;
+extern crate libc;
 extern crate termion;
 extern crate tokio;
+extern crate tokio_signal;
 extern crate xdg;
 extern crate xrl;
 
@@ -29,7 +32,7 @@ use log4rs::append::file::FileAppender;
 use log4rs::config::{Appender, Config, Logger, Root};
 use xrl::spawn;
 
-use core::{Command, Tui, TuiServiceBuilder};
+use core::{init_file_path, Command, Tui, TuiServiceBuilder};
 
 fn configure_logs(logfile: &str) {
     let tui = FileAppender::builder().build(logfile).unwrap();
@@ -86,6 +89,7 @@ fn run() -> Result<(), Error> {
         (about: "The Xi Editor")
         (@arg core: -c --core +takes_value "Specify binary to use for the backend")
         (@arg logfile: -l --logfile +takes_value "Log file location")
+        (@arg system_clipboard: --("system-clipboard") "Share copied text with the system clipboard")
         (@arg file: +required "File to edit"));
 
     let matches = xi.get_matches();
@@ -128,6 +132,12 @@ fn run() -> Result<(), Error> {
                         matches.value_of("file").map(ToString::to_string),
                     ));
                     tui.run_command(Command::SetTheme("base16-eighties.dark".into()));
+                    if matches.is_present("system_clipboard") {
+                        tui.run_command(Command::ToggleSystemClipboard);
+                    }
+                    if let Some(path) = init_file_path() {
+                        tui.run_init_file(&path);
+                    }
                     tui.map_err(|e| error!("TUI exited with an error: {:?}", e))
                 })
         }));
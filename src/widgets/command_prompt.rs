@@ -2,25 +2,181 @@
 //! heavily inspired by vim and is just designed to
 //! get a simple base to work off of.
 
+use std::env;
+use std::fs;
 use std::io::Error;
 use std::io::Write;
 use termion::event::{Event, Key};
 
-use core::{Command, ParseCommandError};
+use core::{resolve_path, Aliases, Command, FindConfig, LineTarget, ParseCommandError, COMMANDS};
 use termion::clear::CurrentLine as ClearLine;
 use termion::cursor::Goto;
 
 use std::str::FromStr;
 
+/// What the prompt reads.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CommandPromptMode {
+    /// Any command.
+    #[default]
+    Command,
+    /// Only a line to move to, like `42` or `42:7`.
+    Goto,
+}
+
 #[derive(Debug, Default)]
 pub struct CommandPrompt {
+    mode: CommandPromptMode,
     dex: usize,
     chars: String,
+    /// The completion being cycled through with Tab, if any.
+    completion: Option<Completion>,
+    /// The file of the current view. Paths given to `open` are
+    /// completed relative to its directory.
+    current_file: Option<String>,
+    /// The aliases the typed command may start with.
+    aliases: Aliases,
+}
+
+#[derive(Debug)]
+struct Completion {
+    /// The text before the completed word, which is kept as is.
+    prefix: String,
+    candidates: Vec<String>,
+    /// Index of the candidate currently in the prompt.
+    current: usize,
+}
+
+/// Return the command names matching `partial`, best matches first.
+///
+/// A name matches if it contains all the characters of `partial` in
+/// order. Names where the match starts earlier, and then those where
+/// the matched characters are closer together, come first.
+pub fn candidates(partial: &str) -> Vec<&'static str> {
+    let mut scored: Vec<((usize, usize), &'static str)> = COMMANDS
+        .iter()
+        .filter_map(|cmd| fuzzy_score(cmd.name, partial).map(|score| (score, cmd.name)))
+        .collect();
+    scored.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(a.1.len().cmp(&b.1.len()))
+            .then(a.1.cmp(b.1))
+    });
+    scored.into_iter().map(|(_, name)| name).collect()
+}
+
+/// If `partial` is a subsequence of `name`, return the position of
+/// the first matched character and the number of unmatched characters
+/// between the first and last matched ones.
+pub fn fuzzy_score(name: &str, partial: &str) -> Option<(usize, usize)> {
+    let mut chars = name.char_indices();
+    let mut first = None;
+    let mut last = 0;
+    for wanted in partial.chars() {
+        let (idx, _) = chars.find(|&(_, c)| c == wanted)?;
+        first = first.or(Some(idx));
+        last = idx;
+    }
+    let first = first.unwrap_or(0);
+    let gaps = (last - first + 1).saturating_sub(partial.chars().count());
+    Some((first, gaps))
+}
+
+/// Replace the `~/` starting `path` with the home directory. Return
+/// `None` if `path` doesn't start with `~/` or if the home directory is
+/// not known.
+pub fn expand_home(path: &str) -> Option<String> {
+    let rest = path.strip_prefix("~/")?;
+    let home = env::var("HOME").ok()?;
+    Some(format!("{}/{}", home, rest))
+}
+
+/// Return the paths that `partial` can be completed to, sorted by
+/// name.
+///
+/// The directory part of `partial` is listed, and its entries that
+/// start with the last component of `partial` are returned, with the
+/// directory part kept as typed. Directories end with a `/`. Hidden
+/// entries are only returned when the last component starts with a
+/// `.`. Relative paths are resolved like `open` does, against the
+/// directory of `current_file`.
+pub fn path_candidates(current_file: Option<&str>, partial: &str) -> Vec<String> {
+    let (dir, name) = match partial.rfind('/') {
+        Some(idx) => partial.split_at(idx + 1),
+        None => ("", partial),
+    };
+    let listed = match dir {
+        "" => resolve_path(current_file, "."),
+        _ => expand_home(dir).unwrap_or_else(|| resolve_path(current_file, dir)),
+    };
+    let entries = match fs::read_dir(&listed) {
+        Ok(entries) => entries,
+        Err(e) => {
+            debug!("cannot list {} for completion: {}", listed, e);
+            return Vec::new();
+        }
+    };
+
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            if !file_name.starts_with(name)
+                || (file_name.starts_with('.') && !name.starts_with('.'))
+            {
+                return None;
+            }
+            // Follow symlinks, so that links to directories can be
+            // descended into too.
+            let is_dir = fs::metadata(entry.path())
+                .map(|metadata| metadata.is_dir())
+                .unwrap_or(false);
+            let slash = if is_dir { "/" } else { "" };
+            Some(format!("{}{}{}", dir, file_name, slash))
+        })
+        .collect();
+    candidates.sort();
+    candidates
 }
 
 impl CommandPrompt {
+    /// Create a prompt. `current_file` is the file of the current view,
+    /// if any.
+    pub fn new(current_file: Option<String>, aliases: Aliases) -> CommandPrompt {
+        CommandPrompt {
+            current_file,
+            aliases,
+            ..CommandPrompt::default()
+        }
+    }
+
+    /// Create a prompt that already holds `text`, with the cursor at
+    /// its end.
+    pub fn with_text(current_file: Option<String>, aliases: Aliases, text: &str) -> CommandPrompt {
+        CommandPrompt {
+            dex: text.len(),
+            chars: text.to_owned(),
+            ..CommandPrompt::new(current_file, aliases)
+        }
+    }
+
+    /// Create a prompt that only reads a line to move to.
+    pub fn goto() -> CommandPrompt {
+        CommandPrompt {
+            mode: CommandPromptMode::Goto,
+            ..CommandPrompt::default()
+        }
+    }
+
     /// Process a terminal event for the command prompt.
     pub fn handle_input(&mut self, input: &Event) -> Result<Option<Command>, ParseCommandError> {
+        if let Event::Key(Key::Char('\t')) = input {
+            if self.mode == CommandPromptMode::Goto {
+                return Ok(None);
+            }
+            return Ok(self.complete());
+        }
+        self.completion = None;
         match input {
             Event::Key(Key::Char('\n')) => self.finalize(),
             Event::Key(Key::Backspace) | Event::Key(Key::Ctrl('h')) => Ok(self.back()),
@@ -32,16 +188,24 @@ impl CommandPrompt {
         }
     }
 
+    /// The length in bytes of the character before the cursor, if any.
+    fn prev_char_len(&self) -> Option<usize> {
+        self.chars[..self.dex]
+            .chars()
+            .next_back()
+            .map(char::len_utf8)
+    }
+
     fn left(&mut self) -> Option<Command> {
-        if self.dex > 0 {
-            self.dex -= 1;
+        if let Some(len) = self.prev_char_len() {
+            self.dex -= len;
         }
         None
     }
 
     fn right(&mut self) -> Option<Command> {
-        if self.dex < self.chars.len() {
-            self.dex += 1;
+        if let Some(c) = self.chars[self.dex..].chars().next() {
+            self.dex += c.len_utf8();
         }
         None
     }
@@ -54,38 +218,185 @@ impl CommandPrompt {
     }
 
     fn back(&mut self) -> Option<Command> {
-        if !self.chars.is_empty() {
-            self.dex -= 1;
+        if self.chars.is_empty() {
+            return Some(Command::Cancel);
+        }
+        if let Some(len) = self.prev_char_len() {
+            self.dex -= len;
             self.chars.remove(self.dex);
-            None
-        } else {
-            Some(Command::Cancel)
+        }
+        None
+    }
+
+    /// Replace the command name, or the path given to `open`, with the
+    /// next completion candidate. Other arguments are not completed.
+    fn complete(&mut self) -> Option<Command> {
+        let completion = match self.completion.take() {
+            Some(mut completion) => {
+                completion.current = (completion.current + 1) % completion.candidates.len();
+                completion
+            }
+            None => {
+                let (prefix, candidates) = match self.chars.find(' ') {
+                    None => (
+                        String::new(),
+                        candidates(&self.chars)
+                            .into_iter()
+                            .map(str::to_owned)
+                            .collect(),
+                    ),
+                    Some(idx) => match &self.chars[..idx] {
+                        "o" | "open" => {
+                            let path = self.chars[idx..].trim_start();
+                            let prefix = &self.chars[..self.chars.len() - path.len()];
+                            (
+                                prefix.to_owned(),
+                                path_candidates(self.current_file.as_deref(), path),
+                            )
+                        }
+                        _ => return None,
+                    },
+                };
+                if candidates.is_empty() {
+                    return None;
+                }
+                Completion {
+                    prefix,
+                    candidates,
+                    current: 0,
+                }
+            }
+        };
+        self.chars = format!(
+            "{}{}",
+            completion.prefix, completion.candidates[completion.current]
+        );
+        self.dex = self.chars.len();
+        self.completion = Some(completion);
+        None
+    }
+
+    /// Insert pasted text at the cursor. Line breaks are replaced by
+    /// spaces, since commands fit on one line.
+    pub fn paste(&mut self, text: &str) {
+        self.completion = None;
+        for c in text.chars() {
+            let c = if c == '\n' || c == '\r' { ' ' } else { c };
+            self.new_key(c);
         }
     }
 
     /// Gets called when any character is pressed.
     fn new_key(&mut self, chr: char) -> Option<Command> {
         self.chars.insert(self.dex, chr);
-        self.dex += 1;
+        self.dex += chr.len_utf8();
         None
     }
 
+    /// If the prompt holds a `find` command, return what it searches
+    /// for, so that it can be previewed while it is typed.
+    pub fn find_term(&self) -> Option<FindConfig> {
+        if self.mode == CommandPromptMode::Goto {
+            return None;
+        }
+        match Command::from_str(&self.chars) {
+            Ok(Command::Find(config)) => Some(config),
+            _ => None,
+        }
+    }
+
+    /// In goto mode, return the position typed so far, so that it can
+    /// be previewed while it is typed.
+    pub fn goto_position(&self) -> Option<(LineTarget, Option<u64>)> {
+        match self.mode {
+            CommandPromptMode::Goto => LineTarget::parse_position(self.chars.trim()),
+            CommandPromptMode::Command => None,
+        }
+    }
+
     /// Gets called when return is pressed,
     fn finalize(&mut self) -> Result<Option<Command>, ParseCommandError> {
-        Ok(Some(FromStr::from_str(&self.chars)?))
+        if self.mode == CommandPromptMode::Goto {
+            if self.chars.trim().is_empty() {
+                return Ok(Some(Command::Cancel));
+            }
+            return match self.goto_position() {
+                Some((target, column)) => Ok(Some(Command::goto(target, column))),
+                None => Err(ParseCommandError::InvalidArgument {
+                    cmd: "goto-line".into(),
+                    arg: self.chars.clone(),
+                }),
+            };
+        }
+        Ok(Some(self.aliases.parse(&self.chars)?))
     }
 
     pub fn render<W: Write>(&mut self, w: &mut W, row: u16) -> Result<(), Error> {
+        let hint = match self.completion {
+            Some(ref completion) if completion.candidates.len() > 1 => format!(
+                "  ({}/{})",
+                completion.current + 1,
+                completion.candidates.len()
+            ),
+            _ => String::new(),
+        };
+        let prefix = match self.mode {
+            CommandPromptMode::Command => ":",
+            CommandPromptMode::Goto => "goto: ",
+        };
         if let Err(err) = write!(
             w,
-            "{}{}:{}{}",
+            "{}{}{}{}{}{}",
             Goto(1, row),
             ClearLine,
+            prefix,
             self.chars,
-            Goto(self.dex as u16 + 2, row)
+            hint,
+            Goto(
+                (prefix.len() + self.chars[..self.dex].chars().count()) as u16 + 1,
+                row
+            )
         ) {
             error!("faile to render status bar: {:?}", err);
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(prompt: &mut CommandPrompt, key: Key) {
+        prompt.handle_input(&Event::Key(key)).unwrap();
+    }
+
+    #[test]
+    fn edits_non_ascii_text_by_whole_characters() {
+        let mut prompt = CommandPrompt::new(None, Aliases::default());
+        prompt.paste("find café");
+        key(&mut prompt, Key::Char('s'));
+        assert_eq!(prompt.chars, "find cafés");
+        key(&mut prompt, Key::Left);
+        key(&mut prompt, Key::Left);
+        key(&mut prompt, Key::Char('x'));
+        assert_eq!(prompt.chars, "find cafxés");
+        key(&mut prompt, Key::Delete);
+        assert_eq!(prompt.chars, "find cafxs");
+        key(&mut prompt, Key::Right);
+        key(&mut prompt, Key::Backspace);
+        assert_eq!(prompt.chars, "find cafx");
+        key(&mut prompt, Key::Backspace);
+        assert_eq!(prompt.chars, "find caf");
+    }
+
+    #[test]
+    fn renders_the_cursor_after_the_characters_before_it() {
+        let mut prompt = CommandPrompt::new(None, Aliases::default());
+        prompt.paste("find é");
+        let mut output = Vec::new();
+        prompt.render(&mut output, 5).unwrap();
+        // The ':' prefix and 6 characters come before the cursor
+        assert!(String::from_utf8(output).unwrap().ends_with("\x1b[5;8H"));
+    }
+}
@@ -0,0 +1,65 @@
+//! A yes or no question asked on the bottom line before running a
+//! command that discards changes.
+
+use std::io::Write;
+
+use failure::Error;
+use termion::clear::CurrentLine as ClearLine;
+use termion::cursor::Goto;
+use termion::event::{Event, Key};
+
+use core::Command;
+
+#[derive(Debug)]
+pub struct Confirm {
+    question: String,
+    /// The command run if the answer is yes.
+    command: Command,
+}
+
+impl Confirm {
+    pub fn new<S: Into<String>>(question: S, command: Command) -> Confirm {
+        Confirm {
+            question: question.into(),
+            command,
+        }
+    }
+
+    /// Return `Some(true)` if the event answers yes, `Some(false)` if
+    /// it answers no, and `None` for other events, which are ignored.
+    pub fn handle_input(&self, event: &Event) -> Option<bool> {
+        match *event {
+            Event::Key(Key::Char('y')) | Event::Key(Key::Char('Y')) => Some(true),
+            Event::Key(Key::Char('n')) | Event::Key(Key::Char('N')) => Some(false),
+            _ => None,
+        }
+    }
+
+    /// The command to run once the answer is yes.
+    pub fn into_command(self) -> Command {
+        self.command
+    }
+
+    pub fn render<W: Write>(&self, w: &mut W, row: u16) -> Result<(), Error> {
+        write!(w, "{}{}{} (y/n) ", Goto(1, row), ClearLine, self.question)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers_yes_or_no() {
+        let confirm = Confirm::new("quit anyway?", Command::ForceQuit);
+        let key = |c| Event::Key(Key::Char(c));
+        assert_eq!(confirm.handle_input(&key('y')), Some(true));
+        assert_eq!(confirm.handle_input(&key('Y')), Some(true));
+        assert_eq!(confirm.handle_input(&key('n')), Some(false));
+        assert_eq!(confirm.handle_input(&key('N')), Some(false));
+        assert_eq!(confirm.handle_input(&key('q')), None);
+        assert_eq!(confirm.handle_input(&Event::Key(Key::Esc)), None);
+        assert_eq!(confirm.into_command(), Command::ForceQuit);
+    }
+}
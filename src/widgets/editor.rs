@@ -1,27 +1,226 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::io::Write;
+use std::fs;
+use std::io::{self, Write};
+use std::mem;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 use futures::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use futures::{Async, Future, Poll, Stream};
 
+use chrono::Local;
 use failure::Error;
 use indexmap::IndexMap;
-use termion::event::Event as TermionEvent;
-use xrl::{Client, ConfigChanged, ScrollTo, Style, Update, ViewId, XiNotification};
+use termion::clear::CurrentLine as ClearLine;
+use termion::cursor::Goto;
+use termion::event::{Event as TermionEvent, Key, MouseEvent};
+use termion::style::{Invert, NoInvert};
+use tokio::timer::Delay;
+use xrl::{
+    AvailableLanguages, AvailableThemes, Client, ConfigChanged, FindStatus, LanguageChanged,
+    Position, ScrollTo, Style, ThemeChanged, Update, ViewId, XiNotification,
+};
 
-use core::CoreEvent;
-use widgets::{View, ViewClient};
+use core::{
+    copy_to_system_clipboard, read_system_clipboard, resolve_path, AutoReload, BellMode,
+    BufferCycleOrder, CoreEvent, FileLocation, FileWatcher, FindConfig, LineEnding, LineNumberMode,
+    LineTarget, MovePoint, Setting, SortConfig, SplitDirection,
+};
+use widgets::command_prompt::expand_home;
+use widgets::{Quickfix, QuickfixEntry, Symbol, View, ViewClient};
+
+/// How long a status message stays on screen.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long the status line stays inverted for the visual bell.
+const VISUAL_BELL_DURATION: Duration = Duration::from_millis(150);
+
+/// The escape sequence ringing the terminal bell.
+const AUDIBLE_BELL: &str = "\x07";
+
+/// A position to move the cursor to once a new view is created, as a
+/// line and column starting at 0.
+type InitialPosition = Option<(u64, Option<u64>)>;
+
+/// Where a symbol is defined, as a path, and a line and column starting
+/// at 0.
+type Definition = (String, u64, u64);
+
+/// A region of the terminal: the column and row of its top left
+/// corner (from 0), its height and its width.
+type Region = ((u16, u16), u16, u16);
+
+/// The screen divided in two panes, each showing a view. The focused
+/// pane shows the current view.
+#[derive(Debug, Clone, Copy)]
+struct Split {
+    direction: SplitDirection,
+    /// The view shown in the pane that does not have the focus.
+    other: ViewId,
+    /// Whether the focused pane is the first one, at the top or on
+    /// the left.
+    focus_first: bool,
+}
+
+/// Check that `path` can be opened in a view: it must be a readable
+/// file, or not exist yet.
+fn check_openable(path: &str) -> Result<(), String> {
+    match fs::metadata(path) {
+        Ok(ref metadata) if metadata.is_dir() => Err(format!("{} is a directory", path)),
+        Ok(ref metadata) if !metadata.is_file() => Err(format!("{} is not a regular file", path)),
+        Ok(_) => match fs::File::open(path) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("cannot read {}: {}", path, e)),
+        },
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("cannot open {}: {}", path, e)),
+    }
+}
+
+/// The views in the order they were last focused, to cycle through
+/// them like with Alt-Tab.
+#[derive(Debug, Default)]
+struct RecentViews {
+    /// The most recently focused first.
+    order: Vec<ViewId>,
+    /// The order the cycle in progress goes through, frozen when it
+    /// started, and the view it is at. Otherwise each switch would move
+    /// the view switched to to the front, and next-buffer would only
+    /// alternate between the two most recent views.
+    cycle: Option<(Vec<ViewId>, ViewId)>,
+}
+
+impl RecentViews {
+    /// Forget the views that are not `open` anymore.
+    fn retain<F: Fn(&ViewId) -> bool>(&mut self, open: F) {
+        self.order.retain(|id| open(id));
+        if let Some((ref mut order, _)) = self.cycle {
+            order.retain(|id| open(id));
+        }
+    }
+
+    fn touch(&mut self, id: ViewId) {
+        self.order.retain(|recent| *recent != id);
+        self.order.insert(0, id);
+    }
+
+    /// Note that `current` has the focus. Unless a cycle got there, this
+    /// ends the cycle, and the view it ended at then `current` move to
+    /// the front.
+    fn focus(&mut self, current: ViewId) {
+        match self.cycle.take() {
+            Some((order, at)) if at == current => self.cycle = Some((order, at)),
+            Some((_, at)) => {
+                self.touch(at);
+                self.touch(current);
+            }
+            None => self.touch(current),
+        }
+    }
+
+    /// Return the view to switch to from `current`, going through the
+    /// open `views` (in creation order) in the given `order`. A new
+    /// cycle starts unless `current` is where the last one got to.
+    fn cycle(
+        &mut self,
+        order: BufferCycleOrder,
+        views: &[ViewId],
+        current: ViewId,
+        forward: bool,
+    ) -> Option<ViewId> {
+        let cycle = match self.cycle.take() {
+            Some((cycle, at)) if at == current => cycle,
+            _ => cycle_order(order, views, &self.order),
+        };
+        let next = cycle_next(&cycle, current, forward)?;
+        self.cycle = Some((cycle, next));
+        Some(next)
+    }
+}
+
+/// Return the open `views`, given in the order they were created, in
+/// the `order` that `next-buffer` goes through them. In the most
+/// recently used order, the views that were never current, like views
+/// reloaded in the background, come after the others.
+fn cycle_order(order: BufferCycleOrder, views: &[ViewId], recent: &[ViewId]) -> Vec<ViewId> {
+    match order {
+        BufferCycleOrder::Created => views.to_vec(),
+        BufferCycleOrder::Mru => {
+            let never_current = views.iter().filter(|id| !recent.contains(id));
+            recent.iter().chain(never_current).cloned().collect()
+        }
+    }
+}
+
+/// Return the view after `current` in `order`, or before it if
+/// `forward` is not set. The views wrap around: the first one comes
+/// after the last one.
+fn cycle_next(order: &[ViewId], current: ViewId, forward: bool) -> Option<ViewId> {
+    let idx = order.iter().position(|id| *id == current)?;
+    let len = order.len();
+    let next = if forward {
+        (idx + 1) % len
+    } else {
+        (idx + len - 1) % len
+    };
+    Some(order[next])
+}
+
+/// How many closed views are remembered for `reopen`.
+const MAX_CLOSED_VIEWS: usize = 10;
+
+/// The format of the time inserted by `date` when none is given, as
+/// in ISO 8601.
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%:z";
+
+/// A view that was closed, to open it again with `reopen`.
+#[derive(Debug, Clone)]
+struct ClosedView {
+    file: Option<String>,
+    line: u64,
+    column: u64,
+}
+
+/// A position remembered with the `mark` command.
+#[derive(Debug, Clone, Copy)]
+struct Mark {
+    view_id: ViewId,
+    line: u64,
+    column: u64,
+}
 
 /// The main interface to xi-core
 pub struct Editor {
     /// Channel from which the responses to "new_view" requests are
     /// received. Upon receiving a `ViewId`, the `Editdor` creates a
     /// new view.
-    pub new_view_rx: UnboundedReceiver<(ViewId, Option<String>)>,
+    pub new_view_rx: UnboundedReceiver<(ViewId, Option<String>, InitialPosition)>,
 
     /// Channel into which the responses to "new_view" requests are
     /// sent, when they are received from the core.
-    pub new_view_tx: UnboundedSender<(ViewId, Option<String>)>,
+    pub new_view_tx: UnboundedSender<(ViewId, Option<String>, InitialPosition)>,
+
+    /// Channel into which the responses to "goto_definition" requests
+    /// are sent, with `None` if no definition was found.
+    definition_tx: UnboundedSender<Option<Definition>>,
+
+    /// Channel from which the responses to "goto_definition" requests
+    /// are received.
+    definition_rx: UnboundedReceiver<Option<Definition>>,
+
+    /// Channel into which the responses to "document_symbols" requests
+    /// are sent, with the view they were asked for, and `None` if no
+    /// plugin answered.
+    symbols_tx: UnboundedSender<(ViewId, Option<Vec<Symbol>>)>,
+
+    /// Channel from which the responses to "document_symbols" requests
+    /// are received.
+    symbols_rx: UnboundedReceiver<(ViewId, Option<Vec<Symbol>>)>,
+
+    /// The symbols of the current view, waiting for the TUI to list
+    /// them in the outline.
+    outline: Option<Vec<Symbol>>,
 
     /// Store the events that we cannot process right away.
     ///
@@ -44,6 +243,114 @@ pub struct Editor {
 
     pub size: (u16, u16),
     pub styles: HashMap<u64, Style>,
+
+    /// A message for the user, displayed on the bottom line of the
+    /// terminal, with the time it was set at.
+    pub status: Option<(String, Instant)>,
+
+    /// A timer that fires when the status message should be cleared.
+    status_timer: Option<Delay>,
+
+    /// How a command that can't run is signaled.
+    pub bell_mode: BellMode,
+
+    /// Whether the bell was rung since the last render.
+    bell_pending: bool,
+
+    /// A timer that fires when the visual bell should stop, while the
+    /// status line is inverted.
+    bell_timer: Option<Delay>,
+
+    /// A list of locations, displayed in a panel above the status
+    /// line.
+    pub quickfix: Quickfix,
+
+    /// The themes available, as reported by the core.
+    pub themes: Vec<String>,
+
+    /// The name of the current theme.
+    pub theme: Option<String>,
+
+    /// The syntaxes available, as reported by the core.
+    languages: Vec<String>,
+
+    /// How many lines are kept visible above and below the cursor.
+    pub scroll_off: u16,
+
+    /// Whether trailing whitespace is trimmed before saving.
+    pub trim_on_save: bool,
+
+    /// Whether saving makes the buffer end with exactly one newline.
+    pub final_newline: bool,
+
+    /// Whether moving to the start of the line (with `move-to bol` or
+    /// Home) stops at its first non-blank character first.
+    pub smart_home: bool,
+
+    /// The positions remembered with the `mark` command, by name.
+    marks: HashMap<char, Mark>,
+
+    /// The text copied or cut from a view, for pasting it back.
+    clipboard: Option<String>,
+
+    /// The text copied or cut to a named register, by name.
+    registers: HashMap<char, String>,
+
+    /// Whether copied text also goes to the system clipboard, and
+    /// pasting takes text copied in other applications.
+    pub use_system_clipboard: bool,
+
+    /// The content of the system clipboard after the last copy. When it
+    /// changes, the text was copied in another application.
+    system_clipboard: Option<String>,
+
+    /// The order in which `next_buffer` and `prev_buffer` go through
+    /// the views.
+    pub buffer_cycle_order: BufferCycleOrder,
+
+    /// The views that were current, the most recently used first. The
+    /// current view is moved to the front when the editor is polled,
+    /// and before cycling through the views, which is when focus
+    /// changes are noticed.
+    recent_views: RecentViews,
+
+    /// The view that had the focus when focus changes were last
+    /// noticed.
+    focused: Option<ViewId>,
+
+    /// The views closed last, the most recent last.
+    closed: Vec<ClosedView>,
+
+    /// The second pane, if the screen is split.
+    split: Option<Split>,
+
+    /// The direction of the split waiting for its view to be created,
+    /// and the view to show in the other pane.
+    pending_split: Option<(SplitDirection, ViewId)>,
+
+    /// The views searched by `grep` whose matches were not reported
+    /// yet. The quickfix list is filled once there are none left.
+    grep_pending: Vec<ViewId>,
+
+    /// The matching lines found by `grep` in the views that reported
+    /// their matches.
+    grep_entries: Vec<QuickfixEntry>,
+
+    /// What happens when the file of a view changes on disk.
+    auto_reload: AutoReload,
+
+    /// Checks the files of the views for changes, unless
+    /// `auto_reload` is off.
+    watcher: Option<FileWatcher>,
+
+    /// The views whose file changed on disk, waiting to be asked
+    /// whether to reload them.
+    changed_on_disk: Vec<ViewId>,
+
+    /// The files and the ids of the views being reloaded, in the order
+    /// they were opened again. Each new view takes the place of the old
+    /// one, in its pane and in the marks and quickfix list.
+    reloads: Vec<(String, ViewId)>,
 }
 
 /// Methods for general use.
@@ -51,17 +358,188 @@ impl Editor {
     pub fn new(client: Client) -> Editor {
         let mut styles = HashMap::new();
         styles.insert(0, Default::default());
-        let (new_view_tx, new_view_rx) = mpsc::unbounded::<(ViewId, Option<String>)>();
+        let (new_view_tx, new_view_rx) =
+            mpsc::unbounded::<(ViewId, Option<String>, InitialPosition)>();
+        let (definition_tx, definition_rx) = mpsc::unbounded();
+        let (symbols_tx, symbols_rx) = mpsc::unbounded();
 
         Editor {
             new_view_rx,
             new_view_tx,
+            definition_tx,
+            definition_rx,
+            symbols_tx,
+            symbols_rx,
+            outline: None,
             delayed_events: Vec::new(),
             views: IndexMap::new(),
             current_view: ViewId(0),
             client,
             size: (0, 0),
             styles,
+            status: None,
+            status_timer: None,
+            bell_mode: BellMode::default(),
+            bell_pending: false,
+            bell_timer: None,
+            quickfix: Quickfix::default(),
+            themes: Vec::new(),
+            theme: None,
+            languages: Vec::new(),
+            scroll_off: 0,
+            trim_on_save: false,
+            final_newline: true,
+            smart_home: false,
+            marks: HashMap::new(),
+            clipboard: None,
+            registers: HashMap::new(),
+            use_system_clipboard: false,
+            system_clipboard: None,
+            buffer_cycle_order: BufferCycleOrder::default(),
+            recent_views: RecentViews::default(),
+            focused: None,
+            closed: Vec::new(),
+            split: None,
+            pending_split: None,
+            grep_pending: Vec::new(),
+            grep_entries: Vec::new(),
+            auto_reload: AutoReload::default(),
+            watcher: Some(FileWatcher::new()),
+            changed_on_disk: Vec::new(),
+            reloads: Vec::new(),
+        }
+    }
+
+    /// Display a message on the status line. The message is cleared
+    /// after a few seconds.
+    pub fn set_status<S: Into<String>>(&mut self, msg: S) {
+        let now = Instant::now();
+        self.status = Some((msg.into(), now));
+        self.status_timer = Some(Delay::new(now + STATUS_TIMEOUT));
+    }
+
+    /// Signal that a command could not run, as chosen with `bell`.
+    pub fn bell(&mut self) {
+        match self.bell_mode {
+            BellMode::Off => {}
+            BellMode::Visual => {
+                self.bell_timer = Some(Delay::new(Instant::now() + VISUAL_BELL_DURATION));
+            }
+            BellMode::Audible => self.bell_pending = true,
+        }
+    }
+
+    pub fn set_auto_reload(&mut self, policy: AutoReload) {
+        self.auto_reload = policy;
+        if policy == AutoReload::Off {
+            self.watcher = None;
+            self.changed_on_disk.clear();
+        } else if self.watcher.is_none() {
+            self.watcher = Some(FileWatcher::new());
+        }
+    }
+
+    /// Reload or queue a question for the views whose file changed on
+    /// disk.
+    fn poll_file_changes(&mut self) {
+        let changed = match self.watcher {
+            Some(ref mut watcher) => {
+                let mut files: Vec<&String> = self.views.values().filter_map(View::file).collect();
+                files.sort();
+                files.dedup();
+                watcher.poll_changes(&files)
+            }
+            None => return,
+        };
+        for file in changed {
+            let ids: Vec<ViewId> = self
+                .views
+                .iter()
+                .filter(|(_, view)| view.file() == Some(&file))
+                .map(|(id, _)| *id)
+                .collect();
+            for id in ids {
+                if !self.views.contains_key(&id) {
+                    // Reloaded along with another view of the file
+                    continue;
+                }
+                let dirty = self.views.get(&id).is_some_and(View::is_dirty);
+                if self.auto_reload == AutoReload::Auto && !dirty {
+                    self.reload(Some(id), false);
+                    self.set_status(format!("reloaded {}, which changed on disk", file));
+                } else if !self.changed_on_disk.contains(&id) {
+                    self.changed_on_disk.push(id);
+                }
+            }
+        }
+    }
+
+    /// Return the next view whose file changed on disk, with its file,
+    /// to ask whether to reload it.
+    pub fn next_changed_on_disk(&mut self) -> Option<(ViewId, String)> {
+        while !self.changed_on_disk.is_empty() {
+            let id = self.changed_on_disk.remove(0);
+            if let Some(file) = self.views.get(&id).and_then(View::file) {
+                return Some((id, file.clone()));
+            }
+        }
+        None
+    }
+
+    fn clear_status(&mut self) {
+        self.status = None;
+        self.status_timer = None;
+    }
+
+    /// The height available to the views. The bottom line of the
+    /// terminal is used for the status line and command prompt, and
+    /// the quickfix panel goes above it.
+    fn view_height(&self) -> u16 {
+        self.size.1.saturating_sub(1 + self.quickfix.height())
+    }
+
+    /// Return the views shown and the region of each, the first pane
+    /// first. The current view takes all the space unless the screen
+    /// is split.
+    fn panes(&self) -> Vec<(ViewId, Region)> {
+        let height = self.view_height();
+        let width = self.size.0;
+        let split = match self.split {
+            Some(split)
+                if split.other != self.current_view && self.views.contains_key(&split.other) =>
+            {
+                split
+            }
+            _ => return vec![(self.current_view, ((0, 0), height, width))],
+        };
+        // A row or a column is left between the panes for the divider
+        let (first, second) = match split.direction {
+            SplitDirection::Horizontal => {
+                let top = height.saturating_sub(1) / 2;
+                let bottom = height.saturating_sub(top + 1);
+                (((0, 0), top, width), ((0, top + 1), bottom, width))
+            }
+            SplitDirection::Vertical => {
+                let left = width.saturating_sub(1) / 2;
+                let right = width.saturating_sub(left + 1);
+                (((0, 0), height, left), ((left + 1, 0), height, right))
+            }
+        };
+        if split.focus_first {
+            vec![(self.current_view, first), (split.other, second)]
+        } else {
+            vec![(split.other, first), (self.current_view, second)]
+        }
+    }
+
+    /// Resize the views shown to the regions of their panes.
+    fn resize_views(&mut self) {
+        for (view_id, (origin, height, width)) in self.panes() {
+            if let Some(view) = self.views.get_mut(&view_id) {
+                view.set_region(origin, height, width);
+            } else {
+                warn!("view {} not found", view_id);
+            }
         }
     }
 }
@@ -77,6 +555,7 @@ impl Future for Editor {
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
         debug!("polling the editor");
+        self.note_current_view();
 
         debug!("handling delayed events");
         if !self.delayed_events.is_empty() {
@@ -86,17 +565,82 @@ impl Future for Editor {
             }
         }
 
+        debug!("polling the status timer");
+        let expired = match self.status_timer {
+            Some(ref mut timer) => match timer.poll() {
+                Ok(Async::Ready(())) => true,
+                Ok(Async::NotReady) => false,
+                Err(e) => {
+                    error!("status timer failed: {:?}", e);
+                    true
+                }
+            },
+            None => false,
+        };
+        if expired {
+            self.clear_status();
+        }
+
+        debug!("polling the visual bell timer");
+        let done = match self.bell_timer {
+            Some(ref mut timer) => match timer.poll() {
+                Ok(Async::Ready(())) => true,
+                Ok(Async::NotReady) => false,
+                Err(e) => {
+                    error!("visual bell timer failed: {:?}", e);
+                    true
+                }
+            },
+            None => false,
+        };
+        if done {
+            self.bell_timer = None;
+        }
+
+        debug!("checking the files for changes on disk");
+        self.poll_file_changes();
+
         debug!("polling 'new_view' responses");
         loop {
             match self.new_view_rx.poll() {
-                Ok(Async::Ready(Some((view_id, file_path)))) => {
+                Ok(Async::Ready(Some((view_id, file_path, position)))) => {
                     info!("creating new view {:?}", view_id);
+                    let reloaded = file_path.as_ref().and_then(|file| {
+                        let idx = self.reloads.iter().position(|r| &r.0 == file)?;
+                        Some(self.reloads.remove(idx).1)
+                    });
+                    if let (Some(file), Some(watcher)) = (file_path.as_ref(), self.watcher.as_mut())
+                    {
+                        // The other views of the file share its buffer,
+                        // which the core didn't read again for this one
+                        let shared = self.views.values().any(|view| view.file() == Some(file));
+                        if reloaded.is_some() || !shared {
+                            watcher.loaded(file);
+                        }
+                    }
                     let client = ViewClient::new(self.client.clone(), view_id);
                     let mut view = View::new(client, file_path);
-                    view.resize(self.size.1);
+                    view.resize(self.view_height(), self.size.0);
+                    view.set_scroll_off(self.scroll_off);
+                    if let Some((line, column)) = position {
+                        view.goto(line, column);
+                    }
                     self.views.insert(view_id, view);
+                    if let Some(old_id) = reloaded {
+                        self.replace_view(old_id, view_id);
+                        self.resize_views();
+                        continue;
+                    }
                     info!("switching to view {:?}", view_id);
                     self.current_view = view_id;
+                    if let Some((direction, other)) = self.pending_split.take() {
+                        self.split = Some(Split {
+                            direction,
+                            other,
+                            focus_first: true,
+                        });
+                    }
+                    self.resize_views();
                 }
                 // We own one of the senders so this cannot happen
                 Ok(Async::Ready(None)) => unreachable!(),
@@ -110,6 +654,16 @@ impl Future for Editor {
                 }
             }
         }
+
+        debug!("polling 'goto_definition' responses");
+        while let Ok(Async::Ready(Some(definition))) = self.definition_rx.poll() {
+            self.show_definition(definition);
+        }
+
+        debug!("polling 'document_symbols' responses");
+        while let Ok(Async::Ready(Some((view_id, symbols)))) = self.symbols_rx.poll() {
+            self.show_symbols(view_id, symbols);
+        }
         Ok(Async::NotReady)
     }
 }
@@ -117,20 +671,45 @@ impl Future for Editor {
 impl Editor {
     /// Handle keyboard and mouse events
     pub fn handle_input(&mut self, event: TermionEvent) {
-        if let Some(view) = self.views.get_mut(&self.current_view) {
+        self.clear_status();
+        if let TermionEvent::Mouse(MouseEvent::Press(_, x, y)) = event {
+            self.focus_pane_at(x, y);
+        }
+        if let TermionEvent::Key(Key::Ctrl('w')) = event {
+            // Go through `save`, which handles views without a file
+            self.save(None);
+            return;
+        }
+        if self.smart_home && event == TermionEvent::Key(Key::Home) {
+            self.move_to(MovePoint::Bol, false);
+            return;
+        }
+        let view = if View::is_edit(&event) {
+            self.editable_view(self.current_view)
+        } else {
+            self.views.get_mut(&self.current_view)
+        };
+        if let Some(view) = view {
             view.handle_input(event)
         }
     }
 
+    /// Return the given view, unless it is read-only, in which case
+    /// the user is told so on the status line.
+    fn editable_view(&mut self, view_id: ViewId) -> Option<&mut View> {
+        let read_only = self.views.get(&view_id)?.is_read_only();
+        if read_only {
+            self.set_status("the buffer is read-only");
+            return None;
+        }
+        self.views.get_mut(&view_id)
+    }
+
     /// Handle terminal size changes
     pub fn handle_resize(&mut self, size: (u16, u16)) {
         info!("setting new terminal size");
         self.size = size;
-        if let Some(view) = self.views.get_mut(&self.current_view) {
-            view.resize(size.1);
-        } else {
-            warn!("view {} not found", self.current_view);
-        }
+        self.resize_views();
     }
 
     /// Handle message from xi-core, that the TUI forwarded us.
@@ -141,6 +720,13 @@ impl Editor {
                 XiNotification::DefStyle(style) => self.def_style(style),
                 XiNotification::ScrollTo(scroll_to) => self.scroll_to(scroll_to),
                 XiNotification::ConfigChanged(config) => self.config_changed(config),
+                XiNotification::FindStatus(status) => self.find_status(status),
+                XiNotification::AvailableThemes(themes) => self.available_themes(themes),
+                XiNotification::ThemeChanged(theme) => self.theme_changed(theme),
+                XiNotification::AvailableLanguages(languages) => {
+                    self.available_languages(languages)
+                }
+                XiNotification::LanguageChanged(language) => self.language_changed(language),
                 _ => info!("ignoring Xi core notification: {:?}", notification),
             },
             CoreEvent::MeasureWidth((_request, _result_tx)) => unimplemented!(),
@@ -150,7 +736,16 @@ impl Editor {
     /// Handle an "update" notification from Xi core.
     fn update(&mut self, update: Update) {
         match self.views.get_mut(&update.view_id) {
-            Some(view) => view.update_cache(update),
+            Some(view) => {
+                let was_dirty = view.is_dirty();
+                view.update_cache(update);
+                // The core marks the buffer as saved once it wrote it
+                if was_dirty && !view.is_dirty() {
+                    if let (Some(file), Some(watcher)) = (view.file(), self.watcher.as_mut()) {
+                        watcher.saved(file);
+                    }
+                }
+            }
             None => self
                 .delayed_events
                 .push(CoreEvent::Notify(XiNotification::Update(update))),
@@ -182,9 +777,164 @@ impl Editor {
         }
     }
 
+    /// Handle an "available_themes" notification from Xi core.
+    fn available_themes(&mut self, themes: AvailableThemes) {
+        self.themes = themes.themes;
+    }
+
+    /// Handle an "available_languages" notification from Xi core.
+    fn available_languages(&mut self, languages: AvailableLanguages) {
+        self.languages = languages.languages;
+    }
+
+    /// Handle a "language_changed" notification from Xi core.
+    fn language_changed(&mut self, language: LanguageChanged) {
+        match self.views.get_mut(&language.view_id) {
+            Some(view) => view.set_language(language.language_id),
+            None => self
+                .delayed_events
+                .push(CoreEvent::Notify(XiNotification::LanguageChanged(language))),
+        }
+    }
+
+    /// Handle a "theme_changed" notification from Xi core.
+    fn theme_changed(&mut self, theme: ThemeChanged) {
+        // Style 0 is the selection. Draw it with the colors of the
+        // theme if it has some, and in reverse video otherwise.
+        let argb = |a: u8, r: u8, g: u8, b: u8| {
+            u32::from(a) << 24 | u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b)
+        };
+        let settings = &theme.theme;
+        let selection = Style {
+            id: 0,
+            fg_color: settings
+                .selection_foreground
+                .map(|c| argb(c.a, c.r, c.g, c.b)),
+            bg_color: settings.selection.map(|c| argb(c.a, c.r, c.g, c.b)),
+            ..Default::default()
+        };
+        self.styles.insert(0, selection);
+        self.theme = Some(theme.name);
+    }
+
+    /// Handle a "find_status" notification from Xi core.
+    fn find_status(&mut self, status: FindStatus) {
+        match self.views.get_mut(&status.view_id) {
+            Some(view) => {
+                let view_id = status.view_id;
+                let msg = view.find_status(status);
+                let grep = self.grep_pending.contains(&view_id);
+                let failed = view_id == self.current_view && !grep && view.search_failed();
+                if let Some(msg) = msg {
+                    self.set_status(msg);
+                }
+                if failed {
+                    self.bell();
+                }
+                self.grep_matches(view_id);
+            }
+            None => self
+                .delayed_events
+                .push(CoreEvent::Notify(XiNotification::FindStatus(status))),
+        }
+    }
+
+    /// Whether a view is open.
+    pub fn has_views(&self) -> bool {
+        !self.views.is_empty()
+    }
+
+    /// The file of the current view, if any.
+    pub fn current_file(&self) -> Option<String> {
+        self.views
+            .get(&self.current_view)
+            .and_then(|view| view.file().cloned())
+    }
+
     /// Spawn a future that sends a "new_view" request to the core,
     /// and forwards the response back to the `Editor`.
     pub fn new_view(&mut self, file_path: Option<String>) {
+        self.new_view_at(file_path, None)
+    }
+
+    /// Open a file given as a location like `src/main.rs:120:5`. The
+    /// cursor is moved to the line and column, if any, once the view
+    /// is created. Relative paths are relative to the directory of the
+    /// current view's file.
+    pub fn open(&mut self, location: Option<String>) {
+        match location {
+            Some(location) => {
+                let location = FileLocation::parse(&location);
+                let current_file = self.current_file();
+                let path = resolve_path(current_file.as_deref(), &location.path);
+                self.open_location(path, &location);
+            }
+            None => self.new_view(None),
+        }
+    }
+
+    /// Open the file whose location is written around the cursor, like
+    /// Vim's `gf`. Unlike `open`, the file must exist, and if it is
+    /// already open its view becomes the current view.
+    pub fn open_file_under_cursor(&mut self) {
+        let location = match self.views.get(&self.current_view) {
+            Some(view) => view.location_under_cursor(),
+            None => return,
+        };
+        let location = match location {
+            Some(location) => FileLocation::parse(&location),
+            None => {
+                self.set_status("no file name under the cursor");
+                return;
+            }
+        };
+        let current_file = self.current_file();
+        let path = expand_home(&location.path)
+            .unwrap_or_else(|| resolve_path(current_file.as_deref(), &location.path));
+        if !Path::new(&path).exists() {
+            self.set_status(format!("{} doesn't exist", path));
+            return;
+        }
+        let open = self
+            .views
+            .iter()
+            .find(|(_, view)| view.file() == Some(&path))
+            .map(|(id, _)| *id);
+        let view_id = match open {
+            Some(view_id) => view_id,
+            None => return self.open_location(path, &location),
+        };
+        if view_id != self.current_view {
+            self.current_view = view_id;
+            self.resize_views();
+        }
+        if let (Some(view), Some(line)) = (self.views.get_mut(&view_id), location.line) {
+            view.goto(
+                line.saturating_sub(1),
+                location.column.map(|c| c.saturating_sub(1)),
+            );
+        }
+    }
+
+    /// Open `path` in a new view, at the position of `location` if it
+    /// has one.
+    fn open_location(&mut self, path: String, location: &FileLocation) {
+        if let Err(msg) = check_openable(&path) {
+            self.set_status(msg);
+            return;
+        }
+        let position = location.line.map(|line| {
+            (
+                line.saturating_sub(1),
+                location.column.map(|c| c.saturating_sub(1)),
+            )
+        });
+        self.new_view_at(Some(path), position)
+    }
+
+    /// Spawn a future that sends a "new_view" request to the core,
+    /// and move the cursor to `position` once the view is created.
+    fn new_view_at(&mut self, file_path: Option<String>, position: InitialPosition) {
         let response_tx = self.new_view_tx.clone();
         let future = self
             .client
@@ -193,7 +943,7 @@ impl Editor {
                 // when we get the response from the core, forward the new
                 // view id to the editor so that the view can be created
                 response_tx
-                    .unbounded_send((id, file_path))
+                    .unbounded_send((id, file_path, position))
                     .unwrap_or_else(|e| error!("failed to send \"new_view\" response: {:?}", e));
                 Ok(())
             })
@@ -204,113 +954,1733 @@ impl Editor {
         tokio::spawn(future);
     }
 
+    /// Re-open the file backing the given view (or the current view)
+    /// from disk, along with the other views of the file. Unless
+    /// `force` is set, this refuses to discard unsaved changes.
+    pub fn reload(&mut self, view_id: Option<ViewId>, force: bool) {
+        let view_id = view_id.unwrap_or(self.current_view);
+        let file = match self.views.get(&view_id) {
+            Some(view) => {
+                if view.is_dirty() && !force {
+                    self.set_status(
+                        "cannot reload: the buffer has unsaved changes (use e! to discard them)",
+                    );
+                    return;
+                }
+                match view.file() {
+                    Some(file) => file.clone(),
+                    None => {
+                        self.set_status("cannot reload a buffer that has no file name");
+                        return;
+                    }
+                }
+            }
+            None => {
+                warn!("cannot reload view {:?}: not found", view_id);
+                return;
+            }
+        };
+
+        if !Path::new(&file).is_file() {
+            self.set_status(format!("cannot reload {}: the file does not exist", file));
+            return;
+        }
+
+        // Xi has no "reload" request, so we close the view and open
+        // the file again. The views of a file share its buffer, which
+        // the core only reads from disk again once they are all
+        // closed. Each new view takes the place of an old one once the
+        // core responds, with the cursor where it was if the file is
+        // still long enough.
+        let mut views: Vec<(ViewId, InitialPosition)> = self
+            .views
+            .iter()
+            .filter(|(_, view)| view.file() == Some(&file))
+            .map(|(id, view)| {
+                let (line, column) = view.cursor_position();
+                (*id, Some((line, Some(column))))
+            })
+            .collect();
+        views.sort_by_key(|(id, _)| *id);
+        for &(id, _) in &views {
+            info!("reloading view {:?} from {}", id, file);
+            self.reloads.push((file.clone(), id));
+            self.views.remove(&id);
+            self.grep_done(id);
+            tokio::spawn(self.client.close_view(id).map_err(|_| ()));
+        }
+        for (_, position) in views {
+            self.new_view_at(Some(file.clone()), position);
+        }
+    }
+
+    /// Make the view `new`, opened to reload `old`, take its place:
+    /// as the current view or in the other pane, and in the marks and
+    /// the quickfix list.
+    fn replace_view(&mut self, old: ViewId, new: ViewId) {
+        if self.current_view == old {
+            info!("switching to view {:?}", new);
+            self.current_view = new;
+        }
+        if let Some(ref mut split) = self.split {
+            if split.other == old {
+                split.other = new;
+            }
+        }
+        for mark in self.marks.values_mut() {
+            if mark.view_id == old {
+                mark.view_id = new;
+            }
+        }
+        self.quickfix.replace_view(old, new);
+    }
+
     /// Spawn a future that sends a "set_theme" notification to the
     /// core for the current view.
     pub fn set_theme(&mut self, theme: &str) {
         tokio::spawn(self.client.set_theme(theme).map_err(|_| ()));
     }
 
-    /// Spawn a future that sends a "save" notification to the core.
-    pub fn save(&mut self, view_id: Option<ViewId>) {
-        match self.views.get_mut(&view_id.unwrap_or(self.current_view)) {
-            Some(view) => view.save(),
-            None => warn!("cannot save view {:?}: not found", &view_id),
+    /// Show the available themes on the status line, with the current
+    /// one in brackets.
+    pub fn list_themes(&mut self) {
+        if self.themes.is_empty() {
+            self.set_status("no theme available");
+            return;
         }
+        let themes: Vec<String> = self
+            .themes
+            .iter()
+            .map(|theme| {
+                if Some(theme) == self.theme.as_ref() {
+                    format!("[{}]", theme)
+                } else {
+                    theme.clone()
+                }
+            })
+            .collect();
+        self.set_status(format!("themes: {}", themes.join(", ")));
     }
 
-    pub fn back(&mut self) {
-        if let Some(view) = self.views.get_mut(&self.current_view) {
-            view.back();
+    /// Highlight the current view with the syntax called `name`,
+    /// ignoring case. The core ignores the names it doesn't know, so
+    /// they are checked against the syntaxes it reported.
+    pub fn set_syntax(&mut self, name: &str) {
+        let language = match self
+            .languages
+            .iter()
+            .find(|language| language.eq_ignore_ascii_case(name))
+        {
+            Some(language) => language.clone(),
+            None if self.languages.is_empty() => name.to_owned(),
+            None => {
+                self.set_status(format!("unknown syntax \"{}\" (see syntaxes)", name));
+                return;
+            }
+        };
+        let view_id = self.current_view;
+        if self.views.contains_key(&view_id) {
+            let f = self.client.set_language(view_id, &language);
+            tokio::spawn(f.map_err(|_| ()));
         }
     }
 
-    pub fn delete(&mut self) {
-        if let Some(view) = self.views.get_mut(&self.current_view) {
-            view.delete();
+    /// Show the available syntaxes on the status line, with the one of
+    /// the current view in brackets.
+    pub fn list_syntaxes(&mut self) {
+        if self.languages.is_empty() {
+            self.set_status("no syntax available");
+            return;
         }
+        let current = self
+            .views
+            .get(&self.current_view)
+            .and_then(|view| view.language().cloned());
+        let languages: Vec<String> = self
+            .languages
+            .iter()
+            .map(|language| {
+                if Some(language) == current.as_ref() {
+                    format!("[{}]", language)
+                } else {
+                    language.clone()
+                }
+            })
+            .collect();
+        self.set_status(format!("syntaxes: {}", languages.join(", ")));
     }
 
-    pub fn next_buffer(&mut self) {
-        if let Some((dex, _, _)) = self.views.get_full(&self.current_view) {
-            if dex + 1 == self.views.len() {
-                if let Some((view, _)) = self.views.get_index(0) {
-                    self.current_view = *view;
-                }
-            } else if let Some((view, _)) = self.views.get_index(dex + 1) {
-                self.current_view = *view;
-            }
+    /// Switch to the theme that comes after the current one in the
+    /// list of available themes, wrapping around.
+    pub fn cycle_theme(&mut self) {
+        if self.themes.len() < 2 {
+            info!("no other theme to cycle to");
+            return;
         }
+        let next = match self.theme {
+            Some(ref current) => match self.themes.iter().position(|t| t == current) {
+                Some(idx) => (idx + 1) % self.themes.len(),
+                None => 0,
+            },
+            None => 0,
+        };
+        let theme = self.themes[next].clone();
+        self.set_theme(&theme);
+        self.set_status(format!("theme: {}", theme));
+        // Don't wait for the core to confirm, so that cycling again
+        // right away moves on to the next theme.
+        self.theme = Some(theme);
     }
 
-    pub fn prev_buffer(&mut self) {
-        if let Some((dex, _, _)) = self.views.get_full(&self.current_view) {
-            if dex == 0 {
-                if let Some((view, _)) = self.views.get_index(self.views.len() - 1) {
-                    self.current_view = *view;
+    /// Spawn a future that sends a "save" notification to the core.
+    pub fn save(&mut self, view_id: Option<ViewId>) {
+        let id = view_id.unwrap_or(self.current_view);
+        let file = match self.views.get(&id) {
+            Some(view) => match view.file() {
+                Some(file) => file.clone(),
+                None => {
+                    self.set_status("the buffer has no file name, use save-as <file>");
+                    return;
                 }
-            } else if let Some((view, _)) = self.views.get_index(dex - 1) {
-                self.current_view = *view;
+            },
+            None => {
+                warn!("cannot save view {:?}: not found", &view_id);
+                return;
             }
+        };
+        let (trim, final_newline) = (self.trim_on_save, self.final_newline);
+        if let Some(view) = self.editable_view(id) {
+            view.clean_and_save(trim, final_newline);
+            self.saving(&file);
         }
     }
 
-    pub fn move_left(&mut self) {
-        if let Some(view) = self.views.get_mut(&self.current_view) {
-            view.move_left();
+    /// Save the current view to `path`, which becomes the file backing
+    /// the view. Relative paths are relative to the directory of the
+    /// current file, like for `open`.
+    pub fn save_as(&mut self, path: &str) {
+        let path = resolve_path(self.current_file().as_deref(), path);
+        let (trim, final_newline) = (self.trim_on_save, self.final_newline);
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.set_file(path.clone());
+            view.clean_and_save(trim, final_newline);
+            self.saving(&path);
         }
     }
 
-    pub fn move_right(&mut self) {
-        if let Some(view) = self.views.get_mut(&self.current_view) {
-            view.move_right();
+    /// Tell the watcher that `file` is being saved, so that the change
+    /// is not taken for an external one.
+    fn saving(&mut self, file: &str) {
+        if let Some(ref mut watcher) = self.watcher {
+            watcher.saving(file);
         }
     }
 
-    pub fn move_up(&mut self) {
-        if let Some(view) = self.views.get_mut(&self.current_view) {
-            view.move_up();
-        }
+    /// Whether the given view (or the current view) has unsaved
+    /// changes.
+    pub fn is_dirty(&self, view_id: Option<ViewId>) -> bool {
+        self.views
+            .get(&view_id.unwrap_or(self.current_view))
+            .is_some_and(View::is_dirty)
     }
 
-    pub fn move_down(&mut self) {
-        if let Some(view) = self.views.get_mut(&self.current_view) {
-            view.move_down();
+    /// Return the names of the views that have unsaved changes.
+    pub fn dirty_buffers(&self) -> Vec<String> {
+        self.views
+            .iter()
+            .filter(|(_, view)| view.is_dirty())
+            .map(|(id, view)| match view.file() {
+                Some(file) => file.clone(),
+                None => format!("{} (no file name)", id),
+            })
+            .collect()
+    }
+
+    /// Save all the views backed by a file. Return `false`, and list
+    /// them on the status line, if some views could not be saved:
+    /// views without a file, and read-only views with unsaved changes.
+    pub fn save_all(&mut self) -> bool {
+        let mut unsaved = Vec::new();
+        let (trim, final_newline) = (self.trim_on_save, self.final_newline);
+        for (id, view) in &mut self.views {
+            match view.file() {
+                None => unsaved.push(format!("{} (no file name)", id)),
+                Some(file) if view.is_read_only() => {
+                    if view.is_dirty() {
+                        unsaved.push(format!("{} (read-only)", file));
+                    }
+                }
+                Some(file) => {
+                    if let Some(ref mut watcher) = self.watcher {
+                        watcher.saving(file);
+                    }
+                    view.clean_and_save(trim, final_newline);
+                }
+            }
+        }
+        if unsaved.is_empty() {
+            true
+        } else {
+            self.set_status(format!("not saved: {}", unsaved.join(", ")));
+            false
         }
     }
 
-    pub fn page_down(&mut self) {
-        if let Some(view) = self.views.get_mut(&self.current_view) {
-            view.page_down();
+    /// Make the given view (or the current view) read-only, or
+    /// writable again.
+    pub fn toggle_read_only(&mut self, view_id: Option<ViewId>) {
+        let msg = match self.views.get_mut(&view_id.unwrap_or(self.current_view)) {
+            Some(view) => {
+                view.toggle_read_only();
+                if view.is_read_only() {
+                    "read-only"
+                } else {
+                    "writable"
+                }
+            }
+            None => {
+                warn!(
+                    "cannot toggle read-only mode for view {:?}: not found",
+                    &view_id
+                );
+                return;
+            }
+        };
+        self.set_status(msg);
+    }
+
+    /// Enable or disable soft wrapping in the given view (or the
+    /// current view).
+    pub fn toggle_wrap(&mut self, view_id: Option<ViewId>) {
+        let msg = match self.views.get_mut(&view_id.unwrap_or(self.current_view)) {
+            Some(view) => {
+                view.toggle_wrap();
+                if view.is_wrapped() {
+                    "wrapping long lines"
+                } else {
+                    "not wrapping long lines"
+                }
+            }
+            None => {
+                warn!("cannot toggle wrapping for view {:?}: not found", &view_id);
+                return;
+            }
+        };
+        self.set_status(msg);
+    }
+
+    /// Insert pasted text in the current view.
+    pub fn paste(&mut self, text: &str) {
+        self.clear_status();
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.insert_str(text);
         }
     }
 
-    pub fn page_up(&mut self) {
+    pub fn select_line(&mut self) {
         if let Some(view) = self.views.get_mut(&self.current_view) {
-            view.page_up();
+            view.select_line();
         }
     }
 
-    pub fn toggle_line_numbers(&mut self) {
+    pub fn select_word(&mut self) {
         if let Some(view) = self.views.get_mut(&self.current_view) {
-            view.toggle_line_numbers();
+            view.select_word();
         }
     }
-}
 
-/// Methods ment to be called by the tui struct
-impl Editor {
-    // We render if:
-    //  - the current view is dirty
+    /// Copy the lines that have a cursor or a selection.
+    pub fn copy_line(&mut self) {
+        let text = match self.views.get(&self.current_view) {
+            Some(view) => view.copy_lines(),
+            None => return,
+        };
+        self.set_clipboard(text);
+    }
+
+    /// Cut the lines that have a cursor or a selection.
+    pub fn cut_line(&mut self) {
+        let text = match self.editable_view(self.current_view) {
+            Some(view) => view.cut_lines(),
+            None => return,
+        };
+        self.set_clipboard(text);
+    }
+
+    fn set_clipboard(&mut self, text: Option<String>) {
+        match text {
+            Some(text) => {
+                let nb_lines = text.lines().count();
+                if self.use_system_clipboard {
+                    // Without a system clipboard, only our own copy is
+                    // kept.
+                    self.system_clipboard = if copy_to_system_clipboard(&text) {
+                        Some(text.clone())
+                    } else {
+                        read_system_clipboard()
+                    };
+                }
+                self.clipboard = Some(text);
+                self.set_status(format!("{} line(s) copied", nb_lines));
+            }
+            None => self.set_status("some lines are not loaded yet"),
+        }
+    }
+
+    /// Copy the lines that have a cursor or a selection to the register
+    /// `name`. The clipboard is left alone.
+    pub fn copy_to_register(&mut self, name: char) {
+        let text = match self.views.get(&self.current_view) {
+            Some(view) => view.copy_lines(),
+            None => return,
+        };
+        self.set_register(name, text);
+    }
+
+    /// Cut the lines that have a cursor or a selection to the register
+    /// `name`.
+    pub fn cut_to_register(&mut self, name: char) {
+        let text = match self.editable_view(self.current_view) {
+            Some(view) => view.cut_lines(),
+            None => return,
+        };
+        self.set_register(name, text);
+    }
+
+    fn set_register(&mut self, name: char, text: Option<String>) {
+        match text {
+            Some(text) => {
+                let nb_lines = text.lines().count();
+                self.registers.insert(name, text);
+                self.set_status(format!("{} line(s) copied to register {}", nb_lines, name));
+            }
+            None => self.set_status("some lines are not loaded yet"),
+        }
+    }
+
+    /// Insert the text of the register `name` at the cursor.
+    pub fn paste_register(&mut self, name: char) {
+        let text = match self.registers.get(&name) {
+            Some(text) => text.clone(),
+            None => {
+                self.set_status(format!("register {} is empty", name));
+                return;
+            }
+        };
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.insert_str(&text);
+        }
+    }
+
+    pub fn toggle_system_clipboard(&mut self) {
+        self.use_system_clipboard = !self.use_system_clipboard;
+        self.system_clipboard = None;
+        if self.use_system_clipboard {
+            self.set_status("using the system clipboard");
+        } else {
+            self.set_status("not using the system clipboard");
+        }
+    }
+
+    /// Insert the text last copied or cut at the cursor. With the
+    /// system clipboard enabled, text copied in another application
+    /// since then is inserted instead.
+    pub fn paste_clipboard(&mut self) {
+        if self.use_system_clipboard {
+            if let Some(text) = read_system_clipboard() {
+                if self.system_clipboard.as_ref() != Some(&text) {
+                    self.system_clipboard = Some(text.clone());
+                    self.clipboard = Some(text);
+                }
+            }
+        }
+        let text = match self.clipboard {
+            Some(ref text) => text.clone(),
+            None => {
+                self.set_status("nothing to paste");
+                return;
+            }
+        };
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.insert_str(&text);
+        }
+    }
+
+    pub fn back(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.back();
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.delete();
+        }
+    }
+
+    /// Collapse the cursors of the current view, or else clear its
+    /// selection. Return `false` if there was nothing to dismiss.
+    pub fn escape(&mut self) -> bool {
+        match self.views.get_mut(&self.current_view) {
+            Some(view) => view.escape(),
+            None => false,
+        }
+    }
+
+    pub fn clear_search_highlight(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.clear_search_highlight();
+        }
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.undo();
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.redo();
+        }
+    }
+
+    pub fn delete_to_eol(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.delete_to_eol();
+        }
+    }
+
+    pub fn delete_to_bol(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.delete_to_bol();
+        }
+    }
+
+    pub fn delete_word(&mut self, forward: bool) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.delete_word(forward);
+        }
+    }
+
+    /// Insert the current local time at each cursor of the current
+    /// view, formatted with `format` (strftime syntax) if given.
+    pub fn insert_date_time(&mut self, format: Option<&str>) {
+        let now = Local::now();
+        let text = now
+            .format(format.unwrap_or(DEFAULT_DATE_FORMAT))
+            .to_string();
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.insert_str(&text);
+        }
+    }
+
+    pub fn transpose(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.transpose();
+        }
+    }
+
+    pub fn duplicate_line(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.duplicate_line();
+        }
+    }
+
+    /// Display the number of lines, words and characters of the
+    /// current view.
+    pub fn buffer_stats(&mut self) {
+        let msg = match self.views.get(&self.current_view) {
+            Some(view) => match view.stats() {
+                (stats, true) => stats.to_string(),
+                (stats, false) => format!("{} (lines in cache only)", stats),
+            },
+            None => return,
+        };
+        self.set_status(msg);
+    }
+
+    pub fn swap_selection_ends(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.swap_selection_ends();
+        }
+    }
+
+    pub fn jump_back(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.jump_back();
+        }
+    }
+
+    pub fn jump_forward(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.jump_forward();
+        }
+    }
+
+    pub fn goto_last_edit(&mut self) {
+        let moved = match self.views.get_mut(&self.current_view) {
+            Some(view) => view.goto_last_edit(),
+            None => return,
+        };
+        if !moved {
+            self.set_status("no edit made in this buffer yet");
+        }
+    }
+
+    pub fn move_to_matching_indent(&mut self, forward: bool, extend: bool) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.move_to_matching_indent(forward, extend);
+        }
+    }
+
+    pub fn goto_matching_bracket(&mut self, extend: bool) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.goto_matching_bracket(extend);
+        }
+    }
+
+    pub fn set_line_ending(&mut self, ending: LineEnding) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.set_line_ending(ending);
+        }
+    }
+
+    pub fn trim_trailing_whitespace(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.trim_trailing_whitespace();
+        }
+    }
+
+    /// Enable or disable trimming trailing whitespace before saving.
+    pub fn toggle_trim_on_save(&mut self) {
+        self.trim_on_save = !self.trim_on_save;
+        let msg = if self.trim_on_save {
+            "trimming trailing whitespace on save"
+        } else {
+            "not trimming trailing whitespace on save"
+        };
+        self.set_status(msg);
+    }
+
+    pub fn set_final_newline(&mut self, enabled: bool) {
+        self.final_newline = enabled;
+        let msg = if enabled {
+            "ending buffers with exactly one newline on save"
+        } else {
+            "leaving the final newline of buffers as is on save"
+        };
+        self.set_status(msg);
+    }
+
+    pub fn sort_lines(&mut self, config: &SortConfig) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.sort_lines(config);
+        }
+    }
+
+    pub fn join_lines(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.join_lines();
+        }
+    }
+
+    pub fn toggle_case(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.toggle_case();
+        }
+    }
+
+    pub fn upper_case(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.upper_case();
+        }
+    }
+
+    pub fn lower_case(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.lower_case();
+        }
+    }
+
+    pub fn surround(&mut self, open: &str, close: &str) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.surround(open, close);
+        }
+    }
+
+    pub fn increment_number(&mut self, delta: i64) {
+        let incremented = match self.editable_view(self.current_view) {
+            Some(view) => view.increment_number(delta),
+            None => return,
+        };
+        if !incremented {
+            self.set_status("no number under or after the cursor");
+        }
+    }
+
+    pub fn reindent(&mut self) {
+        let reindented = match self.editable_view(self.current_view) {
+            Some(view) => view.reindent(),
+            None => return,
+        };
+        if !reindented {
+            self.set_status("the indentation is already consistent");
+        }
+    }
+
+    pub fn toggle_comment(&mut self, token: &str) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.toggle_comment(token);
+        }
+    }
+
+    pub fn indent(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.indent();
+        }
+    }
+
+    pub fn unindent(&mut self) {
+        if let Some(view) = self.editable_view(self.current_view) {
+            view.unindent();
+        }
+    }
+
+    pub fn goto_line(&mut self, target: LineTarget) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.goto_line_target(target);
+        }
+    }
+
+    pub fn goto_position(&mut self, target: LineTarget, column: u64) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.goto_target_position(target, column);
+        }
+    }
+
+    /// Show the position typed in the goto prompt.
+    pub fn preview_goto(&mut self, target: LineTarget, column: Option<u64>) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.preview_goto(target, column);
+        }
+    }
+
+    pub fn cancel_goto_preview(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.cancel_goto_preview();
+        }
+    }
+
+    pub fn add_cursor_at_line(&mut self, line: u64) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.add_cursor_at_line(line);
+        }
+    }
+
+    /// Set how many lines are kept visible above and below the cursor,
+    /// in all the views.
+    pub fn set_scroll_off(&mut self, scroll_off: u16) {
+        self.scroll_off = scroll_off;
+        for view in self.views.values_mut() {
+            view.set_scroll_off(scroll_off);
+        }
+    }
+
+    pub fn set_tab_width(&mut self, width: u16) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.set_tab_width(width);
+        }
+    }
+
+    /// Apply a setting changed with `set`.
+    pub fn set(&mut self, setting: Setting) {
+        match setting {
+            Setting::TabWidth(width) => self.set_tab_width(width),
+            Setting::Wrap(wrap) => {
+                let wrapped = self.views.get(&self.current_view).map(View::is_wrapped);
+                if wrapped.is_some_and(|wrapped| wrapped != wrap) {
+                    self.toggle_wrap(None);
+                }
+            }
+            Setting::LineNumbers(mode) => self.set_line_number_mode(mode),
+            Setting::ScrollOff(lines) => self.set_scroll_off(lines),
+            Setting::Theme(theme) => self.set_theme(&theme),
+            Setting::TranslateTabsToSpaces(enabled) => self.set_expand_tabs(enabled),
+            Setting::SmartHome(enabled) => self.smart_home = enabled,
+        }
+    }
+
+    /// Make Tab insert spaces instead of a tab character. The core
+    /// sends the new setting to every view with "config_changed".
+    pub fn set_expand_tabs(&mut self, enabled: bool) {
+        // The value the core expects is built from JSON, as xrl takes
+        // changes as a raw value.
+        let changes = format!("{{\"translate_tabs_to_spaces\": {}}}", enabled);
+        match changes.parse() {
+            Ok(changes) => {
+                let f = self.client.modify_user_config("general", changes);
+                tokio::spawn(f.map_err(|_| ()));
+            }
+            Err(e) => error!("failed to build config changes: {:?}", e),
+        }
+    }
+
+    pub fn find(&mut self, config: &FindConfig) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.find(config);
+        }
+    }
+
+    /// Show the matches of a search while it is being typed.
+    pub fn preview_find(&mut self, config: &FindConfig) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.preview_find(config);
+        }
+    }
+
+    pub fn cancel_find_preview(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.cancel_find_preview();
+        }
+    }
+
+    pub fn find_next(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.find_next();
+        }
+    }
+
+    pub fn find_prev(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.find_prev();
+        }
+    }
+
+    /// Place a cursor at every match of the search in the current
+    /// view.
+    pub fn find_all(&mut self) {
+        let msg = match self.views.get_mut(&self.current_view) {
+            Some(view) => view.find_all(),
+            None => return,
+        };
+        if let Some(msg) = msg {
+            self.set_status(msg);
+        }
+    }
+
+    /// Search every open buffer for `config.term`. The matching lines
+    /// are listed in the quickfix panel once all the buffers reported
+    /// their matches. A buffer shown in several panes is searched
+    /// once, from its first view.
+    pub fn find_in_files(&mut self, config: &FindConfig) {
+        if self.views.is_empty() {
+            return;
+        }
+        let mut files = Vec::new();
+        self.grep_pending.clear();
+        self.grep_entries.clear();
+        for (id, view) in &self.views {
+            if let Some(file) = view.file() {
+                if files.contains(&file) {
+                    continue;
+                }
+                files.push(file);
+            }
+            self.grep_pending.push(*id);
+        }
+        for id in &self.grep_pending {
+            if let Some(view) = self.views.get_mut(id) {
+                view.search(config);
+            }
+        }
+        let msg = format!("searching {} buffers", self.grep_pending.len());
+        self.set_status(msg);
+    }
+
+    /// Keep the matches of the view if it is searched by `grep`, and
+    /// go back to the search it had before.
+    fn grep_matches(&mut self, view_id: ViewId) {
+        if !self.grep_pending.contains(&view_id) {
+            return;
+        }
+        if let Some(view) = self.views.get_mut(&view_id) {
+            let file = view.file().cloned();
+            let entries = view
+                .end_search()
+                .into_iter()
+                .map(|(line, text)| QuickfixEntry {
+                    view_id,
+                    file: file.clone(),
+                    line,
+                    text,
+                });
+            self.grep_entries.extend(entries);
+        }
+        self.grep_done(view_id);
+    }
+
+    /// Stop waiting for the matches of `view_id`, which were reported
+    /// or won't be because the view was closed.
+    fn grep_done(&mut self, view_id: ViewId) {
+        if let Some(idx) = self.grep_pending.iter().position(|id| *id == view_id) {
+            self.grep_pending.remove(idx);
+            if self.grep_pending.is_empty() {
+                self.show_grep_results();
+            }
+        }
+    }
+
+    /// Fill the quickfix list with the matching lines found by `grep`,
+    /// in the order the views were opened.
+    fn show_grep_results(&mut self) {
+        let mut entries = mem::take(&mut self.grep_entries);
+        if entries.is_empty() {
+            self.set_status("no matches in the open buffers");
+            return;
+        }
+        let views = &self.views;
+        entries.sort_by_key(|entry| {
+            let idx = views.get_full(&entry.view_id).map(|(idx, _, _)| idx);
+            (idx, entry.line)
+        });
+        let mut buffers: Vec<ViewId> = entries.iter().map(|entry| entry.view_id).collect();
+        buffers.dedup();
+        let msg = format!(
+            "{} matching lines in {} buffers",
+            entries.len(),
+            buffers.len()
+        );
+        self.quickfix.set_entries(entries);
+        self.quickfix.visible = true;
+        self.resize_views();
+        self.set_status(msg);
+    }
+
+    /// Show or hide the quickfix panel. When it is shown, it is filled
+    /// with the results of the last search in the current view.
+    pub fn toggle_quickfix(&mut self) {
+        if self.quickfix.visible {
+            self.quickfix.visible = false;
+        } else {
+            let entries: Vec<QuickfixEntry> = match self.views.get(&self.current_view) {
+                Some(view) => view
+                    .find_matches()
+                    .into_iter()
+                    .map(|(line, text)| QuickfixEntry {
+                        view_id: self.current_view,
+                        file: view.file().cloned(),
+                        line,
+                        text,
+                    })
+                    .collect(),
+                None => return,
+            };
+            if entries.is_empty() {
+                self.set_status("no search results");
+                return;
+            }
+            self.quickfix.set_entries(entries);
+            self.quickfix.visible = true;
+        }
+        self.resize_views();
+    }
+
+    /// Go to the next (or previous) location of the quickfix list.
+    pub fn quickfix_move(&mut self, forward: bool) {
+        let entry = if forward {
+            self.quickfix.next()
+        } else {
+            self.quickfix.prev()
+        };
+        let entry = match entry {
+            Some(entry) => entry.clone(),
+            None => {
+                self.set_status("the quickfix list is empty");
+                return;
+            }
+        };
+        if self.views.contains_key(&entry.view_id) {
+            self.current_view = entry.view_id;
+            self.resize_views();
+            if let Some(view) = self.views.get_mut(&self.current_view) {
+                view.goto_line(entry.line);
+            }
+        } else if entry.file.is_some() {
+            info!("view {:?} was closed, opening it again", entry.view_id);
+            self.new_view_at(entry.file, Some((entry.line, None)));
+        } else {
+            self.set_status("the buffer of this location was closed");
+        }
+    }
+
+    /// Ask the core for the definition of the symbol under the cursor.
+    /// Only language plugins can answer, with a location or a list of
+    /// locations, each made of a path, a line and a column.
+    pub fn goto_definition(&mut self) {
+        let (line, column) = match self.views.get(&self.current_view) {
+            Some(view) => view.cursor_position(),
+            None => return,
+        };
+        let response_tx = self.definition_tx.clone();
+        let future = self
+            .client
+            .edit_request(
+                self.current_view,
+                "goto_definition",
+                Some(Position(line, column)),
+            )
+            .then(move |response| {
+                if let Err(ref e) = response {
+                    info!("\"goto_definition\" request failed: {:?}", e);
+                }
+                let definition = response.ok().and_then(|value| {
+                    // Of several locations, take the first one
+                    let location = if value.is_array() {
+                        value.get(0)?.clone()
+                    } else {
+                        value
+                    };
+                    let path = location.get("path")?.as_str()?.to_owned();
+                    let line = location.get("line")?.as_u64()?;
+                    let column = location.get("column").and_then(|c| c.as_u64());
+                    Some((path, line, column.unwrap_or(0)))
+                });
+                response_tx.unbounded_send(definition).unwrap_or_else(|e| {
+                    error!("failed to send \"goto_definition\" response: {:?}", e)
+                });
+                Ok(())
+            });
+        tokio::spawn(future);
+    }
+
+    /// Go to a definition returned by the core, in the view of its file
+    /// if there is one, or else in a new view.
+    fn show_definition(&mut self, definition: Option<Definition>) {
+        let (path, line, column) = match definition {
+            Some(definition) => definition,
+            None => {
+                self.set_status("no definition found");
+                return;
+            }
+        };
+        let view_id = self
+            .views
+            .iter()
+            .find(|(_, view)| view.file().map(Path::new) == Some(Path::new(&path)))
+            .map(|(id, _)| *id);
+        match view_id {
+            Some(view_id) => {
+                if view_id != self.current_view {
+                    self.current_view = view_id;
+                    self.resize_views();
+                }
+                if let Some(view) = self.views.get_mut(&self.current_view) {
+                    view.goto_position(line, column);
+                }
+            }
+            None => self.new_view_at(Some(path), Some((line, Some(column)))),
+        }
+    }
+
+    /// Ask the core for the symbols of the current view, to list them
+    /// in the outline. Only language plugins can answer, with a list of
+    /// symbols, each made of a name, a line and a column.
+    pub fn goto_symbol(&mut self) {
+        if !self.views.contains_key(&self.current_view) {
+            return;
+        }
+        let view_id = self.current_view;
+        let response_tx = self.symbols_tx.clone();
+        let future = self
+            .client
+            .edit_request(view_id, "document_symbols", None::<()>)
+            .then(move |response| {
+                if let Err(ref e) = response {
+                    info!("\"document_symbols\" request failed: {:?}", e);
+                }
+                let symbols = response.ok().and_then(|value| {
+                    let symbols = value
+                        .as_array()?
+                        .iter()
+                        .filter_map(|symbol| {
+                            let name = symbol.get("name")?.as_str()?.to_owned();
+                            let line = symbol.get("line")?.as_u64()?;
+                            let column = symbol.get("column").and_then(|c| c.as_u64());
+                            Some(Symbol {
+                                name,
+                                line,
+                                column: column.unwrap_or(0),
+                            })
+                        })
+                        .collect::<Vec<Symbol>>();
+                    if symbols.is_empty() {
+                        None
+                    } else {
+                        Some(symbols)
+                    }
+                });
+                response_tx
+                    .unbounded_send((view_id, symbols))
+                    .unwrap_or_else(|e| {
+                        error!("failed to send \"document_symbols\" response: {:?}", e)
+                    });
+                Ok(())
+            });
+        tokio::spawn(future);
+    }
+
+    /// Keep the symbols returned by the core for the outline, or find
+    /// them by their keywords if no plugin answered. They are dropped
+    /// if the view is not the current one anymore.
+    fn show_symbols(&mut self, view_id: ViewId, symbols: Option<Vec<Symbol>>) {
+        if view_id != self.current_view {
+            return;
+        }
+        let symbols = match (symbols, self.views.get(&view_id)) {
+            (Some(symbols), _) => symbols,
+            (None, Some(view)) => view.symbols(),
+            (None, None) => return,
+        };
+        if symbols.is_empty() {
+            self.set_status("no symbol found");
+        } else {
+            self.outline = Some(symbols);
+        }
+    }
+
+    /// Return the symbols to list in the outline, once they are known.
+    pub fn take_outline(&mut self) -> Option<Vec<Symbol>> {
+        self.outline.take()
+    }
+
+    /// Move the cursor of the current view to `line` and `column`.
+    pub fn jump_to(&mut self, line: u64, column: u64) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.goto_position(line, column);
+        }
+    }
+
+    /// Remember the cursor position of the current view as `name`.
+    pub fn set_mark(&mut self, name: char) {
+        let (line, column) = match self.views.get(&self.current_view) {
+            Some(view) => view.cursor_position(),
+            None => return,
+        };
+        let mark = Mark {
+            view_id: self.current_view,
+            line,
+            column,
+        };
+        self.marks.insert(name, mark);
+        self.set_status(format!("mark '{}' set", name));
+    }
+
+    /// Go to the position remembered as `name`. The buffer may have
+    /// changed since the mark was set, so the position is clamped to
+    /// the current content.
+    pub fn goto_mark(&mut self, name: char) {
+        let mark = match self.marks.get(&name) {
+            Some(mark) => *mark,
+            None => {
+                self.set_status(format!("mark '{}' is not set", name));
+                return;
+            }
+        };
+        if !self.views.contains_key(&mark.view_id) {
+            self.set_status(format!("the buffer of mark '{}' was closed", name));
+            return;
+        }
+        if mark.view_id != self.current_view {
+            self.current_view = mark.view_id;
+            self.resize_views();
+        }
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.goto_position(mark.line, mark.column);
+        }
+    }
+
+    /// Show the open views on the status line, with the current one
+    /// in brackets and the ones with unsaved changes marked [+].
+    pub fn list_buffers(&mut self) {
+        let buffers: Vec<String> = self
+            .views
+            .iter()
+            .map(|(id, view)| {
+                let mut name =
+                    format!("{} {}", id, view.file().map_or("[scratch]", |f| f.as_str()));
+                if view.is_dirty() {
+                    name.push_str(" [+]");
+                }
+                if *id == self.current_view {
+                    format!("[{}]", name)
+                } else {
+                    name
+                }
+            })
+            .collect();
+        self.set_status(format!("buffers: {}", buffers.join(", ")));
+    }
+
+    /// Switch to the view whose file name contains `name`, or whose id
+    /// is `name`. If several files match, the one named exactly `name`
+    /// is preferred.
+    pub fn switch_buffer(&mut self, name: &str) {
+        let matching: Vec<(ViewId, &str)> = self
+            .views
+            .iter()
+            .filter_map(|(id, view)| view.file().map(|file| (*id, file.as_str())))
+            .filter(|(_, file)| file.contains(name))
+            .collect();
+        let view_id = if let Some(id) = self.views.keys().find(|id| id.to_string() == name) {
+            *id
+        } else {
+            let exact: Vec<ViewId> = matching
+                .iter()
+                .filter(|(_, file)| Path::new(file).file_name().is_some_and(|f| f == name))
+                .map(|(id, _)| *id)
+                .collect();
+            match (matching.as_slice(), exact.as_slice()) {
+                ([], _) => {
+                    self.set_status(format!("no buffer matching '{}'", name));
+                    return;
+                }
+                ([(id, _)], _) | (_, [id]) => *id,
+                _ => {
+                    let files: Vec<&str> = matching.iter().map(|(_, file)| *file).collect();
+                    self.set_status(format!(
+                        "several buffers match '{}': {}",
+                        name,
+                        files.join(", ")
+                    ));
+                    return;
+                }
+            }
+        };
+        if view_id != self.current_view {
+            self.current_view = view_id;
+            self.resize_views();
+        }
+    }
+
+    /// Close the current view and switch to the previous one. Unless
+    /// `force` is set, this refuses to discard unsaved changes. The
+    /// file and cursor position are remembered for `reopen`.
+    pub fn close_current_view(&mut self, force: bool) {
+        let view_id = self.current_view;
+        match self.views.get(&view_id) {
+            Some(view) if view.is_dirty() && !force => {
+                self.set_status("the buffer has unsaved changes (use close! to discard them)");
+                return;
+            }
+            Some(_) => {}
+            None => return,
+        }
+        let (dex, _, view) = match self.views.swap_remove_full(&view_id) {
+            Some(removed) => removed,
+            None => return,
+        };
+        info!("closing view {:?}", view_id);
+        self.grep_done(view_id);
+        tokio::spawn(self.client.close_view(view_id).map_err(|_| ()));
+
+        let (line, column) = view.cursor_position();
+        if self.closed.len() == MAX_CLOSED_VIEWS {
+            self.closed.remove(0);
+        }
+        self.closed.push(ClosedView {
+            file: view.file().cloned(),
+            line,
+            column,
+        });
+
+        // The other pane takes the whole screen
+        if let Some(split) = self.split.take() {
+            if self.views.contains_key(&split.other) {
+                self.current_view = split.other;
+                self.resize_views();
+                return;
+            }
+        }
+        match self.views.get_index(dex.saturating_sub(1)) {
+            Some((id, _)) => {
+                self.current_view = *id;
+                self.resize_views();
+            }
+            // Always keep a view to type in
+            None => self.new_view(None),
+        }
+    }
+
+    /// Open again the view closed last, where its cursor was. A buffer
+    /// that had no file cannot be opened again, and is skipped.
+    pub fn reopen_closed(&mut self) {
+        let closed = match self.closed.pop() {
+            Some(closed) => closed,
+            None => {
+                self.set_status("no closed buffer to reopen");
+                return;
+            }
+        };
+        let file = match closed.file {
+            Some(file) => file,
+            None => {
+                self.set_status("the closed buffer had no file name, skipping it");
+                return;
+            }
+        };
+        let view_id = self
+            .views
+            .iter()
+            .find(|(_, view)| view.file().map(Path::new) == Some(Path::new(&file)))
+            .map(|(id, _)| *id);
+        match view_id {
+            // The file was opened again in the meantime
+            Some(view_id) => {
+                if view_id != self.current_view {
+                    self.current_view = view_id;
+                    self.resize_views();
+                }
+            }
+            None => self.new_view_at(Some(file), Some((closed.line, Some(closed.column)))),
+        }
+    }
+
+    /// Show the current buffer in a second pane too, with its own
+    /// cursor and scroll position. The new pane goes first and gets the
+    /// focus.
+    pub fn split_view(&mut self, direction: SplitDirection) {
+        if self.panes().len() > 1 {
+            self.set_status("the screen is split already");
+            return;
+        }
+        let (file, (line, column)) = match self.views.get(&self.current_view) {
+            Some(view) => match view.file() {
+                Some(file) => (file.clone(), view.cursor_position()),
+                None => {
+                    self.set_status("cannot split a buffer that has no file name");
+                    return;
+                }
+            },
+            None => return,
+        };
+        // The core opens another view of the file, which is shown in
+        // the new pane once created.
+        self.pending_split = Some((direction, self.current_view));
+        self.new_view_at(Some(file), Some((line, Some(column))));
+    }
+
+    /// Move the focus to the other pane.
+    pub fn focus_next_pane(&mut self) {
+        if self.panes().len() < 2 {
+            self.set_status("there is only one pane");
+            return;
+        }
+        if let Some(ref mut split) = self.split {
+            let other = split.other;
+            split.other = self.current_view;
+            split.focus_first = !split.focus_first;
+            self.current_view = other;
+        }
+    }
+
+    /// Focus the pane that has the given position, starting at 1, if
+    /// it is not focused already.
+    fn focus_pane_at(&mut self, column: u16, row: u16) {
+        let (column, row) = (column.saturating_sub(1), row.saturating_sub(1));
+        let pane = self
+            .panes()
+            .into_iter()
+            .find(|(_, ((left, top), height, width))| {
+                (*left..left + width).contains(&column) && (*top..top + height).contains(&row)
+            });
+        if let Some((view_id, _)) = pane {
+            if view_id != self.current_view {
+                self.focus_next_pane();
+            }
+        }
+    }
+
+    pub fn next_buffer(&mut self) {
+        self.cycle_buffer(true);
+    }
+
+    pub fn prev_buffer(&mut self) {
+        self.cycle_buffer(false);
+    }
+
+    /// Move the current view to the front of the most recently used
+    /// views, unless cycling got there, and forget the views that were
+    /// closed. If the current
+    /// view changed, the view that lost the focus remembers its scroll
+    /// position, and the new one scrolls back to where it was.
+    fn note_current_view(&mut self) {
+        let views = &self.views;
+        self.recent_views.retain(|id| views.contains_key(id));
+        if !self.views.contains_key(&self.current_view) {
+            return;
+        }
+        if self.focused == Some(self.current_view) {
+            return;
+        }
+        if let Some(previous) = self.focused {
+            if let Some(view) = self.views.get_mut(&previous) {
+                view.save_scroll();
+            }
+        }
+        let current = self.current_view;
+        self.focused = Some(current);
+        self.recent_views.focus(current);
+        // The view may have been last shown in a pane of another size
+        self.resize_views();
+        if let Some(view) = self.views.get_mut(&current) {
+            view.restore_scroll();
+        }
+    }
+
+    /// Switch to the view after the current one, or before it if
+    /// `forward` is not set, in the order set by `buffer_cycle_order`.
+    fn cycle_buffer(&mut self, forward: bool) {
+        self.note_current_view();
+        let views: Vec<ViewId> = self.views.keys().cloned().collect();
+        let next =
+            self.recent_views
+                .cycle(self.buffer_cycle_order, &views, self.current_view, forward);
+        if let Some(next) = next {
+            self.current_view = next;
+            self.note_current_view();
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.move_left();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.move_right();
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        let at_edge = match self.views.get_mut(&self.current_view) {
+            Some(view) => {
+                view.move_up();
+                view.cursor_position().0 == 0
+            }
+            None => return,
+        };
+        if at_edge {
+            self.bell();
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        let at_edge = match self.views.get_mut(&self.current_view) {
+            Some(view) => {
+                view.move_down();
+                view.on_last_line()
+            }
+            None => return,
+        };
+        if at_edge {
+            self.bell();
+        }
+    }
+
+    pub fn move_to(&mut self, point: MovePoint, extend: bool) {
+        let smart_home = self.smart_home;
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.move_to(point, extend, smart_home);
+        }
+    }
+
+    pub fn page_down(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.page_down();
+        }
+    }
+
+    pub fn page_up(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.page_up();
+        }
+    }
+
+    pub fn scroll_center(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.scroll_center();
+        }
+    }
+
+    pub fn scroll_top(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.scroll_top();
+        }
+    }
+
+    pub fn scroll_bottom(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.scroll_bottom();
+        }
+    }
+
+    pub fn half_page(&mut self, down: bool, extend: bool) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.half_page(down, extend);
+        }
+    }
+
+    pub fn toggle_line_numbers(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.toggle_line_numbers();
+        }
+    }
+
+    pub fn toggle_whitespace(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.toggle_whitespace();
+        }
+    }
+
+    pub fn set_line_number_mode(&mut self, mode: LineNumberMode) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.set_line_number_mode(mode);
+        }
+    }
+
+    pub fn markdown_heading_move(&mut self, forward: bool, level: Option<u8>) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.markdown_heading_move(forward, level);
+        }
+    }
+
+    pub fn fold_all_sections(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.fold_all_sections();
+        }
+    }
+
+    pub fn toggle_fold(&mut self) {
+        let folded = match self.views.get_mut(&self.current_view) {
+            Some(view) => view.toggle_fold(),
+            None => return,
+        };
+        if !folded {
+            self.set_status("no indented lines to fold under the cursor");
+        }
+    }
+
+    pub fn fold_all(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.fold_all();
+        }
+    }
+
+    pub fn unfold_all(&mut self) {
+        if let Some(view) = self.views.get_mut(&self.current_view) {
+            view.unfold_all();
+        }
+    }
+}
+
+/// Methods ment to be called by the tui struct
+impl Editor {
+    // We render if:
+    //  - the current view is dirty
     //  - we switched views
     //  - the style changed
     //  - the terminal size changed
     pub fn render<W: Write>(&mut self, term: &mut W) -> Result<(), Error> {
-        if let Some(view) = self.views.get_mut(&self.current_view) {
-            debug!("rendering the current view");
-            view.render(term, &self.styles)?;
+        self.render_status(term)?;
+        let panel_top = self.view_height() + 1;
+        self.quickfix.render(term, panel_top, self.size.0)?;
+        // The first pane is drawn first, because each line of a view
+        // is cleared up to the right edge of the terminal.
+        let panes = self.panes();
+        for (view_id, (origin, height, width)) in &panes {
+            match self.views.get_mut(view_id) {
+                Some(view) => {
+                    debug!("rendering view {}", view_id);
+                    view.set_region(*origin, *height, *width);
+                    view.render(term, &self.styles)?;
+                }
+                None => warn!("no view to render"),
+            }
+        }
+        if let Some((_, ((left, top), height, width))) = panes.get(1) {
+            if *top > 0 {
+                write!(term, "{}{}", Goto(1, *top), "─".repeat(*width as usize))?;
+            } else {
+                for row in 1..=*height {
+                    write!(term, "{}│", Goto(*left, row))?;
+                }
+            }
+            // Put the terminal cursor back in the focused pane
+            if let Some(view) = self.views.get(&self.current_view) {
+                view.render_cursor(term);
+            }
+        }
+        Ok(())
+    }
+
+    fn render_status<W: Write>(&mut self, term: &mut W) -> Result<(), Error> {
+        if self.bell_pending {
+            self.bell_pending = false;
+            write!(term, "{}", AUDIBLE_BELL)?;
+        }
+        write!(term, "{}{}", Goto(1, self.size.1), ClearLine)?;
+        let flash = self.bell_timer.is_some();
+        if flash {
+            // Fill the whole line, the text below is drawn inverted too
+            let blank = " ".repeat(self.size.0 as usize);
+            write!(term, "{}{}{}", Invert, blank, Goto(1, self.size.1))?;
+        }
+        let result = self.render_status_text(term);
+        if flash {
+            write!(term, "{}", NoInvert)?;
+        }
+        result
+    }
+
+    fn render_status_text<W: Write>(&self, term: &mut W) -> Result<(), Error> {
+        // The file name, marked with [+] if it has unsaved changes, the
+        // cursor position, the search match counter and the line ending
+        // go on the right
+        let view = match self.views.get(&self.current_view) {
+            Some(view) => view,
+            None => {
+                if let Some((ref status, _)) = self.status {
+                    write!(term, "{}", truncate(status, self.size.0 as usize))?;
+                }
+                return Ok(());
+            }
+        };
+        let name = view.file().map_or("[scratch]", |f| f.as_str());
+        let mut parts = vec![if view.is_dirty() {
+            format!("{} [+]", name)
         } else {
-            warn!("no view to render");
+            name.to_owned()
+        }];
+        parts.push(view.position_indicator());
+        parts.extend(view.search_counter());
+        parts.extend(view.line_ending().map(str::to_owned));
+        let right = parts.join("  ");
+        let right_width = right.chars().count();
+        // The status keeps a space between it and the right side, so
+        // that they don't overwrite each other
+        if let Some((ref status, _)) = self.status {
+            let width = (self.size.0 as usize).saturating_sub(right_width + 1);
+            write!(term, "{}", truncate(status, width))?;
         }
+        let column = self.size.0.saturating_sub(right_width as u16) + 1;
+        write!(term, "{}{}", Goto(column, self.size.1), right)?;
         Ok(())
     }
 }
+
+/// Cut `text` to at most `width` characters, ending it with an
+/// ellipsis if anything was cut.
+fn truncate<'a>(text: &'a str, width: usize) -> Cow<'a, str> {
+    if text.chars().count() <= width {
+        return Cow::Borrowed(text);
+    }
+    if width == 0 {
+        return Cow::Borrowed("");
+    }
+    let mut cut: String = text.chars().take(width - 1).collect();
+    cut.push('…');
+    Cow::Owned(cut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[usize]) -> Vec<ViewId> {
+        ids.iter().map(|id| ViewId(*id)).collect()
+    }
+
+    fn focused(ids: &[usize]) -> RecentViews {
+        let mut recent = RecentViews::default();
+        for id in ids {
+            recent.focus(ViewId(*id));
+        }
+        recent
+    }
+
+    #[test]
+    fn truncates_the_status_at_a_char_boundary() {
+        assert_eq!(truncate("saved", 5), "saved");
+        assert_eq!(truncate("écrit dans le fichier", 7), "écrit …");
+        assert_eq!(truncate("saved", 1), "…");
+        assert_eq!(truncate("saved", 0), "");
+    }
+
+    #[test]
+    fn the_focused_view_moves_to_the_front() {
+        assert_eq!(focused(&[1, 2, 3, 1]).order, ids(&[1, 3, 2]));
+    }
+
+    #[test]
+    fn cycles_through_the_most_recently_used_views() {
+        let views = ids(&[1, 2, 3, 4]);
+        let recent = focused(&[2, 3, 1]).order;
+        let order = cycle_order(BufferCycleOrder::Mru, &views, &recent);
+        assert_eq!(order, ids(&[1, 3, 2, 4]));
+        assert_eq!(cycle_next(&order, ViewId(1), true), Some(ViewId(3)));
+        assert_eq!(cycle_next(&order, ViewId(4), true), Some(ViewId(1)));
+        assert_eq!(cycle_next(&order, ViewId(1), false), Some(ViewId(4)));
+    }
+
+    #[test]
+    fn a_cycle_goes_through_every_view() {
+        let views = ids(&[1, 2, 3]);
+        let mut recent = focused(&[1, 2, 3]);
+        let mut current = ViewId(3);
+        let mut visited = Vec::new();
+        for _ in 0..3 {
+            current = recent
+                .cycle(BufferCycleOrder::Mru, &views, current, true)
+                .unwrap();
+            recent.focus(current);
+            visited.push(current);
+        }
+        // Past the least recent view, the cycle wraps around
+        assert_eq!(visited, ids(&[2, 1, 3]));
+        assert_eq!(recent.order, ids(&[3, 2, 1]));
+    }
+
+    #[test]
+    fn the_view_a_cycle_ends_at_becomes_the_most_recent() {
+        let views = ids(&[1, 2, 3]);
+        let mut recent = focused(&[1, 2, 3]);
+        let mut current = ViewId(3);
+        for _ in 0..2 {
+            current = recent
+                .cycle(BufferCycleOrder::Mru, &views, current, true)
+                .unwrap();
+            recent.focus(current);
+        }
+        assert_eq!(current, ViewId(1));
+        // Opening another view ends the cycle
+        recent.focus(ViewId(4));
+        assert_eq!(recent.order, ids(&[4, 1, 3, 2]));
+        // And the next cycle starts from the new order
+        let views = ids(&[1, 2, 3, 4]);
+        let next = recent.cycle(BufferCycleOrder::Mru, &views, ViewId(4), true);
+        assert_eq!(next, Some(ViewId(1)));
+    }
+
+    #[test]
+    fn cycles_through_the_views_in_creation_order() {
+        let views = vec![ViewId(1), ViewId(2), ViewId(3)];
+        let order = cycle_order(BufferCycleOrder::Created, &views, &[ViewId(3)]);
+        assert_eq!(order, views);
+        assert_eq!(cycle_next(&order, ViewId(3), true), Some(ViewId(1)));
+        assert_eq!(cycle_next(&order, ViewId(1), false), Some(ViewId(3)));
+        assert_eq!(cycle_next(&order, ViewId(5), true), None);
+    }
+}
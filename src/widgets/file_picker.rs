@@ -0,0 +1,116 @@
+//! A list of the entries of a directory, filtered by what is typed,
+//! from which a file is picked to be opened, or a directory to be
+//! listed instead.
+
+use std::fs;
+use std::io::Write;
+
+use failure::Error;
+use termion::event::Event;
+
+use core::resolve_path;
+use widgets::command_prompt::{expand_home, fuzzy_score, path_candidates};
+use widgets::filtered_list::{FilteredList, ListEvent, ListSource};
+
+/// What the file picker asks the TUI to do after handling an event.
+pub enum FilePickerEvent {
+    /// Open the file at this path.
+    Open(String),
+    /// Close the file picker.
+    Cancel,
+}
+
+/// The entries of the directory listed by the file picker.
+struct Directory {
+    /// The file of the current view, which relative paths are resolved
+    /// against, like for `open`.
+    current_file: Option<String>,
+    /// The directory listed, as typed, ending with a `/`.
+    dir: String,
+}
+
+impl ListSource for Directory {
+    /// The name of an entry. Directories end with a `/`.
+    type Item = String;
+
+    /// List the directory again, keeping the entries that match the
+    /// filter. Hidden entries are only listed once the filter starts
+    /// with a `.`, like for the completion of `open`.
+    fn matching(&self, filter: &str) -> Vec<String> {
+        let listed = if filter.starts_with('.') {
+            format!("{}.", self.dir)
+        } else {
+            self.dir.clone()
+        };
+        let names = path_candidates(self.current_file.as_deref(), &listed)
+            .into_iter()
+            .map(|path| path[self.dir.len()..].to_owned());
+        let mut scored: Vec<((usize, usize), String)> = Some("../".to_owned())
+            .into_iter()
+            .chain(names)
+            .filter_map(|name| fuzzy_score(&name, filter).map(|score| (score, name)))
+            .collect();
+        // The sort is stable, so entries that score the same stay
+        // sorted by name
+        scored.sort_by_key(|entry| entry.0);
+        scored.into_iter().map(|(_, name)| name).collect()
+    }
+
+    fn text(&self, name: &String) -> String {
+        name.clone()
+    }
+
+    fn prompt(&self) -> String {
+        format!("{}> ", self.dir)
+    }
+}
+
+pub struct FilePicker {
+    list: FilteredList<Directory>,
+}
+
+impl FilePicker {
+    pub fn new(current_file: Option<String>, dir: &str) -> FilePicker {
+        let mut dir = dir.to_owned();
+        if !dir.ends_with('/') {
+            dir.push('/');
+        }
+        FilePicker {
+            list: FilteredList::new(Directory { current_file, dir }),
+        }
+    }
+
+    /// Whether `path`, as given to `open`, is a directory.
+    pub fn is_directory(current_file: Option<&str>, path: &str) -> bool {
+        let path = expand_home(path).unwrap_or_else(|| resolve_path(current_file, path));
+        fs::metadata(path)
+            .map(|metadata| metadata.is_dir())
+            .unwrap_or(false)
+    }
+
+    /// Picking a directory lists it instead of opening it.
+    pub fn handle_input(&mut self, event: &Event) -> Option<FilePickerEvent> {
+        match self.list.handle_input(event)? {
+            ListEvent::Accept(name) => {
+                let path = format!("{}{}", self.list.source().dir, name);
+                if !path.ends_with('/') {
+                    return Some(FilePickerEvent::Open(expand_home(&path).unwrap_or(path)));
+                }
+                self.list.change_source(|source| source.dir = path);
+                None
+            }
+            ListEvent::Cancel => Some(FilePickerEvent::Cancel),
+        }
+    }
+
+    /// Add pasted text to the filter.
+    pub fn paste(&mut self, text: &str) {
+        self.list.paste(text);
+    }
+
+    /// Render the list above the bottom row, and the directory and the
+    /// filter on the bottom row.
+    pub fn render<W: Write>(&self, w: &mut W, size: (u16, u16)) -> Result<(), Error> {
+        self.list.render(w, size)
+    }
+}
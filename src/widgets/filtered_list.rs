@@ -0,0 +1,217 @@
+//! A list filtered by what is typed, from which an item is picked. The
+//! command palette, the file picker and the outline are such lists,
+//! each with its own source of items.
+
+use std::io::Write;
+
+use failure::Error;
+use termion::clear::CurrentLine as ClearLine;
+use termion::cursor::Goto;
+use termion::event::{Event, Key};
+use termion::style::{Invert, NoInvert};
+
+/// Maximum number of items shown at once.
+const MAX_HEIGHT: u16 = 10;
+
+/// The items a `FilteredList` picks from.
+pub trait ListSource {
+    type Item: Clone;
+
+    /// Return the items matching `filter`, best matches first.
+    fn matching(&self, filter: &str) -> Vec<Self::Item>;
+
+    /// Return the text of `item` in the list.
+    fn text(&self, item: &Self::Item) -> String;
+
+    /// Return what is written before the filter on the bottom row.
+    fn prompt(&self) -> String {
+        "> ".to_owned()
+    }
+}
+
+/// What the list asks the TUI to do after handling an event.
+pub enum ListEvent<T> {
+    /// An item was picked.
+    Accept(T),
+    /// Close the list.
+    Cancel,
+}
+
+pub struct FilteredList<S: ListSource> {
+    source: S,
+    filter: String,
+    /// The items matching the filter, best matches first.
+    entries: Vec<S::Item>,
+    /// Index of the selected entry.
+    current: usize,
+}
+
+impl<S: ListSource> FilteredList<S> {
+    pub fn new(source: S) -> FilteredList<S> {
+        let mut list = FilteredList {
+            source,
+            filter: String::new(),
+            entries: Vec::new(),
+            current: 0,
+        };
+        list.update_entries();
+        list
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Change the source with `change`, and list its items from
+    /// scratch.
+    pub fn change_source<F: FnOnce(&mut S)>(&mut self, change: F) {
+        change(&mut self.source);
+        self.filter.clear();
+        self.update_entries();
+    }
+
+    pub fn handle_input(&mut self, event: &Event) -> Option<ListEvent<S::Item>> {
+        match *event {
+            Event::Key(Key::Char('\n')) => {
+                return self
+                    .entries
+                    .get(self.current)
+                    .cloned()
+                    .map(ListEvent::Accept)
+            }
+            Event::Key(Key::Up) | Event::Key(Key::Ctrl('p')) => {
+                self.current = self.current.saturating_sub(1);
+            }
+            Event::Key(Key::Down) | Event::Key(Key::Ctrl('n')) => {
+                self.current = (self.current + 1).min(self.entries.len().saturating_sub(1));
+            }
+            Event::Key(Key::Backspace) | Event::Key(Key::Ctrl('h')) => {
+                if self.filter.pop().is_none() {
+                    return Some(ListEvent::Cancel);
+                }
+                self.update_entries();
+            }
+            Event::Key(Key::Char(c)) => {
+                self.filter.push(c);
+                self.update_entries();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Add pasted text to the filter.
+    pub fn paste(&mut self, text: &str) {
+        self.filter
+            .extend(text.chars().filter(|c| *c != '\n' && *c != '\r'));
+        self.update_entries();
+    }
+
+    fn update_entries(&mut self) {
+        self.entries = self.source.matching(&self.filter);
+        self.current = 0;
+    }
+
+    /// Render the list above the bottom row, and the prompt and the
+    /// filter on the bottom row.
+    pub fn render<W: Write>(&self, w: &mut W, size: (u16, u16)) -> Result<(), Error> {
+        let (width, height) = size;
+        let rows = MAX_HEIGHT.min(height.saturating_sub(1));
+        let top = height - rows;
+        // Scroll the list so that the selected entry is visible
+        let first = (self.current + 1).saturating_sub(rows as usize);
+        let entries = self.entries.iter().enumerate().skip(first);
+        let mut row = 0;
+        for (idx, item) in entries.take(rows as usize) {
+            let text: String = self
+                .source
+                .text(item)
+                .chars()
+                .take(width as usize)
+                .collect();
+            write!(w, "{}{}", Goto(1, top + row), ClearLine)?;
+            if idx == self.current {
+                write!(w, "{}{}{}", Invert, text, NoInvert)?;
+            } else {
+                write!(w, "{}", text)?;
+            }
+            row += 1;
+        }
+        // Clear the rows left when few items match
+        for row in row..rows {
+            write!(w, "{}{}", Goto(1, top + row), ClearLine)?;
+        }
+        write!(
+            w,
+            "{}{}{}{}",
+            Goto(1, height),
+            ClearLine,
+            self.source.prompt(),
+            self.filter
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Words(&'static [&'static str]);
+
+    impl ListSource for Words {
+        type Item = &'static str;
+
+        fn matching(&self, filter: &str) -> Vec<&'static str> {
+            self.0
+                .iter()
+                .cloned()
+                .filter(|w| w.contains(filter))
+                .collect()
+        }
+
+        fn text(&self, item: &&'static str) -> String {
+            (*item).to_owned()
+        }
+    }
+
+    fn accepted(list: &mut FilteredList<Words>) -> Option<&'static str> {
+        match list.handle_input(&Event::Key(Key::Char('\n'))) {
+            Some(ListEvent::Accept(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn typing_filters_the_items() {
+        let mut list = FilteredList::new(Words(&["open", "save", "save-as"]));
+        list.handle_input(&Event::Key(Key::Char('a')));
+        list.handle_input(&Event::Key(Key::Down));
+        assert_eq!(accepted(&mut list), Some("save-as"));
+        // The selection starts over when the filter changes
+        list.paste("v");
+        assert_eq!(accepted(&mut list), Some("save"));
+    }
+
+    #[test]
+    fn the_selection_stays_in_the_list() {
+        let mut list = FilteredList::new(Words(&["open", "save"]));
+        list.handle_input(&Event::Key(Key::Up));
+        assert_eq!(accepted(&mut list), Some("open"));
+        for _ in 0..3 {
+            list.handle_input(&Event::Key(Key::Down));
+        }
+        assert_eq!(accepted(&mut list), Some("save"));
+    }
+
+    #[test]
+    fn backspace_without_a_filter_cancels() {
+        let mut list = FilteredList::new(Words(&["open"]));
+        list.handle_input(&Event::Key(Key::Char('o')));
+        assert!(list.handle_input(&Event::Key(Key::Backspace)).is_none());
+        assert!(matches!(
+            list.handle_input(&Event::Key(Key::Backspace)),
+            Some(ListEvent::Cancel)
+        ));
+    }
+}
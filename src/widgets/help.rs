@@ -0,0 +1,94 @@
+//! A list of the key bindings with the commands they run, shown over
+//! the views until a key is pressed.
+
+use std::io::Write;
+
+use failure::Error;
+use termion::clear::CurrentLine as ClearLine;
+use termion::cursor::Goto;
+use termion::event::{Event, Key, MouseButton, MouseEvent};
+use termion::style::{Invert, NoInvert};
+
+use core::{describe_keys, CommandInfo, KeyBindings};
+
+#[derive(Debug)]
+pub struct Help {
+    /// One line per key binding.
+    lines: Vec<String>,
+    /// Index of the first line shown.
+    top: usize,
+}
+
+impl Help {
+    /// List the sequences of `bindings`, with the name and description
+    /// of their command as found in the command table.
+    pub fn new(bindings: &KeyBindings) -> Help {
+        let lines = bindings
+            .entries()
+            .iter()
+            .map(|(keys, cmd)| {
+                let keys = describe_keys(keys);
+                match CommandInfo::for_command(cmd) {
+                    Some(info) => format!("{:<10}{:<20}{}", keys, info.name, info.description),
+                    None => format!("{:<10}{:?}", keys, cmd),
+                }
+            })
+            .collect();
+        Help { lines, top: 0 }
+    }
+
+    /// Scroll the list with the arrows, the page keys and the mouse
+    /// wheel, `height` being the height of the terminal. Return `true`
+    /// for the other events, which close it.
+    pub fn handle_input(&mut self, event: &Event, height: u16) -> bool {
+        let page = usize::from(height.saturating_sub(2)).max(1);
+        let last_top = self.lines.len().saturating_sub(page);
+        match *event {
+            Event::Key(Key::Up) | Event::Mouse(MouseEvent::Press(MouseButton::WheelUp, _, _)) => {
+                self.top = self.top.saturating_sub(1);
+            }
+            Event::Key(Key::Down)
+            | Event::Mouse(MouseEvent::Press(MouseButton::WheelDown, _, _)) => {
+                self.top = (self.top + 1).min(last_top);
+            }
+            Event::Key(Key::PageUp) => self.top = self.top.saturating_sub(page),
+            Event::Key(Key::PageDown) => self.top = (self.top + page).min(last_top),
+            Event::Mouse(_) => {}
+            _ => return true,
+        }
+        false
+    }
+
+    /// Render the list on all the rows but the last one, which tells
+    /// how to scroll and close it.
+    pub fn render<W: Write>(&self, w: &mut W, size: (u16, u16)) -> Result<(), Error> {
+        let (width, height) = size;
+        let rows = height.saturating_sub(1);
+        let mut lines = self.lines.iter().skip(self.top);
+        for row in 1..=rows {
+            write!(w, "{}{}", Goto(1, row), ClearLine)?;
+            if let Some(line) = lines.next() {
+                let line: String = line.chars().take(width as usize).collect();
+                write!(w, "{}", line)?;
+            }
+        }
+        let last = (self.top + usize::from(rows)).min(self.lines.len());
+        let footer = format!(
+            "key bindings {}-{}/{}: arrows and page keys scroll, any other key closes",
+            (self.top + 1).min(last),
+            last,
+            self.lines.len()
+        );
+        let footer: String = footer.chars().take(width as usize).collect();
+        write!(
+            w,
+            "{}{}{}{}{}",
+            Goto(1, height),
+            ClearLine,
+            Invert,
+            footer,
+            NoInvert
+        )?;
+        Ok(())
+    }
+}
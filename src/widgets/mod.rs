@@ -1,5 +1,6 @@
 /// A widget is something that can be displayed on screen
 mod view;
+pub use self::view::Symbol;
 pub use self::view::View;
 pub use self::view::ViewClient;
 
@@ -8,3 +9,24 @@ pub use self::editor::Editor;
 
 mod command_prompt;
 pub use self::command_prompt::CommandPrompt;
+
+mod confirm;
+pub use self::confirm::Confirm;
+
+mod help;
+pub use self::help::Help;
+
+mod filtered_list;
+pub use self::filtered_list::ListEvent;
+
+mod file_picker;
+pub use self::file_picker::{FilePicker, FilePickerEvent};
+
+mod palette;
+pub use self::palette::{Commands, Palette};
+
+mod quickfix;
+pub use self::quickfix::{Quickfix, QuickfixEntry};
+
+mod outline;
+pub use self::outline::{Outline, Symbols};
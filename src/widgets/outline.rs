@@ -0,0 +1,41 @@
+//! A list of the symbols of a document, filtered by what is typed, from
+//! which one is picked to move the cursor to it.
+
+use widgets::command_prompt::fuzzy_score;
+use widgets::filtered_list::{FilteredList, ListSource};
+use widgets::Symbol;
+
+/// The outline of a document.
+pub type Outline = FilteredList<Symbols>;
+
+/// The symbols listed in the outline, in the order of the document.
+pub struct Symbols(pub Vec<Symbol>);
+
+impl ListSource for Symbols {
+    type Item = Symbol;
+
+    /// Keep the symbols matching the filter. Without a filter, they
+    /// stay in the order of the document.
+    fn matching(&self, filter: &str) -> Vec<Symbol> {
+        let mut scored: Vec<((usize, usize), &Symbol)> = self
+            .0
+            .iter()
+            .filter_map(|symbol| fuzzy_score(&symbol.name, filter).map(|s| (s, symbol)))
+            .collect();
+        if !filter.is_empty() {
+            scored.sort_by_key(|entry| entry.0);
+        }
+        scored
+            .into_iter()
+            .map(|(_, symbol)| symbol.clone())
+            .collect()
+    }
+
+    fn text(&self, symbol: &Symbol) -> String {
+        format!("{:>6}  {}", symbol.line + 1, symbol.name)
+    }
+
+    fn prompt(&self) -> String {
+        "symbol: ".to_owned()
+    }
+}
@@ -0,0 +1,66 @@
+//! A list of all the commands, with their description and key
+//! binding, filtered by what is typed and from which one is picked to
+//! be run.
+
+use std::collections::HashMap;
+
+use core::{CommandInfo, COMMANDS};
+use widgets::command_prompt::fuzzy_score;
+use widgets::filtered_list::{FilteredList, ListSource};
+
+/// The command palette. Picking a command runs it, or prompts for its
+/// arguments.
+pub type Palette = FilteredList<Commands>;
+
+/// The commands listed in the palette.
+pub struct Commands {
+    /// The key bindings of the commands that have one, by name.
+    keys: HashMap<&'static str, String>,
+}
+
+impl Commands {
+    /// `describe` returns the key binding of a command, if any.
+    pub fn new<F: Fn(&CommandInfo) -> Option<String>>(describe: F) -> Commands {
+        let keys = COMMANDS
+            .iter()
+            .filter_map(|cmd| describe(cmd).map(|keys| (cmd.name, keys)))
+            .collect();
+        Commands { keys }
+    }
+}
+
+impl ListSource for Commands {
+    type Item = &'static CommandInfo;
+
+    /// Return the commands whose name or one of its aliases matches
+    /// `filter`, scored like the completion of the prompt.
+    fn matching(&self, filter: &str) -> Vec<&'static CommandInfo> {
+        let mut scored: Vec<((usize, usize), &'static CommandInfo)> = COMMANDS
+            .iter()
+            .filter_map(|cmd| {
+                let names = Some(cmd.name)
+                    .into_iter()
+                    .chain(cmd.aliases.iter().cloned());
+                names
+                    .filter_map(|name| fuzzy_score(name, filter))
+                    .min()
+                    .map(|score| (score, cmd))
+            })
+            .collect();
+        scored.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.name.cmp(b.1.name)));
+        scored.into_iter().map(|(_, cmd)| cmd).collect()
+    }
+
+    fn text(&self, cmd: &&'static CommandInfo) -> String {
+        let name = if cmd.args.is_empty() {
+            cmd.name.to_owned()
+        } else {
+            format!("{} {}", cmd.name, cmd.args)
+        };
+        let keys = self
+            .keys
+            .get(cmd.name)
+            .map_or_else(String::new, |keys| format!(" ({})", keys));
+        format!("{:<24}{}{}", name, cmd.description, keys)
+    }
+}
@@ -0,0 +1,175 @@
+//! A list of locations in the open buffers, such as search results,
+//! that can be displayed in a panel and navigated, like Vim's quickfix
+//! list.
+
+use std::io::Write;
+
+use failure::Error;
+use termion::clear::CurrentLine as ClearLine;
+use termion::cursor::Goto;
+use termion::style::{Invert, NoInvert};
+use xrl::ViewId;
+
+/// Maximum number of rows occupied by the quickfix panel.
+const MAX_HEIGHT: u16 = 8;
+
+#[derive(Debug, Clone)]
+pub struct QuickfixEntry {
+    /// The view the location is in.
+    pub view_id: ViewId,
+    /// The file backing the view, so that it can be opened again if
+    /// the view was closed.
+    pub file: Option<String>,
+    pub line: u64,
+    pub text: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Quickfix {
+    entries: Vec<QuickfixEntry>,
+    /// Index of the selected entry.
+    current: Option<usize>,
+    /// Whether the panel is displayed.
+    pub visible: bool,
+}
+
+impl Quickfix {
+    /// Replace the entries of the list.
+    pub fn set_entries(&mut self, entries: Vec<QuickfixEntry>) {
+        self.entries = entries;
+        self.current = None;
+    }
+
+    /// Point the entries of the view `old` to the view `new`, which
+    /// replaced it.
+    pub fn replace_view(&mut self, old: ViewId, new: ViewId) {
+        for entry in &mut self.entries {
+            if entry.view_id == old {
+                entry.view_id = new;
+            }
+        }
+    }
+
+    /// Select the next entry, wrapping around after the last one.
+    pub fn next(&mut self) -> Option<&QuickfixEntry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let next = match self.current {
+            Some(current) => (current + 1) % self.entries.len(),
+            None => 0,
+        };
+        self.current = Some(next);
+        self.entries.get(next)
+    }
+
+    /// Select the previous entry, wrapping around before the first
+    /// one.
+    pub fn prev(&mut self) -> Option<&QuickfixEntry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let len = self.entries.len();
+        let prev = match self.current {
+            Some(current) => (current + len - 1) % len,
+            None => len - 1,
+        };
+        self.current = Some(prev);
+        self.entries.get(prev)
+    }
+
+    /// Number of rows the panel takes on screen.
+    pub fn height(&self) -> u16 {
+        if !self.visible {
+            return 0;
+        }
+        let len = self.entries.len() as u16;
+        if len > MAX_HEIGHT {
+            MAX_HEIGHT
+        } else {
+            len
+        }
+    }
+
+    /// Render the panel, starting at row `top`.
+    pub fn render<W: Write>(&self, w: &mut W, top: u16, width: u16) -> Result<(), Error> {
+        let height = self.height();
+        if height == 0 {
+            return Ok(());
+        }
+        // Scroll the list so that the selected entry is visible
+        let current = self.current.unwrap_or(0);
+        let first = (current + 1).saturating_sub(height as usize);
+        let entries = self.entries.iter().enumerate().skip(first);
+        for (row, (idx, entry)) in entries.take(height as usize).enumerate() {
+            let text = format!(
+                "{}:{}: {}",
+                entry.file.as_ref().map_or("[scratch]", |f| f.as_str()),
+                entry.line + 1,
+                entry.text.trim_end()
+            );
+            let text: String = text.chars().take(width as usize).collect();
+            write!(w, "{}{}", Goto(1, top + row as u16), ClearLine)?;
+            if Some(idx) == self.current {
+                write!(w, "{}{}{}", Invert, text, NoInvert)?;
+            } else {
+                write!(w, "{}", text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quickfix(lines: &[u64]) -> Quickfix {
+        let mut quickfix = Quickfix::default();
+        quickfix.set_entries(
+            lines
+                .iter()
+                .map(|&line| QuickfixEntry {
+                    view_id: ViewId(1),
+                    file: None,
+                    line,
+                    text: String::new(),
+                })
+                .collect(),
+        );
+        quickfix
+    }
+
+    #[test]
+    fn next_wraps_around_after_the_last_entry() {
+        let mut quickfix = quickfix(&[3, 7, 9]);
+        let lines: Vec<u64> = (0..4).map(|_| quickfix.next().unwrap().line).collect();
+        assert_eq!(lines, vec![3, 7, 9, 3]);
+    }
+
+    #[test]
+    fn prev_wraps_around_before_the_first_entry() {
+        let mut quickfix = quickfix(&[3, 7, 9]);
+        assert_eq!(quickfix.prev().unwrap().line, 9);
+        assert_eq!(quickfix.prev().unwrap().line, 7);
+        quickfix.next();
+        assert_eq!(quickfix.next().unwrap().line, 3);
+        assert_eq!(quickfix.prev().unwrap().line, 9);
+    }
+
+    #[test]
+    fn new_entries_start_over() {
+        let mut quickfix = quickfix(&[3, 7]);
+        quickfix.next();
+        quickfix.set_entries(Vec::new());
+        assert!(quickfix.next().is_none());
+        assert!(quickfix.prev().is_none());
+    }
+
+    #[test]
+    fn entries_follow_a_reloaded_view() {
+        let mut quickfix = quickfix(&[3]);
+        quickfix.replace_view(ViewId(1), ViewId(4));
+        assert_eq!(quickfix.next().unwrap().view_id, ViewId(4));
+    }
+}
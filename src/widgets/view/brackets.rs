@@ -0,0 +1,104 @@
+//! Find the bracket matching the one under the cursor.
+
+use super::selection::Position;
+
+const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// If `c` is a bracket, return the bracket it pairs with and whether
+/// that one comes after it.
+fn pair(c: char) -> Option<(char, bool)> {
+    PAIRS.iter().find_map(|&(open, close)| {
+        if c == open {
+            Some((close, true))
+        } else if c == close {
+            Some((open, false))
+        } else {
+            None
+        }
+    })
+}
+
+/// Return the position of the bracket matching the one at `pos` (or
+/// right before it, if there's no bracket at `pos`). Positions are
+/// given as a line index in `lines` and a byte offset in that line.
+pub fn find_matching(lines: &[&str], pos: Position) -> Option<Position> {
+    let (line, column) = (pos.0 as usize, pos.1 as usize);
+    let text = lines.get(line)?;
+    let at = text.get(column..).and_then(|s| s.chars().next());
+    let before = text.get(..column).and_then(|s| s.chars().next_back());
+
+    let (bracket, start) = match (at, before) {
+        (Some(c), _) if pair(c).is_some() => (c, column),
+        (_, Some(c)) if pair(c).is_some() => (c, column - c.len_utf8()),
+        _ => return None,
+    };
+    let (matching, forward) = pair(bracket)?;
+
+    // Walk the buffer from the bracket, counting the nested pairs.
+    let mut depth = 0;
+    if forward {
+        for (idx, text) in lines.iter().enumerate().skip(line) {
+            let offset = if idx == line { start } else { 0 };
+            for (i, c) in text[offset..].char_indices() {
+                if c == bracket {
+                    depth += 1;
+                } else if c == matching {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((idx as u64, (offset + i) as u64));
+                    }
+                }
+            }
+        }
+    } else {
+        for (idx, text) in lines.iter().enumerate().take(line + 1).rev() {
+            let end = if idx == line { start + 1 } else { text.len() };
+            for (i, c) in text[..end].char_indices().rev() {
+                if c == bracket {
+                    depth += 1;
+                } else if c == matching {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((idx as u64, i as u64));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINES: &[&str] = &["fn f(a: [u8; 2]) {", "    g((a), {x});", "}"];
+
+    #[test]
+    fn jumping_there_and_back_returns_to_the_start() {
+        for start in &[(0, 4), (0, 8), (0, 17), (1, 5), (1, 6), (1, 11)] {
+            let matching = find_matching(LINES, *start).unwrap();
+            assert_ne!(matching, *start);
+            assert_eq!(find_matching(LINES, matching), Some(*start));
+        }
+    }
+
+    #[test]
+    fn finds_the_bracket_across_lines() {
+        assert_eq!(find_matching(LINES, (0, 17)), Some((2, 0)));
+        assert_eq!(find_matching(LINES, (2, 0)), Some((0, 17)));
+    }
+
+    #[test]
+    fn uses_the_bracket_before_the_cursor() {
+        // Right after the closing parenthesis of (a)
+        assert_eq!(find_matching(LINES, (1, 9)), Some((1, 6)));
+    }
+
+    #[test]
+    fn needs_a_bracket_and_its_match() {
+        assert_eq!(find_matching(LINES, (1, 1)), None);
+        assert_eq!(find_matching(&["(a"], (0, 0)), None);
+        assert_eq!(find_matching(LINES, (5, 0)), None);
+    }
+}
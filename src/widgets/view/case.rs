@@ -0,0 +1,30 @@
+//! Changing the case of letters.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Case {
+    /// Swap lower case and upper case letters.
+    Toggle,
+    Upper,
+    Lower,
+}
+
+/// Change the case of the letters of `text`. Other characters are
+/// left alone.
+pub fn change(text: &str, case: Case) -> String {
+    match case {
+        Case::Upper => text.to_uppercase(),
+        Case::Lower => text.to_lowercase(),
+        Case::Toggle => text
+            .chars()
+            .flat_map(|c| {
+                if c.is_lowercase() {
+                    c.to_uppercase().collect::<Vec<char>>()
+                } else if c.is_uppercase() {
+                    c.to_lowercase().collect()
+                } else {
+                    vec![c]
+                }
+            })
+            .collect(),
+    }
+}
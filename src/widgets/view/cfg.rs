@@ -1,17 +1,32 @@
+use core::LineNumberMode;
+
 pub struct ViewConfig {
     // Gutter Settings
-    pub display_gutter: bool,
+    pub line_numbers: LineNumberMode,
     pub gutter_size: u16,
     // Tab Settings
     pub tab_size: u16,
+    /// Whether Tab inserts spaces instead of a tab character, as set
+    /// by the core.
+    pub expand_tabs: bool,
+    // Wrap Settings
+    pub wrap: bool,
+    /// Width of the terminal, which long lines are wrapped at.
+    pub width: u16,
+    /// Whether spaces and tabs are drawn as visible characters.
+    pub show_whitespace: bool,
 }
 
 impl Default for ViewConfig {
     fn default() -> ViewConfig {
         ViewConfig {
-            display_gutter: true,
+            line_numbers: LineNumberMode::Absolute,
             gutter_size: 0,
             tab_size: 4,
+            expand_tabs: false,
+            wrap: false,
+            width: 0,
+            show_whitespace: false,
         }
     }
 }
@@ -1,6 +1,8 @@
-use futures::Future;
+use futures::future::{self, Either};
+use futures::{stream, Future, Stream};
 use tokio::spawn;
 use xrl;
+use xrl::ModifySelection;
 
 pub struct Client {
     inner: xrl::Client,
@@ -20,6 +22,24 @@ impl Client {
         spawn(f);
     }
 
+    pub fn insert_str(&mut self, text: &str) {
+        let f = self.inner.insert(self.view_id, text).map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Insert `text` at each cursor, then move the cursors `count`
+    /// characters to the left.
+    pub fn insert_then_left(&mut self, text: &str, count: usize) {
+        let inner = self.inner.clone();
+        let view_id = self.view_id;
+        let f = self
+            .inner
+            .insert(view_id, text)
+            .and_then(move |_| stream::iter_ok(0..count).for_each(move |_| inner.left(view_id)))
+            .map_err(|_| ());
+        spawn(f);
+    }
+
     pub fn insert_newline(&mut self) {
         let f = self.inner.insert_newline(self.view_id).map_err(|_| ());
         spawn(f);
@@ -55,6 +75,24 @@ impl Client {
         spawn(f);
     }
 
+    /// Move the cursor `count` lines down (or up), extending the
+    /// selection if `extend` is true.
+    pub fn move_lines(&mut self, count: u16, down: bool, extend: bool) {
+        let method = match (down, extend) {
+            (true, false) => "move_down",
+            (true, true) => "move_down_and_modify_selection",
+            (false, false) => "move_up",
+            (false, true) => "move_up_and_modify_selection",
+        };
+        for _ in 0..count {
+            let f = self
+                .inner
+                .edit_notify(self.view_id, method, None as Option<()>)
+                .map_err(|_| ());
+            spawn(f);
+        }
+    }
+
     pub fn page_down(&mut self) {
         let f = self.inner.page_down(self.view_id).map_err(|_| ());
         spawn(f);
@@ -65,6 +103,108 @@ impl Client {
         spawn(f);
     }
 
+    /// Search for `term`, and select its first occurrence after the
+    /// cursor.
+    pub fn find(&mut self, term: &str) {
+        let inner = self.inner.clone();
+        let view_id = self.view_id;
+        let f = self
+            .inner
+            .find(view_id, term, false, false, false)
+            .and_then(move |_| inner.find_next(view_id, true, true, ModifySelection::Set))
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Search for `term` without moving the cursor. The matches are
+    /// reported with "find_status".
+    pub fn search(&mut self, term: &str) {
+        let f = self
+            .inner
+            .find(self.view_id, term, false, false, false)
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Like `find`, but search from `line` and `column` rather than
+    /// from the cursor.
+    pub fn find_from(&mut self, line: u64, column: u64, term: &str) {
+        let inner = self.inner.clone();
+        let view_id = self.view_id;
+        let term = term.to_owned();
+        let f = self
+            .inner
+            .click_point_select(view_id, line, column)
+            .and_then(move |_| {
+                let finder = inner.clone();
+                inner
+                    .find(view_id, &term, false, false, false)
+                    .and_then(move |_| finder.find_next(view_id, true, true, ModifySelection::Set))
+            })
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Select the next occurrence of the current search, wrapping
+    /// around at the end of the buffer.
+    pub fn find_next(&mut self) {
+        let f = self
+            .inner
+            .find_next(self.view_id, true, false, ModifySelection::Set)
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Select the previous occurrence of the current search, wrapping
+    /// around at the start of the buffer.
+    pub fn find_prev(&mut self) {
+        let f = self
+            .inner
+            .find_prev(self.view_id, true, false, ModifySelection::Set)
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Search for the whole word `word`, matching case.
+    pub fn find_word(&mut self, word: &str) {
+        let f = self
+            .inner
+            .find(self.view_id, word, true, false, true)
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Select every match of the current search.
+    pub fn find_all(&mut self) {
+        let f = self.inner.find_all(self.view_id).map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Select the first `count` matches of the current search from the
+    /// cursor, wrapping around at the end of the buffer.
+    pub fn find_next_matches(&mut self, count: usize) {
+        let inner = self.inner.clone();
+        let view_id = self.view_id;
+        let f = stream::iter_ok(0..count)
+            .for_each(move |idx| {
+                // The first match replaces the selections, the next
+                // ones are added to it.
+                let modify = if idx == 0 {
+                    ModifySelection::Set
+                } else {
+                    ModifySelection::Add
+                };
+                inner.find_next(view_id, true, idx == 0, modify)
+            })
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    pub fn goto_line(&mut self, line: u64) {
+        let f = self.inner.goto_line(self.view_id, line).map_err(|_| ());
+        spawn(f);
+    }
+
     pub fn home(&mut self) {
         let f = self.inner.line_start(self.view_id).map_err(|_| ());
         spawn(f);
@@ -75,6 +215,18 @@ impl Client {
         spawn(f);
     }
 
+    /// Like `home`, extending the selection.
+    pub fn home_sel(&mut self) {
+        let f = self.inner.line_start_sel(self.view_id).map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Like `end`, extending the selection.
+    pub fn end_sel(&mut self) {
+        let f = self.inner.line_end_sel(self.view_id).map_err(|_| ());
+        spawn(f);
+    }
+
     pub fn delete(&mut self) {
         let f = self.inner.delete(self.view_id).map_err(|_| ());
         spawn(f);
@@ -85,6 +237,101 @@ impl Client {
         spawn(f);
     }
 
+    /// Delete from each cursor to the next word boundary if `forward`,
+    /// or else to the previous one. Selections are deleted instead.
+    pub fn delete_word(&mut self, forward: bool) {
+        let method = if forward {
+            "delete_word_forward"
+        } else {
+            "delete_word_backward"
+        };
+        let f = self
+            .inner
+            .edit_notify(self.view_id, method, None as Option<()>)
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Delete from each cursor to the end of its line, or the line
+    /// ending if the cursor is at the end of the line already.
+    pub fn delete_to_end_of_line(&mut self) {
+        let f = self
+            .inner
+            .edit_notify(
+                self.view_id,
+                "delete_to_end_of_paragraph",
+                None as Option<()>,
+            )
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    pub fn delete_to_beginning_of_line(&mut self) {
+        let f = self
+            .inner
+            .edit_notify(
+                self.view_id,
+                "delete_to_beginning_of_line",
+                None as Option<()>,
+            )
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    pub fn duplicate_line(&mut self) {
+        let f = self
+            .inner
+            .edit_notify(self.view_id, "duplicate_line", None as Option<()>)
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Swap the characters around each cursor. The core does it as a
+    /// single edit, so it is undone in one step.
+    pub fn transpose(&mut self) {
+        let f = self
+            .inner
+            .edit_notify(self.view_id, "transpose", None as Option<()>)
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    pub fn indent(&mut self) {
+        let f = self
+            .inner
+            .edit_notify(self.view_id, "indent", None as Option<()>)
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    pub fn collapse_selections(&mut self) {
+        let f = self.inner.collapse_selections(self.view_id).map_err(|_| ());
+        spawn(f);
+    }
+
+    pub fn highlight_find(&mut self, visible: bool) {
+        let f = self
+            .inner
+            .highlight_find(self.view_id, visible)
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    pub fn undo(&mut self) {
+        let f = self.inner.undo(self.view_id).map_err(|_| ());
+        spawn(f);
+    }
+
+    pub fn redo(&mut self) {
+        let f = self.inner.redo(self.view_id).map_err(|_| ());
+        spawn(f);
+    }
+
+    pub fn outdent(&mut self) {
+        let f = self.inner.outdent(self.view_id).map_err(|_| ());
+        spawn(f);
+    }
+
     pub fn save(&mut self, file: &str) {
         let f = self.inner.save(self.view_id, file).map_err(|_| ());
         spawn(f);
@@ -98,6 +345,134 @@ impl Client {
         spawn(f);
     }
 
+    /// Select the region between `anchor` and `cursor`, leaving the
+    /// cursor at `cursor`.
+    pub fn select(&mut self, anchor: (u64, u64), cursor: (u64, u64)) {
+        let inner = self.inner.clone();
+        let view_id = self.view_id;
+        let f = self
+            .inner
+            .click_point_select(view_id, anchor.0, anchor.1)
+            .and_then(move |_| inner.drag(view_id, cursor.0, cursor.1))
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Replace the selections with one selection per `(anchor, cursor)`
+    /// pair.
+    pub fn select_regions(&mut self, regions: Vec<((u64, u64), (u64, u64))>) {
+        let inner = self.inner.clone();
+        let view_id = self.view_id;
+        let f = stream::iter_ok(regions.into_iter().enumerate())
+            .for_each(move |(idx, (anchor, cursor))| {
+                let drag = inner.clone();
+                // The first click drops the previous selections, the
+                // next ones add to it.
+                let click = if idx == 0 {
+                    Either::A(inner.click_point_select(view_id, anchor.0, anchor.1))
+                } else {
+                    Either::B(inner.click_toggle_sel(view_id, anchor.0, anchor.1))
+                };
+                click.and_then(move |_| drag.drag(view_id, cursor.0, cursor.1))
+            })
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Replace the selections with the word at each position, as found
+    /// by the core like for a double click.
+    pub fn select_words(&mut self, positions: Vec<(u64, u64)>) {
+        let inner = self.inner.clone();
+        let view_id = self.view_id;
+        let f = stream::iter_ok(positions.into_iter().enumerate())
+            .for_each(move |(idx, (line, column))| {
+                if idx == 0 {
+                    Either::A(inner.click_word_select(view_id, line, column))
+                } else {
+                    Either::B(inner.click_multi_word_select(view_id, line, column))
+                }
+            })
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Add a cursor at the given position. If there is already one
+    /// there, the core removes it instead.
+    pub fn add_cursor(&mut self, line: u64, column: u64) {
+        let f = self
+            .inner
+            .click_toggle_sel(self.view_id, line, column)
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Replace the text between `start` and `end` with `text`, then
+    /// move the cursor to `cursor` and save the buffer to `file`, if
+    /// given. The requests are chained so that they reach the core in
+    /// order.
+    pub fn replace_range(
+        &mut self,
+        start: (u64, u64),
+        end: (u64, u64),
+        text: String,
+        cursor: Option<(u64, u64)>,
+        file: Option<String>,
+    ) {
+        let inner = self.inner.clone();
+        let view_id = self.view_id;
+        let drag = self.inner.clone();
+        let click = self.inner.clone();
+        let save = self.inner.clone();
+        let f = self
+            .inner
+            .click_point_select(view_id, start.0, start.1)
+            .and_then(move |_| drag.drag(view_id, end.0, end.1))
+            .and_then(move |_| inner.insert(view_id, &text))
+            .and_then(move |_| match cursor {
+                Some((line, column)) => Either::A(click.click_point_select(view_id, line, column)),
+                None => Either::B(future::ok(())),
+            })
+            .and_then(move |_| match file {
+                Some(file) => Either::A(save.save(view_id, &file)),
+                None => Either::B(future::ok(())),
+            })
+            .map_err(|_| ());
+        spawn(f);
+    }
+
+    /// Make the core end the new lines of this view with `ending`.
+    pub fn set_line_ending(&mut self, ending: &str) {
+        // xrl only takes a string as the domain, and this setting is
+        // for this view only, so the notification is built by hand.
+        let params = format!(
+            r#"{{"domain": {{"user_override": "{}"}}, "changes": {{"line_ending": "{}"}}}}"#,
+            self.view_id,
+            ending.escape_default()
+        );
+        match params.parse() {
+            Ok(params) => {
+                let f = self
+                    .inner
+                    .notify("modify_user_config", params)
+                    .map_err(|_| ());
+                spawn(f);
+            }
+            Err(e) => error!("failed to build the line ending change: {:?}", e),
+        }
+    }
+
+    /// Click `count` times in a row at `line` and `column`: twice
+    /// selects the word there, and three times the line.
+    pub fn multi_click(&mut self, line: u64, column: u64, count: u64) {
+        // The "click" edit takes the line, column, modifiers and click
+        // count.
+        let f = self
+            .inner
+            .edit_notify(self.view_id, "click", Some((line, column, 0, count)))
+            .map_err(|_| ());
+        spawn(f);
+    }
+
     pub fn drag(&mut self, line: u64, column: u64) {
         let f = self.inner.drag(self.view_id, line, column).map_err(|_| ());
         spawn(f);
@@ -0,0 +1,40 @@
+//! Commenting and uncommenting lines with a line comment token, such
+//! as `#` or `//`.
+
+/// Split the leading whitespace from the rest of the line.
+fn split_indent(line: &str) -> (&str, &str) {
+    let idx = line.len() - line.trim_start().len();
+    line.split_at(idx)
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// Comment the given lines, or uncomment them if they're all
+/// commented already. The token goes after the indentation of each
+/// line. Blank lines are left alone.
+pub fn toggle(lines: &[&str], token: &str) -> Vec<String> {
+    let commented = lines
+        .iter()
+        .filter(|line| !is_blank(line))
+        .all(|line| split_indent(line).1.starts_with(token));
+
+    lines
+        .iter()
+        .map(|line| {
+            if is_blank(line) {
+                return (*line).to_owned();
+            }
+            let (indent, rest) = split_indent(line);
+            if commented {
+                let rest = rest.strip_prefix(token).unwrap_or(rest);
+                // Also remove the space we insert after the token
+                let rest = rest.strip_prefix(' ').unwrap_or(rest);
+                format!("{}{}", indent, rest)
+            } else {
+                format!("{}{} {}", indent, token, rest)
+            }
+        })
+        .collect()
+}
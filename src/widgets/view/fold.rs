@@ -0,0 +1,26 @@
+//! Folds hide ranges of lines, collapsing them into the line that
+//! precedes them.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fold {
+    /// The line the fold is collapsed into. It remains visible.
+    pub start: u64,
+    /// The last line hidden by the fold.
+    pub end: u64,
+}
+
+impl Fold {
+    pub fn new(start: u64, end: u64) -> Self {
+        Fold { start, end }
+    }
+
+    /// Whether the given line is hidden by this fold.
+    pub fn hides(&self, line: u64) -> bool {
+        line > self.start && line <= self.end
+    }
+
+    /// The number of lines hidden by this fold.
+    pub fn hidden_lines(&self) -> u64 {
+        self.end - self.start
+    }
+}
@@ -0,0 +1,71 @@
+//! Moving between lines by their indentation.
+
+/// Return the width of the indentation of `line`, with tabs reaching
+/// the next multiple of `tab_width`, or `None` if the line is blank.
+pub fn width(line: &str, tab_width: u16) -> Option<u16> {
+    let tab_width = tab_width.max(1);
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += tab_width - width % tab_width,
+            '\n' | '\r' => return None,
+            _ => return Some(width),
+        }
+    }
+    None
+}
+
+/// Return `line` with its indentation written with tabs, and spaces
+/// for what is left of a tab stop, or only with spaces if
+/// `expand_tabs` is set. Its width is unchanged. Blank lines are
+/// returned as is.
+pub fn normalize(line: &str, tab_width: u16, expand_tabs: bool) -> String {
+    let width = match width(line, tab_width) {
+        Some(width) => usize::from(width),
+        None => return line.to_owned(),
+    };
+    let text = line.trim_start_matches(&[' ', '\t'][..]);
+    let tab_width = usize::from(tab_width.max(1));
+    let indentation = if expand_tabs {
+        " ".repeat(width)
+    } else {
+        "\t".repeat(width / tab_width) + &" ".repeat(width % tab_width)
+    };
+    indentation + text
+}
+
+/// Return the index of the last line of the block under `line`: the
+/// lines after it that are more indented, with the blank lines among
+/// them but not those that end the block. Return `None` if the next
+/// non-blank line is not more indented, or if `line` is blank.
+pub fn block_end(lines: &[&str], line: usize, tab_width: u16) -> Option<usize> {
+    let indent = width(lines.get(line)?, tab_width)?;
+    let mut end = None;
+    for (idx, text) in lines.iter().enumerate().skip(line + 1) {
+        match width(text, tab_width) {
+            Some(width) if width > indent => end = Some(idx),
+            Some(_) => break,
+            None => {}
+        }
+    }
+    end
+}
+
+/// Return the index of the next line (or the previous one if `forward`
+/// is false) after `line` that is indented like it, skipping blank
+/// lines. The search stops at the first or last line.
+pub fn find_same_indent(
+    lines: &[&str],
+    line: usize,
+    forward: bool,
+    tab_width: u16,
+) -> Option<usize> {
+    let indent = width(lines.get(line)?, tab_width)?;
+    let same = |idx: &usize| width(lines[*idx], tab_width) == Some(indent);
+    if forward {
+        (line + 1..lines.len()).find(same)
+    } else {
+        (0..line).rev().find(same)
+    }
+}
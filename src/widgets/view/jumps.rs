@@ -0,0 +1,59 @@
+use std::collections::VecDeque;
+
+use super::selection::Position;
+
+/// Maximum number of positions kept in a jump list.
+const MAX_JUMPS: usize = 100;
+
+/// The positions the cursor was at before big moves, so that users can
+/// go back (and forward again) to them, like Vim's jump list.
+#[derive(Debug, Default)]
+pub struct JumpList {
+    jumps: VecDeque<Position>,
+    /// Index of the current position in the list. It is equal to the
+    /// length of the list when we're not navigating the history.
+    current: usize,
+}
+
+impl JumpList {
+    /// Record the position the cursor is about to jump from. This
+    /// discards the positions we went back from.
+    pub fn push(&mut self, position: Position) {
+        self.jumps.truncate(self.current);
+        if self.jumps.back() != Some(&position) {
+            self.jumps.push_back(position);
+            if self.jumps.len() > MAX_JUMPS {
+                self.jumps.pop_front();
+            }
+        }
+        self.current = self.jumps.len();
+    }
+
+    /// Return the position to go back to, given the current position
+    /// of the cursor.
+    pub fn back(&mut self, from: Position) -> Option<Position> {
+        if self.current == 0 {
+            return None;
+        }
+        // Remember where we come from, so that we can jump forward to
+        // it again.
+        if self.current == self.jumps.len() {
+            self.push(from);
+            self.current = self.jumps.len() - 1;
+        }
+        if self.current == 0 {
+            return None;
+        }
+        self.current -= 1;
+        self.jumps.get(self.current).cloned()
+    }
+
+    /// Return the position to go forward to, if we went back before.
+    pub fn forward(&mut self) -> Option<Position> {
+        if self.current + 1 >= self.jumps.len() {
+            return None;
+        }
+        self.current += 1;
+        self.jumps.get(self.current).cloned()
+    }
+}
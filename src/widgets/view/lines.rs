@@ -0,0 +1,56 @@
+//! Transformations applied to whole lines of a buffer, such as joining
+//! them.
+
+use core::SortConfig;
+
+/// Join `lines` into a single line. The leading whitespace of each
+/// line but the first is replaced with a single space, like Vim's `J`.
+/// Nothing is added for blank lines.
+pub fn join(lines: &[&str]) -> String {
+    let mut joined = String::new();
+    for (idx, line) in lines.iter().enumerate() {
+        if idx == 0 {
+            joined.push_str(line);
+            continue;
+        }
+        let line = line.trim_start();
+        if line.is_empty() {
+            continue;
+        }
+        if !joined.is_empty() && !joined.ends_with(' ') {
+            joined.push(' ');
+        }
+        joined.push_str(line);
+    }
+    joined
+}
+
+/// Remove the spaces and tabs at the end of `line`.
+pub fn trim_trailing(line: &str) -> &str {
+    line.trim_end_matches(&[' ', '\t'][..])
+}
+
+/// Sort `lines` as described by `config`. Lines that compare equal
+/// keep their order.
+pub fn sort(lines: &[&str], config: &SortConfig) -> Vec<String> {
+    let key = |line: &str| {
+        if config.case_insensitive {
+            line.to_lowercase()
+        } else {
+            line.to_owned()
+        }
+    };
+    let mut keyed: Vec<(String, &str)> = lines.iter().map(|line| (key(line), *line)).collect();
+    keyed.sort_by(|a, b| {
+        let order = a.0.cmp(&b.0);
+        if config.reverse {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+    if config.unique {
+        keyed.dedup_by(|a, b| a.0 == b.0);
+    }
+    keyed.into_iter().map(|(_, line)| line.to_owned()).collect()
+}
@@ -0,0 +1,205 @@
+//! Helpers to navigate the structure of markdown documents.
+
+/// An ATX style markdown heading, such as `## Usage`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    /// Index of the line the heading is on.
+    pub line: usize,
+    /// Number of `#` characters, from 1 to 6.
+    pub level: u8,
+}
+
+/// Return the level of the heading on the given line, if it is one.
+fn heading_level(line: &str) -> Option<u8> {
+    let line = line.trim_end_matches(&['\n', '\r'][..]);
+    // Up to three spaces of indentation are allowed
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let line = &line[indent..];
+    let level = line.len() - line.trim_start_matches('#').len();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    match line[level..].chars().next() {
+        None | Some(' ') | Some('\t') => Some(level as u8),
+        _ => None,
+    }
+}
+
+/// Return the fence that opens or closes a code block on the given
+/// line, if any.
+fn code_fence(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    if line.starts_with("```") {
+        Some("```")
+    } else if line.starts_with("~~~") {
+        Some("~~~")
+    } else {
+        None
+    }
+}
+
+/// Scan `lines` for headings. Lines within fenced code blocks are
+/// ignored, so that `#` comments in code samples are not mistaken for
+/// headings.
+pub fn headings<'a, I>(lines: I) -> Vec<Heading>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut headings = Vec::new();
+    let mut fence: Option<&str> = None;
+    for (idx, line) in lines.into_iter().enumerate() {
+        match (fence, code_fence(line)) {
+            (None, Some(opening)) => fence = Some(opening),
+            (Some(opened), Some(closing)) if opened == closing => fence = None,
+            (Some(_), _) => {}
+            (None, None) => {
+                if let Some(level) = heading_level(line) {
+                    headings.push(Heading { line: idx, level });
+                }
+            }
+        }
+    }
+    headings
+}
+
+/// Find the first heading after (or before, if `forward` is false)
+/// line `from`. If `level` is given, only headings of that level are
+/// considered.
+pub fn find_heading(
+    headings: &[Heading],
+    from: usize,
+    forward: bool,
+    level: Option<u8>,
+) -> Option<&Heading> {
+    let matches_level = |h: &&Heading| match level {
+        Some(level) => h.level == level,
+        None => true,
+    };
+    if forward {
+        headings
+            .iter()
+            .filter(matches_level)
+            .find(|h| h.line > from)
+    } else {
+        headings
+            .iter()
+            .rev()
+            .filter(matches_level)
+            .find(|h| h.line < from)
+    }
+}
+
+/// Compute the range of lines covered by the section under each
+/// heading, as `(heading line, last line of the section)`. A section
+/// ends right before the next heading of the same or a higher level,
+/// so the sections of nested headings are nested too. Headings with
+/// an empty section are skipped.
+pub fn sections(headings: &[Heading], nb_lines: usize) -> Vec<(usize, usize)> {
+    let mut sections = Vec::new();
+    for (idx, heading) in headings.iter().enumerate() {
+        let end = headings[idx + 1..]
+            .iter()
+            .find(|h| h.level <= heading.level)
+            .map_or(nb_lines, |h| h.line);
+        if end > heading.line + 1 {
+            sections.push((heading.line, end - 1));
+        }
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heading(line: usize, level: u8) -> Heading {
+        Heading { line, level }
+    }
+
+    #[test]
+    fn parses_the_six_levels() {
+        for level in 1..=6 {
+            let line = format!("{} Title", "#".repeat(level));
+            assert_eq!(heading_level(&line), Some(level as u8));
+        }
+        assert_eq!(heading_level("####### Too deep"), None);
+        assert_eq!(heading_level("#"), Some(1));
+        assert_eq!(heading_level("   ## Indented"), Some(2));
+        assert_eq!(heading_level("    ## Code"), None);
+        assert_eq!(heading_level("#hashtag"), None);
+    }
+
+    #[test]
+    fn ignores_trailing_carriage_returns() {
+        assert_eq!(heading_level("##\r\n"), Some(2));
+        assert_eq!(headings(vec!["# Title\r", "text\r"]), vec![heading(0, 1)]);
+    }
+
+    #[test]
+    fn skips_comments_in_code_blocks() {
+        let lines = vec![
+            "# Usage",
+            "```sh",
+            "# not a heading",
+            "~~~",
+            "# still code",
+            "```",
+            "## Install",
+        ];
+        assert_eq!(headings(lines), vec![heading(0, 1), heading(6, 2)]);
+    }
+
+    #[test]
+    fn finds_the_next_and_previous_heading_of_a_level() {
+        let found = vec![heading(0, 1), heading(3, 2), heading(5, 3), heading(8, 2)];
+        assert_eq!(find_heading(&found, 0, true, None), Some(&found[1]));
+        assert_eq!(find_heading(&found, 3, true, Some(2)), Some(&found[3]));
+        assert_eq!(find_heading(&found, 8, false, Some(2)), Some(&found[1]));
+        assert_eq!(find_heading(&found, 6, false, None), Some(&found[2]));
+    }
+
+    #[test]
+    fn finds_nothing_past_the_last_heading() {
+        let found = vec![heading(0, 1), heading(3, 2)];
+        assert_eq!(find_heading(&found, 3, true, None), None);
+        assert_eq!(find_heading(&found, 0, false, None), None);
+        assert_eq!(find_heading(&found, 0, true, Some(4)), None);
+        assert_eq!(find_heading(&[], 0, true, None), None);
+    }
+
+    #[test]
+    fn the_last_section_ends_with_the_document() {
+        let found = vec![heading(0, 1), heading(4, 1)];
+        assert_eq!(sections(&found, 10), vec![(0, 3), (4, 9)]);
+    }
+
+    #[test]
+    fn nested_sections_end_before_the_next_heading_as_high() {
+        let found = vec![
+            heading(0, 1),
+            heading(2, 2),
+            heading(4, 3),
+            heading(6, 2),
+            heading(8, 1),
+        ];
+        assert_eq!(
+            sections(&found, 10),
+            vec![(0, 7), (2, 5), (4, 5), (6, 7), (8, 9)]
+        );
+    }
+
+    #[test]
+    fn empty_sections_are_skipped() {
+        let found = vec![heading(0, 1), heading(1, 2), heading(3, 1)];
+        assert_eq!(sections(&found, 4), vec![(0, 2), (1, 2)]);
+    }
+
+    #[test]
+    fn there_are_no_sections_without_headings() {
+        let lines = vec!["plain", "text"];
+        assert_eq!(sections(&headings(lines), 2), vec![]);
+    }
+}
@@ -1,9 +1,25 @@
+mod brackets;
+mod case;
 mod cfg;
 mod client;
+mod comment;
+mod fold;
+mod indent;
+mod jumps;
+mod lines;
+mod markdown;
+mod number;
+mod search;
+mod selection;
+mod stats;
 mod style;
+mod surround;
+mod symbols;
 #[allow(clippy::module_inception)]
 mod view;
 mod window;
+mod wrap;
 
 pub use self::client::Client as ViewClient;
+pub use self::symbols::Symbol;
 pub use self::view::View;
@@ -0,0 +1,89 @@
+//! Incrementing the number under the cursor, like Vim's `^a` and `^x`.
+
+/// A number found in a line, as the byte offsets of its first and
+/// past its last character, and whether it is hexadecimal.
+struct Number {
+    start: usize,
+    end: usize,
+    hex: bool,
+}
+
+/// Return the numbers of `line`, in order. A `-` right before a
+/// decimal number is part of it, unless it follows a letter or a
+/// digit. Hexadecimal numbers start with `0x` or `0X`.
+fn numbers(line: &str) -> Vec<Number> {
+    let bytes = line.as_bytes();
+    let mut numbers = Vec::new();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if !bytes[idx].is_ascii_digit() {
+            idx += 1;
+            continue;
+        }
+        let is_hex = bytes[idx] == b'0'
+            && matches!(bytes.get(idx + 1), Some(b'x') | Some(b'X'))
+            && bytes.get(idx + 2).is_some_and(u8::is_ascii_hexdigit);
+        let mut end = if is_hex { idx + 2 } else { idx };
+        let is_digit = |b: &u8| {
+            if is_hex {
+                b.is_ascii_hexdigit()
+            } else {
+                b.is_ascii_digit()
+            }
+        };
+        while bytes.get(end).is_some_and(is_digit) {
+            end += 1;
+        }
+        let negative = !is_hex
+            && idx > 0
+            && bytes[idx - 1] == b'-'
+            && (idx < 2 || !bytes[idx - 2].is_ascii_alphanumeric());
+        numbers.push(Number {
+            start: if negative { idx - 1 } else { idx },
+            end,
+            hex: is_hex,
+        });
+        idx = end;
+    }
+    numbers
+}
+
+/// Add `delta` to the number under `column` of `line`, or to the first
+/// one after it. Leading zeros are kept, so that the number keeps its
+/// width, and hexadecimal numbers keep the case of their digits.
+///
+/// Return the new line, and the offset of the last character of the
+/// number, or `None` if there is no number there.
+pub fn increment(line: &str, column: usize, delta: i64) -> Option<(String, usize)> {
+    let number = numbers(line).into_iter().find(|n| n.end > column)?;
+    let text = &line[number.start..number.end];
+    let replacement = if number.hex {
+        let (prefix, digits) = text.split_at(2);
+        let value = u64::from_str_radix(digits, 16).ok()?;
+        let value = value.wrapping_add(delta as u64);
+        let width = digits.len();
+        if digits.chars().any(|c| c.is_ascii_uppercase()) {
+            format!("{}{:0width$X}", prefix, value, width = width)
+        } else {
+            format!("{}{:0width$x}", prefix, value, width = width)
+        }
+    } else {
+        let value = text.parse::<i64>().ok()?.saturating_add(delta);
+        let digits = text.trim_start_matches('-');
+        if digits.len() > 1 && digits.starts_with('0') {
+            let sign = if value < 0 { "-" } else { "" };
+            let width = digits.len();
+            format!("{}{:0width$}", sign, value.unsigned_abs(), width = width)
+        } else {
+            value.to_string()
+        }
+    };
+    let end = number.start + replacement.len() - 1;
+    let new_line = format!(
+        "{}{}{}",
+        &line[..number.start],
+        replacement,
+        &line[number.end..]
+    );
+    Some((new_line, end))
+}
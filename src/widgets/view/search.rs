@@ -0,0 +1,104 @@
+//! The state of the current search in a view, as reported by the core
+//! in its "find_status" notifications.
+
+use xrl::FindStatus;
+
+use super::selection::Position;
+
+#[derive(Debug, Default)]
+pub struct Search {
+    /// The string searched for.
+    term: Option<String>,
+    case_sensitive: bool,
+    /// Total number of matches, or the maximum number of results if
+    /// there are more.
+    matches: u64,
+    /// Whether matches were left out because there are more than the
+    /// maximum number of results.
+    capped: bool,
+    /// The line of each match, starting at 0. A line appears once
+    /// per match it contains.
+    lines: Vec<u64>,
+}
+
+impl Search {
+    /// Read the matches of the search from `status`, keeping only the
+    /// first `max_results` matches if given.
+    pub fn new(status: FindStatus, max_results: Option<usize>) -> Search {
+        let mut search = Search::default();
+        for query in status.queries {
+            search.matches += query.matches;
+            // The core counts lines from 1
+            search
+                .lines
+                .extend(query.lines.iter().map(|line| line.saturating_sub(1)));
+            search.case_sensitive = query.case_sensitive.unwrap_or(false);
+            search.term = query.chars;
+        }
+        search.lines.sort();
+        if let Some(max) = max_results {
+            if search.matches > max as u64 {
+                search.matches = max as u64;
+                search.lines.truncate(max);
+                search.capped = true;
+            }
+        }
+        search
+    }
+
+    /// Whether a search was made in this view.
+    pub fn is_active(&self) -> bool {
+        self.term.is_some()
+    }
+
+    /// The string searched for, if a search was made.
+    pub fn term(&self) -> Option<&str> {
+        self.term.as_deref()
+    }
+
+    pub fn matches(&self) -> u64 {
+        self.matches
+    }
+
+    /// Whether there are more matches than the maximum number of
+    /// results.
+    pub fn is_capped(&self) -> bool {
+        self.capped
+    }
+
+    /// Return the lines that have at least one match.
+    pub fn lines(&self) -> Vec<u64> {
+        let mut lines = self.lines.clone();
+        lines.dedup();
+        lines
+    }
+
+    /// Return the index (starting at 1) of the match that ends at the
+    /// cursor or right before it. `line_text` is the text of the
+    /// cursor line.
+    pub fn current(&self, cursor: Position, line_text: &str) -> Option<u64> {
+        let term = self.term.as_ref()?;
+        if self.matches == 0 || term.is_empty() {
+            return None;
+        }
+        let before = self.lines.iter().filter(|&&line| line < cursor.0).count() as u64;
+        // The core does not tell where the matches are in the line, so
+        // count the occurrences of the term that start before the
+        // cursor.
+        let (text, term) = if self.case_sensitive {
+            (line_text.to_owned(), term.to_owned())
+        } else {
+            (line_text.to_ascii_lowercase(), term.to_ascii_lowercase())
+        };
+        let in_line = text
+            .match_indices(term.as_str())
+            .filter(|&(idx, _)| (idx as u64) < cursor.1)
+            .count() as u64;
+        if before + in_line == 0 {
+            // The cursor is before the first match
+            None
+        } else {
+            Some(before + in_line)
+        }
+    }
+}
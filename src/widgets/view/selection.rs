@@ -0,0 +1,131 @@
+//! Selections are not sent explicitly by the core: they are styles
+//! (with style ID 0) applied to the selected portions of each line.
+//! This module rebuilds the selected regions from these styles.
+
+/// A position in a buffer, as a line number and an offset within that
+/// line.
+pub type Position = (u64, u64);
+
+/// A selected portion of a line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub line: u64,
+    pub start: u64,
+    pub end: u64,
+    /// Whether the span reaches the end of the line, in which case
+    /// the selection may continue on the next line.
+    pub reaches_eol: bool,
+}
+
+/// A selected region, with the end that stays in place (the anchor)
+/// and the end that moves with the cursor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub anchor: Position,
+    pub cursor: Position,
+}
+
+impl Selection {
+    /// Return the same region, with the anchor and cursor swapped.
+    pub fn swapped(&self) -> Selection {
+        Selection {
+            anchor: self.cursor,
+            cursor: self.anchor,
+        }
+    }
+}
+
+/// Merge the selected spans (ordered by line and offset) into regions
+/// given as `(start, end)` positions.
+pub fn regions(spans: &[Span]) -> Vec<(Position, Position)> {
+    let mut regions: Vec<(Position, Position)> = Vec::new();
+    let mut continues = false;
+    for span in spans {
+        match regions.last_mut() {
+            Some(ref mut region)
+                if continues && span.start == 0 && span.line == (region.1).0 + 1 =>
+            {
+                region.1 = (span.line, span.end);
+            }
+            _ => regions.push(((span.line, span.start), (span.line, span.end))),
+        }
+        continues = span.reaches_eol;
+    }
+    regions
+}
+
+/// Find the region that has one of its ends at the cursor, and return
+/// it as a selection.
+pub fn selection_at(regions: &[(Position, Position)], cursor: Position) -> Option<Selection> {
+    regions.iter().find_map(|&(start, end)| {
+        if cursor == end {
+            Some(Selection {
+                anchor: start,
+                cursor: end,
+            })
+        } else if cursor == start {
+            Some(Selection {
+                anchor: end,
+                cursor: start,
+            })
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(line: u64, start: u64, end: u64, reaches_eol: bool) -> Span {
+        Span {
+            line,
+            start,
+            end,
+            reaches_eol,
+        }
+    }
+
+    // A selection from (1, 4) to (3, 2)
+    fn spans() -> Vec<Span> {
+        vec![
+            span(1, 4, 10, true),
+            span(2, 0, 8, true),
+            span(3, 0, 2, false),
+        ]
+    }
+
+    #[test]
+    fn swaps_a_forward_selection() {
+        let regions = regions(&spans());
+        assert_eq!(regions, vec![((1, 4), (3, 2))]);
+        let selection = selection_at(&regions, (3, 2)).unwrap();
+        assert_eq!(selection.anchor, (1, 4));
+        let swapped = selection.swapped();
+        assert_eq!(swapped.anchor, (3, 2));
+        assert_eq!(swapped.cursor, (1, 4));
+        // Swapping from the new cursor goes back to the forward selection
+        assert_eq!(
+            selection_at(&regions, swapped.cursor).unwrap().swapped(),
+            selection
+        );
+    }
+
+    #[test]
+    fn swaps_a_backward_selection() {
+        let regions = regions(&spans());
+        let selection = selection_at(&regions, (1, 4)).unwrap();
+        assert_eq!(selection.anchor, (3, 2));
+        let swapped = selection.swapped();
+        assert_eq!(swapped.anchor, (1, 4));
+        assert_eq!(swapped.cursor, (3, 2));
+    }
+
+    #[test]
+    fn needs_the_cursor_at_an_end() {
+        let regions = regions(&spans());
+        assert_eq!(selection_at(&regions, (2, 3)), None);
+        assert_eq!(selection_at(&[], (1, 4)), None);
+    }
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+
+/// Statistics about the content of a buffer.
+#[derive(Debug, Default, PartialEq)]
+pub struct BufferStats {
+    pub lines: usize,
+    pub words: usize,
+    pub chars: usize,
+}
+
+impl BufferStats {
+    /// Compute the statistics for the given lines. Each line is
+    /// expected to include its trailing newline, if any.
+    pub fn compute<'a, I>(lines: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut stats = BufferStats::default();
+        for line in lines {
+            // The only empty line is the one following the final
+            // newline of a file. It does not count as a line.
+            if line.is_empty() {
+                continue;
+            }
+            stats.lines += 1;
+            stats.words += line.split_whitespace().count();
+            stats.chars += line.chars().count();
+        }
+        stats
+    }
+}
+
+impl fmt::Display for BufferStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} lines, {} words, {} characters",
+            self.lines, self.words, self.chars
+        )
+    }
+}
@@ -12,8 +12,14 @@ fn get_color(argb_color: u32) -> color::Rgb {
     color::Rgb(r, g, b)
 }
 
+/// Whether `style` is the selection style without colors from the
+/// theme, which is drawn in reverse video.
+fn is_plain_selection(style: &Style) -> bool {
+    style.id == 0 && style.fg_color.is_none() && style.bg_color.is_none()
+}
+
 pub fn set_style(style: &Style) -> Result<String, Error> {
-    if style.id == 0 {
+    if is_plain_selection(style) {
         return Ok(format!("{}", termion::style::Invert));
     }
 
@@ -45,7 +51,7 @@ pub fn set_style(style: &Style) -> Result<String, Error> {
 }
 
 pub fn reset_style(style: &Style) -> Result<String, Error> {
-    if style.id == 0 {
+    if is_plain_selection(style) {
         return Ok(format!("{}", termion::style::NoInvert));
     }
 
@@ -0,0 +1,50 @@
+//! Wrapping selected regions between an opening and a closing string.
+
+use super::selection::Position;
+
+/// Insert `open` at the start and `close` at the end of each region of
+/// `texts`, the lines starting at line `first`. Regions are given as
+/// `(start, end)` positions, with byte offsets within the lines.
+///
+/// Return the new lines, and how many bytes were inserted on `line`
+/// up to `column` included, to keep a cursor there in place.
+pub fn wrap(
+    texts: &[&str],
+    first: u64,
+    regions: &[(Position, Position)],
+    open: &str,
+    close: &str,
+    (line, column): Position,
+) -> (Vec<String>, u64) {
+    // Each insertion is the line, the offset, and whether it is a
+    // closing string. At the same offset, a region ending goes before
+    // the next one starting.
+    let mut insertions: Vec<(u64, u64, bool)> = regions
+        .iter()
+        .flat_map(|&(start, end)| vec![(start.0, start.1, false), (end.0, end.1, true)])
+        .collect();
+    insertions.sort_by_key(|&(line, offset, closing)| (line, offset, !closing));
+
+    let mut shift = 0;
+    let lines = texts
+        .iter()
+        .zip(first..)
+        .map(|(text, text_line)| {
+            let mut new_line = String::with_capacity(text.len());
+            let mut copied = 0;
+            for &(_, offset, closing) in insertions.iter().filter(|i| i.0 == text_line) {
+                let offset = (offset as usize).min(text.len());
+                let inserted = if closing { close } else { open };
+                new_line.push_str(&text[copied..offset]);
+                new_line.push_str(inserted);
+                copied = offset;
+                if text_line == line && offset as u64 <= column {
+                    shift += inserted.len() as u64;
+                }
+            }
+            new_line.push_str(&text[copied..]);
+            new_line
+        })
+        .collect();
+    (lines, shift)
+}
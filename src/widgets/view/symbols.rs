@@ -0,0 +1,63 @@
+//! Finding the symbols of a document without help from a plugin.
+
+/// The keywords starting the lines taken as symbols when no plugin
+/// reports them, once visibility and other modifiers are removed.
+const KEYWORDS: &[&str] = &[
+    "fn",
+    "def",
+    "class",
+    "struct",
+    "enum",
+    "trait",
+    "impl",
+    "mod",
+    "function",
+    "interface",
+];
+
+/// The words that may come before a keyword.
+const MODIFIERS: &[&str] = &[
+    "pub", "async", "unsafe", "const", "export", "default", "static",
+];
+
+/// A symbol of a document, such as a function or a class.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    /// What is shown in the list, and matched against the filter.
+    pub name: String,
+    /// The position of the symbol, as a line and column starting at 0.
+    pub line: u64,
+    pub column: u64,
+}
+
+/// Return the symbols found in `lines`, the lines of a document from
+/// line `first`, without help from a plugin: the lines that start with
+/// a keyword like `fn`, `def` or `class`, maybe after modifiers like
+/// `pub`.
+pub fn find_symbols<'a, I: Iterator<Item = &'a str>>(lines: I, first: u64) -> Vec<Symbol> {
+    lines
+        .enumerate()
+        .filter_map(|(idx, text)| {
+            let trimmed = text.trim();
+            let mut words = trimmed.split_whitespace().peekable();
+            // Skip the modifiers, including `pub(crate)`
+            while let Some(word) = words.peek() {
+                if !MODIFIERS.contains(word) && !word.starts_with("pub(") {
+                    break;
+                }
+                words.next();
+            }
+            let keyword = words.next()?;
+            if !KEYWORDS.contains(&keyword) || words.peek().is_none() {
+                return None;
+            }
+            // Drop the body, like a trailing `{` or `:`
+            let name = trimmed.trim_end_matches(&['{', ':', ';'][..]).trim_end();
+            Some(Symbol {
+                name: name.to_owned(),
+                line: idx as u64 + first,
+                column: (text.len() - text.trim_start().len()) as u64,
+            })
+        })
+        .collect()
+}
@@ -1,130 +1,1830 @@
 use std::cmp::max;
 use std::collections::HashMap;
 use std::io::Write;
+use std::mem;
+use std::time::{Duration, Instant};
 
 use failure::Error;
-use termion::clear::CurrentLine as ClearLine;
+use termion::clear::UntilNewline;
 use termion::cursor::Goto;
 use termion::event::{Event, Key, MouseButton, MouseEvent};
-use xrl::{ConfigChanges, Line, LineCache, Style, Update};
+use xrl::{ConfigChanges, FindStatus, Line, LineCache, Style, Update};
 
+use core::{
+    location_at, FindConfig, LineEnding, LineNumberMode, LineTarget, MovePoint, SortConfig,
+};
+
+use super::brackets;
+use super::case::{self, Case};
 use super::cfg::ViewConfig;
 use super::client::Client;
+use super::comment;
+use super::fold::Fold;
+use super::indent;
+use super::jumps::JumpList;
+use super::lines;
+use super::markdown;
+use super::number;
+use super::search::Search;
+use super::selection::{self, Span};
+use super::stats::BufferStats;
 use super::style::{reset_style, set_style};
+use super::surround;
+use super::symbols::{self, Symbol};
 use super::window::Window;
+use super::wrap;
+
+/// How many columns are kept visible right of the cursor when scrolling
+/// horizontally, if the view is wide enough.
+const HORIZONTAL_SCROLL_OFF: u16 = 4;
+
+/// The most cursors `find_all` places, to stay responsive when the
+/// search has many matches.
+const MAX_FIND_ALL_CURSORS: u64 = 1000;
+
+/// The longest time between two clicks at the same place for them to
+/// make a double or triple click.
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// How many lines a turn of the mouse wheel scrolls.
+const WHEEL_SCROLL_LINES: u64 = 3;
+
+/// Escape sequences highlighting trailing whitespace, when whitespace
+/// is shown.
+const TRAILING_WHITESPACE_START: &str = "\x1b[41m";
+const TRAILING_WHITESPACE_END: &str = "\x1b[49m";
+
+#[derive(Debug, Default)]
+pub struct Cursor {
+    pub line: u64,
+    pub column: u64,
+}
+
+pub struct View {
+    cache: LineCache,
+    cursor: Cursor,
+    window: Window,
+    file: Option<String>,
+    client: Client,
+    cfg: ViewConfig,
+    /// Whether the buffer matches the file on disk, as reported by
+    /// the core in its last update.
+    pristine: bool,
+    /// The folded line ranges.
+    folds: Vec<Fold>,
+    /// The positions the cursor jumped from.
+    jumps: JumpList,
+    /// The current search.
+    search: Search,
+    /// Whether edits are refused.
+    read_only: bool,
+    /// The number of columns scrolled past on the left, when lines are
+    /// not wrapped.
+    h_scroll: u16,
+    /// Where the cursor was, and the first line shown, before the
+    /// search being typed in the prompt moved them.
+    search_origin: Option<SearchOrigin>,
+    /// Where the cursor was before the position typed in the goto
+    /// prompt was previewed.
+    goto_origin: Option<SearchOrigin>,
+    /// The number of matches of the current search past which they
+    /// are not counted.
+    max_results: Option<usize>,
+    /// Whether `find_all` searched for the word under the cursor and
+    /// waits for its matches.
+    find_all_pending: bool,
+    /// The search made in the view, and its maximum number of results,
+    /// before `search` searched for something else.
+    saved_search: Option<(Search, Option<usize>)>,
+    /// Where the cursor was when the buffer was last edited from this
+    /// view.
+    last_edit: Option<(u64, u64)>,
+    /// The syntax the buffer is highlighted with, as reported by the
+    /// core.
+    language: Option<String>,
+    /// The column and row of the terminal, starting at 0, where the
+    /// view is drawn from.
+    origin: (u16, u16),
+    /// When and where the last click was, and how many clicks in a row
+    /// it made, to detect double and triple clicks.
+    last_click: Option<(Instant, (u64, u64), u64)>,
+    /// Whether the view was scrolled with the mouse wheel. The cursor
+    /// is not kept visible then, until it moves.
+    detached: bool,
+    /// The first visible line when the view lost the focus, to scroll
+    /// back there when it gets it again.
+    saved_start: Option<u64>,
+}
+
+/// The position to go back to when an incremental search is
+/// cancelled.
+#[derive(Debug, Clone, Copy)]
+struct SearchOrigin {
+    line: u64,
+    column: u64,
+    start: u64,
+}
+
+impl View {
+    pub fn new(client: Client, file: Option<String>) -> View {
+        View {
+            cache: LineCache::default(),
+            cursor: Default::default(),
+            window: Window::new(),
+            cfg: ViewConfig::default(),
+            client,
+            file,
+            pristine: true,
+            folds: Vec::new(),
+            jumps: JumpList::default(),
+            search: Search::default(),
+            read_only: false,
+            h_scroll: 0,
+            search_origin: None,
+            goto_origin: None,
+            max_results: None,
+            find_all_pending: false,
+            saved_search: None,
+            last_edit: None,
+            language: None,
+            origin: (0, 0),
+            last_click: None,
+            detached: false,
+            saved_start: None,
+        }
+    }
+
+    pub fn update_cache(&mut self, update: Update) {
+        info!("updating cache");
+        self.pristine = update.pristine;
+        let height = self.cache.height();
+        self.cache.update(update);
+        // Folds are stored as line numbers, so they become meaningless
+        // as soon as lines are inserted or removed.
+        if self.cache.height() != height && !self.folds.is_empty() {
+            info!("number of lines changed, dropping folds");
+            self.folds.clear();
+        }
+    }
+
+    pub fn file(&self) -> Option<&String> {
+        self.file.as_ref()
+    }
+
+    /// The syntax the buffer is highlighted with, if the core said.
+    pub fn language(&self) -> Option<&String> {
+        self.language.as_ref()
+    }
+
+    pub fn set_language(&mut self, language: String) {
+        self.language = Some(language);
+    }
+
+    /// Set the file the buffer is saved to.
+    pub fn set_file(&mut self, file: String) {
+        self.file = Some(file);
+    }
+
+    /// Whether edits to the buffer are refused.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn toggle_read_only(&mut self) {
+        self.read_only = !self.read_only;
+    }
+
+    /// Whether long lines are wrapped at the width of the terminal.
+    pub fn is_wrapped(&self) -> bool {
+        self.cfg.wrap
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.cfg.wrap = !self.cfg.wrap;
+    }
+
+    /// Whether the buffer has changes that have not been saved yet.
+    pub fn is_dirty(&self) -> bool {
+        !self.pristine
+    }
+
+    /// Return the line and column of the cursor.
+    pub fn cursor_position(&self) -> (u64, u64) {
+        (self.cursor.line, self.cursor.column)
+    }
+
+    /// Describe the position of the cursor, like "Ln 12, Col 5", and
+    /// the number of cursors if there are several. The column is the
+    /// one the cursor is displayed at, with tabs expanded.
+    pub fn position_indicator(&self) -> String {
+        let position = format!(
+            "Ln {}, Col {}",
+            self.cursor.line + 1,
+            self.cursor_column() + 1
+        );
+        match self.cursor_positions().len() {
+            1 => position,
+            cursors => format!("{}, {} cursors", position, cursors),
+        }
+    }
+
+    pub fn set_cursor(&mut self, line: u64, column: u64) {
+        self.cursor = Cursor { line, column };
+        self.detached = false;
+        self.window.set_cursor(&self.cursor);
+    }
+
+    /// Handle a "find_status" notification from the core. Return a
+    /// warning to show if cursors were placed at the matches.
+    pub fn find_status(&mut self, status: FindStatus) -> Option<String> {
+        self.search = Search::new(status, self.max_results);
+        if self.find_all_pending {
+            self.find_all_pending = false;
+            self.select_all_matches()
+        } else {
+            None
+        }
+    }
+
+    /// Whether the search that was submitted last has no match. The
+    /// searches previewed while typing don't count.
+    pub fn search_failed(&self) -> bool {
+        self.search_origin.is_none() && self.search.is_active() && self.search.matches() == 0
+    }
+
+    /// Whether the cursor is on the last line of the buffer.
+    pub fn on_last_line(&self) -> bool {
+        let lines = self.cache.before() + self.cache.lines().len() as u64 + self.cache.after();
+        self.cursor.line + 1 >= lines
+    }
+
+    /// Describe where the cursor is among the matches of the current
+    /// search, like "match 3 of 17". Return `None` if there is no
+    /// search.
+    pub fn search_counter(&self) -> Option<String> {
+        if !self.search.is_active() {
+            return None;
+        }
+        let total = self.search.matches();
+        if total == 0 {
+            return Some("no matches".into());
+        }
+        let line_text = self
+            .cursor
+            .line
+            .checked_sub(self.cache.before())
+            .and_then(|idx| self.cache.lines().get(idx as usize))
+            .map_or("", |line| line.text.as_str());
+        let cursor = (self.cursor.line, self.cursor.column);
+        let total = if self.search.is_capped() {
+            format!("{}+", total)
+        } else {
+            total.to_string()
+        };
+        match self.search.current(cursor, line_text) {
+            Some(current) => Some(format!("match {} of {}", current, total)),
+            None => Some(format!("{} matches", total)),
+        }
+    }
+
+    /// Return the lines that have a match for the current search,
+    /// with their text if it is in cache.
+    pub fn find_matches(&self) -> Vec<(u64, String)> {
+        self.search
+            .lines()
+            .iter()
+            .map(|line| {
+                let text = line
+                    .checked_sub(self.cache.before())
+                    .and_then(|idx| self.cache.lines().get(idx as usize))
+                    .map_or_else(String::new, |l| l.text.clone());
+                (*line, text)
+            })
+            .collect()
+    }
+
+    /// Search for `config.term`. If an incremental search was in
+    /// progress, it is kept, and the position it started from is
+    /// recorded in the jump list.
+    pub fn find(&mut self, config: &FindConfig) {
+        let term = &config.term;
+        self.max_results = config.max_results;
+        match self.search_origin.take() {
+            Some(origin) => {
+                self.jumps.push((origin.line, origin.column));
+                self.client.find_from(origin.line, origin.column, term)
+            }
+            None => {
+                self.record_jump();
+                self.client.find(term)
+            }
+        }
+    }
+
+    /// Search for `config.term` without moving the cursor, to list the
+    /// matches once they are reported. The search made in the view
+    /// before is restored by `end_search`.
+    pub fn search(&mut self, config: &FindConfig) {
+        if self.saved_search.is_none() {
+            let search = mem::take(&mut self.search);
+            self.saved_search = Some((search, self.max_results));
+        }
+        self.max_results = config.max_results;
+        self.client.search(&config.term)
+    }
+
+    /// Return the matches of the search started by `search`, and go
+    /// back to the search made in the view before, if any.
+    pub fn end_search(&mut self) -> Vec<(u64, String)> {
+        let matches = self.find_matches();
+        if let Some((search, max_results)) = self.saved_search.take() {
+            match search.term() {
+                Some(term) => self.client.search(term),
+                None => self.client.highlight_find(false),
+            }
+            self.search = search;
+            self.max_results = max_results;
+        }
+        matches
+    }
+
+    /// Show the first match of `config.term` after the position the
+    /// incremental search started from, remembering that position on
+    /// the first call.
+    pub fn preview_find(&mut self, config: &FindConfig) {
+        self.max_results = config.max_results;
+        let start = self.window.start();
+        let cursor = &self.cursor;
+        let origin = *self.search_origin.get_or_insert(SearchOrigin {
+            line: cursor.line,
+            column: cursor.column,
+            start,
+        });
+        self.client
+            .find_from(origin.line, origin.column, &config.term)
+    }
+
+    /// Stop the incremental search, putting the cursor and the scroll
+    /// position back where they were before it.
+    pub fn cancel_find_preview(&mut self) {
+        if let Some(origin) = self.search_origin.take() {
+            self.set_cursor(origin.line, origin.column);
+            self.window.set_start(origin.start);
+            self.client.click(origin.line, origin.column);
+            self.clear_search_highlight();
+        }
+    }
+
+    pub fn find_next(&mut self) {
+        self.client.find_next()
+    }
+
+    /// Place a cursor at every match of the current search. If there
+    /// is no search, search for the word under the cursor first. Return
+    /// a message to show if the cursors could not all be placed.
+    pub fn find_all(&mut self) -> Option<String> {
+        if self.search.is_active() {
+            return self.select_all_matches();
+        }
+        match self.word_under_cursor() {
+            Some(word) => {
+                self.find_all_pending = true;
+                self.client.find_word(&word);
+                None
+            }
+            None => Some("no search and no word under the cursor".into()),
+        }
+    }
+
+    fn select_all_matches(&mut self) -> Option<String> {
+        let matches = self.search.matches();
+        if matches == 0 {
+            return Some("no matches".into());
+        }
+        if matches <= MAX_FIND_ALL_CURSORS && !self.search.is_capped() {
+            self.client.find_all();
+            return None;
+        }
+        // Selecting the matches one by one lets us stop early, which
+        // the core's find_all does not.
+        let count = matches.min(MAX_FIND_ALL_CURSORS);
+        self.client.find_next_matches(count as usize);
+        Some(format!(
+            "too many matches, only placed {} cursors from the cursor on",
+            count
+        ))
+    }
+
+    /// Return the word the cursor is in or right after, if its line is
+    /// in cache.
+    fn word_under_cursor(&self) -> Option<String> {
+        let text = self.line_texts(self.cursor.line, self.cursor.line)?[0];
+        let column = (self.cursor.column as usize).min(text.len());
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let start = text
+            .get(..column)?
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_word(c))
+            .last()
+            .map_or(column, |(idx, _)| idx);
+        let end = text[column..]
+            .find(|c: char| !is_word(c))
+            .map_or(text.len(), |idx| column + idx);
+        if start == end {
+            None
+        } else {
+            Some(text[start..end].to_owned())
+        }
+    }
+
+    pub fn find_prev(&mut self) {
+        self.client.find_prev()
+    }
+
+    /// Move the cursor to the given line, and column if any.
+    pub fn goto(&mut self, line: u64, column: Option<u64>) {
+        match column {
+            Some(column) => self.client.click(line, column),
+            None => self.client.goto_line(line),
+        }
+    }
+
+    /// Move the cursor to `line` and `column`, as a big move recorded
+    /// in the jump list. The position is clamped to the end of the
+    /// buffer, and to the end of the line if the line is in cache.
+    pub fn goto_position(&mut self, line: u64, column: u64) {
+        let line = line.min(self.cache.height().saturating_sub(1));
+        let column = match self.line_texts(line, line) {
+            Some(texts) => column.min(texts[0].len() as u64),
+            None => column,
+        };
+        self.record_jump();
+        self.client.click(line, column);
+    }
+
+    /// Move the cursor to a line given as typed in the prompt. Lines
+    /// outside of the buffer are clamped to the first or last line.
+    pub fn goto_line_target(&mut self, target: LineTarget) {
+        let line = self.target_line(target, self.cursor.line);
+        self.goto_line(line);
+    }
+
+    /// Like `goto_line_target`, and move to `column` of the line, as a
+    /// number of characters starting at 1.
+    pub fn goto_target_position(&mut self, target: LineTarget, column: u64) {
+        let line = self.target_line(target, self.cursor.line);
+        let column = self.byte_column(line, column.saturating_sub(1));
+        self.goto_position(line, column);
+    }
+
+    /// Show the position typed in the goto prompt, remembering where
+    /// the cursor was on the first call. Relative lines are counted
+    /// from there.
+    pub fn preview_goto(&mut self, target: LineTarget, column: Option<u64>) {
+        let start = self.window.start();
+        let cursor = &self.cursor;
+        let origin = *self.goto_origin.get_or_insert(SearchOrigin {
+            line: cursor.line,
+            column: cursor.column,
+            start,
+        });
+        let line = self.target_line(target, origin.line);
+        let column = column.map_or(0, |column| self.byte_column(line, column.saturating_sub(1)));
+        self.client.click(line, column);
+    }
+
+    /// Put the cursor and the scroll position back where they were
+    /// before the goto prompt was previewed.
+    pub fn cancel_goto_preview(&mut self) {
+        if let Some(origin) = self.goto_origin.take() {
+            self.set_cursor(origin.line, origin.column);
+            self.window.set_start(origin.start);
+            self.client.click(origin.line, origin.column);
+        }
+    }
+
+    /// Return the line `target` designates, relative lines being
+    /// counted from `from`, clamped to the lines of the buffer.
+    fn target_line(&self, target: LineTarget, from: u64) -> u64 {
+        let last = self.cache.height().saturating_sub(1);
+        let line = match target {
+            LineTarget::Absolute(line) => line.saturating_sub(1),
+            LineTarget::Relative(offset) if offset < 0 => {
+                from.saturating_sub(offset.unsigned_abs())
+            }
+            LineTarget::Relative(offset) => from.saturating_add(offset as u64),
+            LineTarget::Last => last,
+            LineTarget::Percent(percent) => {
+                // Round to the nearest line
+                (last * u64::from(percent) + 50) / 100
+            }
+        };
+        line.min(last)
+    }
+
+    /// Return the byte offset of the character at index `column` of
+    /// `line`, or of the end of the line if it is shorter. Lines that
+    /// are not in cache are assumed to be ASCII.
+    fn byte_column(&self, line: u64, column: u64) -> u64 {
+        match self.line_texts(line, line) {
+            Some(texts) => texts[0]
+                .char_indices()
+                .nth(column as usize)
+                .map_or(texts[0].len(), |(idx, _)| idx) as u64,
+            None => column,
+        }
+    }
+
+    pub fn goto_line(&mut self, line: u64) {
+        self.record_jump();
+        self.client.goto_line(line)
+    }
+
+    pub fn config_changed(&mut self, changes: ConfigChanges) {
+        if let Some(tab_size) = changes.tab_size {
+            self.cfg.tab_size = tab_size as u16;
+        }
+        if let Some(expand_tabs) = changes.translate_tabs_to_spaces {
+            self.cfg.expand_tabs = expand_tabs;
+        }
+    }
+
+    pub fn render<W: Write>(
+        &mut self,
+        w: &mut W,
+        styles: &HashMap<u64, Style>,
+    ) -> Result<(), Error> {
+        self.update_window();
+        self.render_lines(w, styles)?;
+        self.render_cursor(w);
+        Ok(())
+    }
+
+    /// Draw the view in the region of the terminal that starts at
+    /// `origin` (a column and a row, from 0) and has the given size.
+    pub fn set_region(&mut self, origin: (u16, u16), height: u16, width: u16) {
+        self.origin = origin;
+        if height != self.window.size() || width != self.cfg.width {
+            self.resize(height, width);
+        }
+    }
+
+    pub fn resize(&mut self, height: u16, width: u16) {
+        self.cfg.width = width;
+        self.window.resize(height);
+        self.update_window();
+        let top = self.cache.before() + self.window.start();
+        let bottom = self.cache.after() + self.window.end();
+        self.client.scroll(top, bottom);
+    }
+
+    /// Remember the scroll position when the view loses the focus.
+    pub fn save_scroll(&mut self) {
+        self.saved_start = Some(self.window.start());
+    }
+
+    /// Scroll back to where the view was when it lost the focus, once
+    /// it was resized to its pane, keeping the cursor visible unless
+    /// the view was scrolled away from it with the mouse wheel.
+    pub fn restore_scroll(&mut self) {
+        let start = match self.saved_start.take() {
+            Some(start) => start,
+            None => return,
+        };
+        self.window.set_start(start);
+        if !self.detached {
+            self.window.set_cursor(&self.cursor);
+        }
+        let top = self.cache.before() + self.window.start();
+        let bottom = self.cache.after() + self.window.end();
+        self.client.scroll(top, bottom);
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.record_edit();
+        self.client.insert(c)
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.record_edit();
+        self.client.insert_str(text)
+    }
+
+    pub fn insert_newline(&mut self) {
+        self.record_edit();
+        self.client.insert_newline()
+    }
+
+    /// Insert a tab at each cursor, or `tab_size` spaces if tabs are
+    /// expanded.
+    pub fn insert_tab(&mut self) {
+        self.record_edit();
+        if self.cfg.expand_tabs {
+            let spaces = " ".repeat(usize::from(self.cfg.tab_size));
+            self.client.insert_str(&spaces)
+        } else {
+            self.client.insert_tab()
+        }
+    }
+
+    pub fn save(&mut self) {
+        self.client.save(self.file.as_ref().unwrap())
+    }
+
+    pub fn back(&mut self) {
+        self.record_edit();
+        self.client.backspace()
+    }
+
+    pub fn delete(&mut self) {
+        self.record_edit();
+        self.client.delete()
+    }
+
+    pub fn duplicate_line(&mut self) {
+        self.record_edit();
+        self.client.duplicate_line()
+    }
+
+    pub fn transpose(&mut self) {
+        self.record_edit();
+        self.client.transpose()
+    }
+
+    pub fn delete_to_eol(&mut self) {
+        self.record_edit();
+        self.client.delete_to_end_of_line()
+    }
+
+    pub fn delete_to_bol(&mut self) {
+        self.record_edit();
+        self.client.delete_to_beginning_of_line()
+    }
+
+    pub fn delete_word(&mut self, forward: bool) {
+        self.record_edit();
+        self.client.delete_word(forward)
+    }
+
+    /// Collapse multiple cursors into one, or else clear the
+    /// selection. Return `false` if there was neither.
+    pub fn escape(&mut self) -> bool {
+        let cursors: usize = self
+            .cache
+            .lines()
+            .iter()
+            .map(|line| line.cursor.len())
+            .sum();
+        if cursors > 1 {
+            self.client.collapse_selections();
+            true
+        } else if !self.selection_spans().is_empty() {
+            self.client.click(self.cursor.line, self.cursor.column);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Stop highlighting the matches of the current search, and
+    /// forget about it.
+    pub fn clear_search_highlight(&mut self) {
+        self.client.highlight_find(false);
+        self.search = Search::default();
+    }
+
+    pub fn undo(&mut self) {
+        self.client.undo()
+    }
+
+    pub fn redo(&mut self) {
+        self.client.redo()
+    }
+
+    /// Indent the lines touched by the selections by one tab stop.
+    pub fn indent(&mut self) {
+        self.record_edit();
+        self.client.indent()
+    }
+
+    /// Remove up to one tab stop of leading whitespace from the lines
+    /// touched by the selections.
+    pub fn unindent(&mut self) {
+        self.record_edit();
+        self.client.outdent()
+    }
+
+    pub fn page_down(&mut self) {
+        self.client.page_down()
+    }
+
+    pub fn page_up(&mut self) {
+        self.client.page_up()
+    }
+
+    /// Scroll so that the cursor line is at the top of the view.
+    pub fn scroll_top(&mut self) {
+        self.scroll_cursor_to(|_| 0)
+    }
+
+    /// Scroll so that the cursor line is in the middle of the view.
+    pub fn scroll_center(&mut self) {
+        self.scroll_cursor_to(|free| free / 2)
+    }
+
+    /// Scroll so that the cursor line is at the bottom of the view.
+    pub fn scroll_bottom(&mut self) {
+        self.scroll_cursor_to(|free| free)
+    }
+
+    /// Scroll without moving the cursor, so that `rows_above(free)`
+    /// rows are shown above the cursor line, `free` being the number of
+    /// rows of the view the cursor line does not take. The view does
+    /// not scroll before the first line, and, unless lines are wrapped,
+    /// past the last one.
+    fn scroll_cursor_to<F: Fn(u64) -> u64>(&mut self, rows_above: F) {
+        let cursor_idx = match self.cursor.line.checked_sub(self.cache.before()) {
+            Some(idx) if (idx as usize) < self.cache.lines().len() => idx as usize,
+            _ => return,
+        };
+        let visible = self.visible_lines();
+        let cursor_line = Self::visible_position(&visible, cursor_idx);
+        let size = u64::from(self.window.size());
+        let free = size.saturating_sub(u64::from(self.line_rows(visible[cursor_line])));
+        let target = rows_above(free);
+        let mut start = cursor_line;
+        let mut used = 0;
+        while start > 0 {
+            let rows = u64::from(self.line_rows(visible[start - 1]));
+            if used + rows > target {
+                break;
+            }
+            used += rows;
+            start -= 1;
+        }
+        let mut start = start as u64;
+        if !self.cfg.wrap {
+            start = start.min((visible.len() as u64).saturating_sub(size));
+        }
+        self.window.set_start(start);
+        let top = self.cache.before() + self.window.start();
+        let bottom = self.cache.after() + self.window.end();
+        self.client.scroll(top, bottom);
+    }
+
+    /// Move the cursor by half the height of the view.
+    pub fn half_page(&mut self, down: bool, extend: bool) {
+        let lines = max(self.window.size() / 2, 1);
+        self.client.move_lines(lines, down, extend)
+    }
+
+    pub fn move_left(&mut self) {
+        self.client.left()
+    }
+
+    pub fn move_right(&mut self) {
+        self.client.right()
+    }
+
+    pub fn move_up(&mut self) {
+        match self.line_past_folds(false) {
+            Some(line) => self.move_to_line(line),
+            None => self.client.up(),
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        match self.line_past_folds(true) {
+            Some(line) => self.move_to_line(line),
+            None => self.client.down(),
+        }
+    }
+
+    /// Return the first visible line below (or above if `forward` is
+    /// false) the cursor line, if the line next to it is folded.
+    fn line_past_folds(&self, forward: bool) -> Option<u64> {
+        let mut line = if forward {
+            self.cursor.line + 1
+        } else {
+            self.cursor.line.checked_sub(1)?
+        };
+        if !self.is_folded(line) {
+            return None;
+        }
+        while self.is_folded(line) {
+            line = if forward { line + 1 } else { line - 1 };
+        }
+        if line >= self.cache.height() {
+            return None;
+        }
+        Some(line)
+    }
+
+    /// Move the cursor to `line`, keeping its column if the line is
+    /// long enough.
+    fn move_to_line(&mut self, line: u64) {
+        let column = match self.line_texts(line, line) {
+            Some(texts) => self.cursor.column.min(texts[0].len() as u64),
+            None => 0,
+        };
+        self.client.click(line, column);
+    }
+
+    /// Move the cursor to the start or end of its line. The visual
+    /// points stop at the screen row the cursor is on instead, and are
+    /// the same as the others if the line is not wrapped. With
+    /// `extend`, the selection is extended to there.
+    ///
+    /// With `smart_home`, moving to the start of the line goes to its
+    /// first non-blank character, unless the cursor is there already.
+    pub fn move_to(&mut self, point: MovePoint, extend: bool, smart_home: bool) {
+        let line = self.cursor.line;
+        let column = match point {
+            MovePoint::VisualBol => self.cursor_row_bounds().map(|(start, _)| start),
+            MovePoint::VisualEol => self.cursor_row_bounds().map(|(_, end)| end),
+            MovePoint::Bol if smart_home => self
+                .line_texts(line, line)
+                .map(|texts| (texts[0].len() - texts[0].trim_start().len()) as u64)
+                .filter(|indent| *indent != self.cursor.column),
+            MovePoint::Eol | MovePoint::Bol => None,
+        };
+        match (point, column) {
+            (_, Some(column)) if extend => {
+                let cursor = (line, self.cursor.column);
+                self.client.select(cursor, (line, column))
+            }
+            (_, Some(column)) => self.client.click(line, column),
+            (MovePoint::Bol, None) | (MovePoint::VisualBol, None) if extend => {
+                self.client.home_sel()
+            }
+            (MovePoint::Bol, None) | (MovePoint::VisualBol, None) => self.client.home(),
+            (MovePoint::Eol, None) | (MovePoint::VisualEol, None) if extend => {
+                self.client.end_sel()
+            }
+            (MovePoint::Eol, None) | (MovePoint::VisualEol, None) => self.client.end(),
+        }
+    }
+
+    /// Return the first and last columns of the screen row the cursor
+    /// is on, if its line is wrapped over several rows. The last column
+    /// is on the last character of the row, so that the cursor stays on
+    /// it, unless the row ends the line.
+    fn cursor_row_bounds(&self) -> Option<(u64, u64)> {
+        if !self.cfg.wrap || self.is_folded(self.cursor.line) {
+            return None;
+        }
+        let line_idx = self.cursor.line.checked_sub(self.cache.before())? as usize;
+        let rows = self.line_rows(line_idx);
+        if rows < 2 {
+            return None;
+        }
+        let text = self.line_texts(self.cursor.line, self.cursor.line)?[0];
+        let width = self.text_width();
+        let row = (self.cursor_column() / width).min(rows - 1);
+        let mut start = None;
+        let mut column: u16 = 0;
+        for (idx, c) in text.chars().enumerate() {
+            let char_row = column / width;
+            if char_row == row && start.is_none() {
+                start = Some(idx as u64);
+            }
+            if char_row > row {
+                let start = start?;
+                return Some((start, (idx as u64 - 1).max(start)));
+            }
+            column += self.translate_char_width(column, c);
+        }
+        Some((start?, text.chars().count() as u64))
+    }
+
+    /// Add a cursor at the start of `line` (starting at 1), without
+    /// clearing the existing cursors. Lines past the end of the buffer
+    /// are clamped to the last line.
+    pub fn add_cursor_at_line(&mut self, line: u64) {
+        let last_line = self.cache.height().saturating_sub(1);
+        let line = line.saturating_sub(1).min(last_line);
+        self.client.add_cursor(line, 0);
+    }
+
+    /// Set how many lines are kept visible above and below the
+    /// cursor.
+    pub fn set_scroll_off(&mut self, scroll_off: u16) {
+        self.window.set_scroll_off(scroll_off);
+    }
+
+    /// Set the width of the tab stops used to render tabs. This is
+    /// overridden if the core sends a new tab size for the view.
+    pub fn set_tab_width(&mut self, width: u16) {
+        self.cfg.tab_size = width;
+    }
+
+    /// Hide the line numbers, or show them if they're hidden.
+    pub fn toggle_line_numbers(&mut self) {
+        self.cfg.line_numbers = match self.cfg.line_numbers {
+            LineNumberMode::Off => LineNumberMode::Absolute,
+            _ => LineNumberMode::Off,
+        };
+    }
+
+    /// Show or hide spaces and tabs, and highlight trailing whitespace.
+    pub fn toggle_whitespace(&mut self) {
+        self.cfg.show_whitespace = !self.cfg.show_whitespace;
+    }
+
+    pub fn set_line_number_mode(&mut self, mode: LineNumberMode) {
+        self.cfg.line_numbers = mode;
+    }
+
+    /// Return the number displayed in the gutter for `line`.
+    fn displayed_line_number(&self, line: u64) -> u64 {
+        let distance = line.abs_diff(self.cursor.line);
+        match self.cfg.line_numbers {
+            LineNumberMode::Relative => distance,
+            LineNumberMode::Hybrid if distance != 0 => distance,
+            _ => line + 1,
+        }
+    }
+
+    /// Compute statistics about the lines of the buffer. This only
+    /// covers the lines that are in cache, so the second member of
+    /// the tuple tells whether the statistics are complete.
+    pub fn stats(&self) -> (BufferStats, bool) {
+        let stats = BufferStats::compute(self.cache.lines().iter().map(|l| l.text.as_str()));
+        let complete = self.cache.before() == 0 && self.cache.after() == 0;
+        (stats, complete)
+    }
+
+    /// Return the symbols found in the cached lines by their keywords,
+    /// for the outline when no plugin reports them.
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let lines = self.cache.lines().iter().map(|l| l.text.as_str());
+        symbols::find_symbols(lines, self.cache.before())
+    }
+
+    /// Return the selected portions of the cached lines, in order.
+    fn selection_spans(&self) -> Vec<Span> {
+        let mut spans = Vec::new();
+        for (idx, line) in self.cache.lines().iter().enumerate() {
+            let line_len = line.text.trim_end_matches(&['\n', '\r'][..]).len() as u64;
+            let mut prev_style_end: i64 = 0;
+            for style_def in &line.styles {
+                let start = prev_style_end + style_def.offset;
+                let end = start + style_def.length as i64;
+                prev_style_end = end;
+                // Style 0 is the selection
+                if style_def.style_id == 0 && end > start && start >= 0 {
+                    spans.push(Span {
+                        line: idx as u64 + self.cache.before(),
+                        start: start as u64,
+                        end: end as u64,
+                        reaches_eol: end as u64 >= line_len,
+                    });
+                }
+            }
+        }
+        spans
+    }
+
+    /// Return the first and last lines touched by the selections, or
+    /// the cursor line if nothing is selected. A selection ending at
+    /// the very start of a line does not touch that line.
+    fn selected_lines(&self) -> (u64, u64) {
+        let cursor = self.cursor.line;
+        selection::regions(&self.selection_spans()).iter().fold(
+            (cursor, cursor),
+            |(first, last), &(start, end)| {
+                let end_line = if end.1 == 0 && end.0 > start.0 {
+                    end.0 - 1
+                } else {
+                    end.0
+                };
+                (first.min(start.0), last.max(end_line))
+            },
+        )
+    }
+
+    /// Return the lines that have a cursor or are touched by a
+    /// selection, in buffer order.
+    fn cursor_lines(&self) -> Vec<u64> {
+        let before = self.cache.before();
+        let mut lines: Vec<u64> = self
+            .cache
+            .lines()
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| !line.cursor.is_empty())
+            .map(|(idx, _)| idx as u64 + before)
+            .collect();
+        for (start, end) in selection::regions(&self.selection_spans()) {
+            let end_line = if end.1 == 0 && end.0 > start.0 {
+                end.0 - 1
+            } else {
+                end.0
+            };
+            lines.extend(start.0..=end_line);
+        }
+        if lines.is_empty() {
+            lines.push(self.cursor.line);
+        }
+        lines.sort();
+        lines.dedup();
+        lines
+    }
+
+    /// Return the position of each cursor, in buffer order.
+    fn cursor_positions(&self) -> Vec<(u64, u64)> {
+        let before = self.cache.before();
+        let positions: Vec<(u64, u64)> = self
+            .cache
+            .lines()
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, line)| {
+                line.cursor
+                    .iter()
+                    .map(move |&column| (idx as u64 + before, column))
+            })
+            .collect();
+        if positions.is_empty() {
+            vec![(self.cursor.line, self.cursor.column)]
+        } else {
+            positions
+        }
+    }
+
+    /// Select the line of each cursor, with its line ending.
+    pub fn select_line(&mut self) {
+        let mut lines: Vec<u64> = self.cursor_positions().iter().map(|pos| pos.0).collect();
+        lines.dedup();
+        let last = self.cache.height().saturating_sub(1);
+        let regions = lines
+            .into_iter()
+            .filter_map(|line| {
+                if line < last {
+                    Some(((line, 0), (line + 1, 0)))
+                } else {
+                    // The last line has no line ending
+                    let len = self.line_texts(line, line)?[0].len() as u64;
+                    Some(((line, 0), (line, len)))
+                }
+            })
+            .collect();
+        self.client.select_regions(regions);
+    }
+
+    /// Select the word under each cursor, with the same word boundaries
+    /// as the core uses for word moves. A cursor on whitespace selects
+    /// the whitespace around it instead.
+    pub fn select_word(&mut self) {
+        let positions = self.cursor_positions();
+        self.client.select_words(positions);
+    }
+
+    /// Return the text of the lines that have a cursor or a selection,
+    /// each with its line ending, or `None` if some of them are not in
+    /// cache.
+    pub fn copy_lines(&self) -> Option<String> {
+        let before = self.cache.before();
+        let mut text = String::new();
+        for line in self.cursor_lines() {
+            let line = line
+                .checked_sub(before)
+                .and_then(|idx| self.cache.lines().get(idx as usize))?;
+            text.push_str(&line.text);
+            if !line.text.ends_with('\n') {
+                text.push('\n');
+            }
+        }
+        Some(text)
+    }
+
+    /// Delete the lines that have a cursor or a selection, and return
+    /// their text like `copy_lines` does.
+    pub fn cut_lines(&mut self) -> Option<String> {
+        let text = self.copy_lines()?;
+        let cut = self.cursor_lines();
+        let (first, last) = (cut[0], cut[cut.len() - 1]);
+        // The lines between the cut ones are written back in place, so
+        // that everything is deleted in a single edit.
+        let kept: Vec<String> = self
+            .line_texts(first, last)?
+            .iter()
+            .zip(first..)
+            .filter(|(_, line)| cut.binary_search(line).is_err())
+            .map(|(text, _)| format!("{}\n", text))
+            .collect();
+        let kept = kept.concat();
+        self.record_edit();
+        if last + 1 < self.cache.height() {
+            self.client
+                .replace_range((first, 0), (last + 1, 0), kept, None, None);
+        } else {
+            // There is no line after the last one to cut, so delete the
+            // line ending before the first one instead.
+            let last_len = self.line_texts(last, last)?[0].len() as u64;
+            let kept = kept.trim_end_matches('\n').to_owned();
+            match first.checked_sub(1) {
+                Some(prev) => {
+                    let prev_len = self.line_texts(prev, prev)?[0].len() as u64;
+                    let kept = if kept.is_empty() {
+                        kept
+                    } else {
+                        format!("\n{}", kept)
+                    };
+                    self.client
+                        .replace_range((prev, prev_len), (last, last_len), kept, None, None);
+                }
+                None => {
+                    self.client
+                        .replace_range((0, 0), (last, last_len), kept, None, None);
+                }
+            }
+        }
+        Some(text)
+    }
+
+    /// Return the text of the lines from `first` to `last` without
+    /// their line endings, or `None` if some of them are not in cache.
+    fn line_texts(&self, first: u64, last: u64) -> Option<Vec<&str>> {
+        if first < self.cache.before() {
+            return None;
+        }
+        let start = (first - self.cache.before()) as usize;
+        let end = (last - self.cache.before()) as usize;
+        self.cache.lines().get(start..=end).map(|lines| {
+            lines
+                .iter()
+                .map(|l| l.text.trim_end_matches(&['\n', '\r'][..]))
+                .collect()
+        })
+    }
+
+    /// Replace the lines from `first` to `last` with `new_lines`. The
+    /// lines are joined with the line ending of the first one.
+    fn replace_lines(&mut self, first: u64, last: u64, new_lines: &[String]) {
+        self.replace_lines_then(first, last, new_lines, None, None)
+    }
+
+    /// Like `replace_lines`, then move the cursor to `cursor` and save
+    /// the buffer to `file`, if given.
+    fn replace_lines_then(
+        &mut self,
+        first: u64,
+        last: u64,
+        new_lines: &[String],
+        cursor: Option<(u64, u64)>,
+        file: Option<String>,
+    ) {
+        let last_len = match self.line_texts(last, last) {
+            Some(texts) => texts[0].len() as u64,
+            None => {
+                error!("line {} is not in cache", last);
+                return;
+            }
+        };
+        let first_line = first
+            .checked_sub(self.cache.before())
+            .and_then(|idx| self.cache.lines().get(idx as usize));
+        let ending = match first_line {
+            Some(line) if line.text.ends_with("\r\n") => "\r\n",
+            _ => "\n",
+        };
+        let text = new_lines.join(ending);
+        self.record_edit();
+        self.client
+            .replace_range((first, 0), (last, last_len), text, cursor, file);
+    }
+
+    /// Describe the line endings of the lines in cache: "lf", "crlf",
+    /// or "mixed" if both appear. Return `None` if no line has an
+    /// ending.
+    pub fn line_ending(&self) -> Option<&'static str> {
+        let (mut lf, mut crlf) = (false, false);
+        for line in self.cache.lines() {
+            if line.text.ends_with("\r\n") {
+                crlf = true;
+            } else if line.text.ends_with('\n') {
+                lf = true;
+            }
+        }
+        match (lf, crlf) {
+            (true, true) => Some("mixed"),
+            (true, false) => Some("lf"),
+            (false, true) => Some("crlf"),
+            (false, false) => None,
+        }
+    }
+
+    /// End all the lines with `ending`, in a single edit, and make the
+    /// core end the new lines with it. Only the lines the core sent us
+    /// can be converted.
+    pub fn set_line_ending(&mut self, ending: LineEnding) {
+        self.client.set_line_ending(ending.as_str());
+        let nb_cached = self.cache.lines().len() as u64;
+        if nb_cached == 0 {
+            return;
+        }
+        if self.cache.before() > 0 || self.cache.after() > 0 {
+            warn!("only converting the lines that are in cache");
+        }
+        let lines = self.cache.lines();
+        let mut text = String::new();
+        let mut changed = false;
+        for line in lines {
+            match line.text.strip_suffix('\n') {
+                Some(content) => {
+                    let content = content.strip_suffix('\r').unwrap_or(content);
+                    changed |= line.text.len() - content.len() != ending.as_str().len();
+                    text.push_str(content);
+                    text.push_str(ending.as_str());
+                }
+                None => text.push_str(&line.text),
+            }
+        }
+        if !changed {
+            return;
+        }
+        // Replace up to the start of the line after the last one, so
+        // that the ending of the last line is converted too.
+        let first = self.cache.before();
+        let last = first + nb_cached - 1;
+        let end = match lines.last() {
+            Some(line) if line.text.ends_with('\n') => (last + 1, 0),
+            Some(line) => (last, line.text.len() as u64),
+            None => return,
+        };
+        let cursor = Some((self.cursor.line, self.cursor.column));
+        self.record_edit();
+        self.client
+            .replace_range((first, 0), end, text, cursor, None);
+    }
+
+    /// Remove the trailing spaces and tabs of every line. The cursor
+    /// stays on its line, and its column is clamped to the new length
+    /// of the line. Only the lines the core sent us can be trimmed.
+    pub fn trim_trailing_whitespace(&mut self) {
+        if !self.clean_then_save(true, false, None) {
+            info!("no trailing whitespace to trim");
+        }
+    }
+
+    /// Save the buffer, after trimming the trailing whitespace if
+    /// `trim` is set, and making the buffer end with exactly one
+    /// newline if `final_newline` is set. The clean up is a single
+    /// edit, undone at once.
+    pub fn clean_and_save(&mut self, trim: bool, final_newline: bool) {
+        let file = self.file.clone().unwrap();
+        if !self.clean_then_save(trim, final_newline, Some(file.clone())) {
+            self.client.save(&file);
+        }
+    }
+
+    /// Like `clean_and_save`, but save to `file` only if given. Return
+    /// `false` if there was nothing to clean up, in which case nothing
+    /// is saved.
+    fn clean_then_save(&mut self, trim: bool, final_newline: bool, file: Option<String>) -> bool {
+        let nb_cached = self.cache.lines().len() as u64;
+        if nb_cached == 0 {
+            return false;
+        }
+        if trim && (self.cache.before() > 0 || self.cache.after() > 0) {
+            warn!("only trimming the lines that are in cache");
+        }
+        let first = self.cache.before();
+        let last = first + nb_cached - 1;
+        let texts: Vec<String> = match self.line_texts(first, last) {
+            Some(texts) => texts.iter().map(|text| (*text).to_owned()).collect(),
+            None => return false,
+        };
+        let mut cleaned: Vec<String> = if trim {
+            texts
+                .iter()
+                .map(|text| lines::trim_trailing(text).to_owned())
+                .collect()
+        } else {
+            texts.clone()
+        };
+        // The last line is the one after the last newline, so a buffer
+        // ending with exactly one newline ends with a single empty line.
+        if final_newline {
+            if self.cache.after() > 0 {
+                warn!("the end of the buffer is not in cache, leaving its final newline");
+            } else if let Some(idx) = cleaned.iter().rposition(|text| !text.is_empty()) {
+                cleaned.truncate(idx + 1);
+                cleaned.push(String::new());
+            }
+        }
+
+        let changed = |idx: &usize| cleaned.get(*idx) != texts.get(*idx);
+        let (start, end) = if cleaned.len() == texts.len() {
+            match (0..texts.len()).find(changed) {
+                Some(start) => (start, (0..texts.len()).rev().find(changed).unwrap()),
+                None => return false,
+            }
+        } else {
+            // Lines were added or removed at the end: replace
+            // everything from the first change to the last line.
+            let start = (0..texts.len()).find(changed).unwrap_or(texts.len());
+            let start = start.min(texts.len() - 1).min(cleaned.len() - 1);
+            (start, texts.len() - 1)
+        };
+        let new_end = end + cleaned.len() - texts.len();
+
+        let (line, column) = match self.cursor.line.checked_sub(first) {
+            Some(idx) if (idx as usize) < cleaned.len() => {
+                let len = cleaned[idx as usize].len() as u64;
+                (self.cursor.line, self.cursor.column.min(len))
+            }
+            // The cursor was on a trailing empty line that was removed
+            Some(idx) if idx < nb_cached => (first + cleaned.len() as u64 - 1, 0),
+            _ => (self.cursor.line, self.cursor.column),
+        };
+        self.replace_lines_then(
+            first + start as u64,
+            first + end as u64,
+            &cleaned[start..=new_end],
+            Some((line, column)),
+            file,
+        );
+        true
+    }
+
+    /// Join the selected lines, or the current line and the next one
+    /// if the selection doesn't span several lines.
+    pub fn join_lines(&mut self) {
+        let (first, mut last) = self.selected_lines();
+        if first == last {
+            last += 1;
+        }
+        if last >= self.cache.height() {
+            info!("no line to join with");
+            return;
+        }
+        let joined = match self.line_texts(first, last) {
+            Some(lines) => lines::join(&lines),
+            None => {
+                error!("lines {} to {} are not in cache", first, last);
+                return;
+            }
+        };
+        self.replace_lines(first, last, &[joined]);
+    }
+
+    /// Sort the selected lines, or all the lines if nothing is
+    /// selected.
+    pub fn sort_lines(&mut self, config: &SortConfig) {
+        let (first, last) = if self.selection_spans().is_empty() {
+            (0, self.cache.height().saturating_sub(1))
+        } else {
+            self.selected_lines()
+        };
+        let sorted = match self.line_texts(first, last) {
+            Some(lines) => lines::sort(&lines, config),
+            None => {
+                error!("lines {} to {} are not in cache", first, last);
+                return;
+            }
+        };
+        self.replace_lines(first, last, &sorted);
+    }
+
+    /// Comment the selected lines with `token`, or uncomment them if
+    /// they're all commented already.
+    pub fn toggle_comment(&mut self, token: &str) {
+        let (first, last) = self.selected_lines();
+        let new_lines = match self.line_texts(first, last) {
+            Some(lines) => comment::toggle(&lines, token),
+            None => {
+                error!("lines {} to {} are not in cache", first, last);
+                return;
+            }
+        };
+        self.replace_lines(first, last, &new_lines);
+    }
+
+    /// Return the file location around the cursor, as typed in the
+    /// buffer, such as `src/main.rs:12`.
+    pub fn location_under_cursor(&self) -> Option<String> {
+        let line = self.line_texts(self.cursor.line, self.cursor.line)?[0];
+        location_at(line, self.cursor.column as usize).map(str::to_owned)
+    }
+
+    /// Write the indentation of the selected lines with tabs or with
+    /// spaces, as Tab inserts them, keeping its width. Return `false`
+    /// if it is written so already.
+    pub fn reindent(&mut self) -> bool {
+        let (first, last) = self.selected_lines();
+        let (lines, new_lines): (Vec<&str>, Vec<String>) = match self.line_texts(first, last) {
+            Some(lines) => {
+                let new_lines = lines
+                    .iter()
+                    .map(|line| indent::normalize(line, self.cfg.tab_size, self.cfg.expand_tabs))
+                    .collect();
+                (lines, new_lines)
+            }
+            None => {
+                error!("lines {} to {} are not in cache", first, last);
+                return false;
+            }
+        };
+        if lines.iter().zip(&new_lines).all(|(old, new)| old == new) {
+            return false;
+        }
+        // Keep the cursor on the same character, or at the end of the
+        // indentation if it was in it
+        let cursor = if (first..=last).contains(&self.cursor.line) {
+            let idx = (self.cursor.line - first) as usize;
+            let old_indent =
+                lines[idx].len() - lines[idx].trim_start_matches(&[' ', '\t'][..]).len();
+            let new_indent =
+                new_lines[idx].len() - new_lines[idx].trim_start_matches(&[' ', '\t'][..]).len();
+            let column = (self.cursor.column as usize).max(old_indent) - old_indent + new_indent;
+            Some((self.cursor.line, column as u64))
+        } else {
+            None
+        };
+        self.replace_lines_then(first, last, &new_lines, cursor, None);
+        true
+    }
 
-#[derive(Debug, Default)]
-pub struct Cursor {
-    pub line: u64,
-    pub column: u64,
-}
+    pub fn toggle_case(&mut self) {
+        self.change_case(Case::Toggle)
+    }
 
-pub struct View {
-    cache: LineCache,
-    cursor: Cursor,
-    window: Window,
-    file: Option<String>,
-    client: Client,
-    cfg: ViewConfig,
-}
+    pub fn upper_case(&mut self) {
+        self.change_case(Case::Upper)
+    }
 
-impl View {
-    pub fn new(client: Client, file: Option<String>) -> View {
-        View {
-            cache: LineCache::default(),
-            cursor: Default::default(),
-            window: Window::new(),
-            cfg: ViewConfig::default(),
-            client,
-            file,
+    pub fn lower_case(&mut self) {
+        self.change_case(Case::Lower)
+    }
+
+    /// Change the case of the selected text in a single edit. Without
+    /// selection, change the character under the cursor and move the
+    /// cursor past it, so that repeating the command walks through the
+    /// text.
+    fn change_case(&mut self, case: Case) {
+        let spans = self.selection_spans();
+        if spans.is_empty() {
+            let (line, column) = (self.cursor.line, self.cursor.column as usize);
+            let text = match self.line_texts(line, line) {
+                Some(texts) => texts[0],
+                None => {
+                    error!("line {} is not in cache", line);
+                    return;
+                }
+            };
+            let c = match text.get(column..).and_then(|rest| rest.chars().next()) {
+                Some(c) => c,
+                None => {
+                    info!("no character under the cursor");
+                    return;
+                }
+            };
+            let changed = case::change(c.encode_utf8(&mut [0; 4]), case);
+            let next = (line, (column + changed.len()) as u64);
+            let new_line = format!(
+                "{}{}{}",
+                &text[..column],
+                changed,
+                &text[column + c.len_utf8()..]
+            );
+            self.replace_lines_then(line, line, &[new_line], Some(next), None);
+            return;
         }
+
+        let (first, last) = (spans[0].line, spans[spans.len() - 1].line);
+        let new_lines: Vec<String> = match self.line_texts(first, last) {
+            Some(texts) => texts
+                .iter()
+                .zip(first..)
+                .map(|(text, line)| {
+                    let mut new_line = String::new();
+                    let mut copied = 0;
+                    for span in spans.iter().filter(|span| span.line == line) {
+                        let (start, end) =
+                            (span.start as usize, (span.end as usize).min(text.len()));
+                        if let Some(selected) = text.get(start..end) {
+                            new_line.push_str(&text[copied..start]);
+                            new_line.push_str(&case::change(selected, case));
+                            copied = end;
+                        }
+                    }
+                    new_line.push_str(&text[copied..]);
+                    new_line
+                })
+                .collect(),
+            None => {
+                error!("lines {} to {} are not in cache", first, last);
+                return;
+            }
+        };
+        let cursor = (self.cursor.line, self.cursor.column);
+        self.replace_lines_then(first, last, &new_lines, Some(cursor), None);
     }
 
-    pub fn update_cache(&mut self, update: Update) {
-        info!("updating cache");
-        self.cache.update(update)
+    /// Insert `open` before and `close` after each selection, in a
+    /// single edit. Without selection, insert both at each cursor and
+    /// put the cursor between them.
+    pub fn surround(&mut self, open: &str, close: &str) {
+        let regions = selection::regions(&self.selection_spans());
+        if regions.is_empty() {
+            self.record_edit();
+            let pair = format!("{}{}", open, close);
+            self.client.insert_then_left(&pair, close.chars().count());
+            return;
+        }
+        let (first, last) = ((regions[0].0).0, (regions[regions.len() - 1].1).0);
+        let cursor = (self.cursor.line, self.cursor.column);
+        let (new_lines, shift) = match self.line_texts(first, last) {
+            Some(texts) => surround::wrap(&texts, first, &regions, open, close, cursor),
+            None => {
+                error!("lines {} to {} are not in cache", first, last);
+                return;
+            }
+        };
+        let cursor = (cursor.0, cursor.1 + shift);
+        self.replace_lines_then(first, last, &new_lines, Some(cursor), None);
     }
 
-    pub fn set_cursor(&mut self, line: u64, column: u64) {
-        self.cursor = Cursor { line, column };
-        self.window.set_cursor(&self.cursor);
+    /// Add `delta` to the number under or after each cursor, on its
+    /// line, in a single edit. Only the first cursor of a line counts.
+    /// The cursor is left on the last character of the number of its
+    /// line. Return `false` if no cursor has a number on its line.
+    pub fn increment_number(&mut self, delta: i64) -> bool {
+        let mut positions = self.cursor_positions();
+        positions.sort();
+        positions.dedup_by_key(|pos| pos.0);
+        let (first, last) = (positions[0].0, positions[positions.len() - 1].0);
+        let mut texts: Vec<String> = match self.line_texts(first, last) {
+            Some(texts) => texts.iter().map(|text| (*text).to_owned()).collect(),
+            None => {
+                error!("lines {} to {} are not in cache", first, last);
+                return false;
+            }
+        };
+        let mut cursor = None;
+        for &(line, column) in &positions {
+            let idx = (line - first) as usize;
+            if let Some((text, end)) = number::increment(&texts[idx], column as usize, delta) {
+                texts[idx] = text;
+                if cursor.is_none() || line == self.cursor.line {
+                    cursor = Some((line, end as u64));
+                }
+            }
+        }
+        if cursor.is_none() {
+            return false;
+        }
+        self.replace_lines_then(first, last, &texts, cursor, None);
+        true
     }
 
-    pub fn config_changed(&mut self, changes: ConfigChanges) {
-        if let Some(tab_size) = changes.tab_size {
-            self.cfg.tab_size = tab_size as u16;
+    /// Move the cursor to the other end of the selection it is on, so
+    /// that the selection is extended from the other side.
+    pub fn swap_selection_ends(&mut self) {
+        let regions = selection::regions(&self.selection_spans());
+        let cursor = (self.cursor.line, self.cursor.column);
+        match selection::selection_at(&regions, cursor) {
+            Some(selection) => {
+                let selection = selection.swapped();
+                self.client.select(selection.anchor, selection.cursor);
+            }
+            None => info!("the cursor is not at the end of a selection"),
         }
     }
 
-    pub fn render<W: Write>(
-        &mut self,
-        w: &mut W,
-        styles: &HashMap<u64, Style>,
-    ) -> Result<(), Error> {
-        self.update_window();
-        self.render_lines(w, styles)?;
-        self.render_cursor(w);
-        Ok(())
+    /// Move the cursor to the bracket matching the one under it. If
+    /// `extend` is true, the text between the two brackets is
+    /// selected.
+    pub fn goto_matching_bracket(&mut self, extend: bool) {
+        if self.cursor.line < self.cache.before() {
+            error!(
+                "cursor is on line {} which is not in cache",
+                self.cursor.line
+            );
+            return;
+        }
+        let lines: Vec<&str> = self.cache.lines().iter().map(|l| l.text.as_str()).collect();
+        let cursor = (self.cursor.line - self.cache.before(), self.cursor.column);
+        match brackets::find_matching(&lines, cursor) {
+            Some((line, column)) => {
+                let target = (line + self.cache.before(), column);
+                self.record_jump();
+                if extend {
+                    self.client
+                        .select((self.cursor.line, self.cursor.column), target);
+                } else {
+                    self.client.click(target.0, target.1);
+                }
+            }
+            None => info!("no matching bracket found"),
+        }
     }
 
-    pub fn resize(&mut self, height: u16) {
-        self.window.resize(height);
-        self.update_window();
-        let top = self.cache.before() + self.window.start();
-        let bottom = self.cache.after() + self.window.end();
-        self.client.scroll(top, bottom);
+    /// Move to the next (or previous) line indented like the cursor
+    /// line, on its first non-blank character, selecting the text in
+    /// between if `extend` is true. Blank lines are skipped.
+    pub fn move_to_matching_indent(&mut self, forward: bool, extend: bool) {
+        if self.cursor.line < self.cache.before() {
+            error!(
+                "cursor is on line {} which is not in cache",
+                self.cursor.line
+            );
+            return;
+        }
+        let lines: Vec<&str> = self.cache.lines().iter().map(|l| l.text.as_str()).collect();
+        let cursor_line = (self.cursor.line - self.cache.before()) as usize;
+        let found = indent::find_same_indent(&lines, cursor_line, forward, self.cfg.tab_size);
+        match found {
+            Some(line) => {
+                let column = lines[line].len() - lines[line].trim_start().len();
+                let target = (line as u64 + self.cache.before(), column as u64);
+                self.record_jump();
+                if extend {
+                    self.client
+                        .select((self.cursor.line, self.cursor.column), target);
+                } else {
+                    self.client.click(target.0, target.1);
+                }
+            }
+            None => info!("no line with the same indentation found"),
+        }
     }
 
-    pub fn insert(&mut self, c: char) {
-        self.client.insert(c)
+    /// Record the cursor position in the jump list, before a big move.
+    fn record_jump(&mut self) {
+        self.jumps.push((self.cursor.line, self.cursor.column));
     }
 
-    pub fn insert_newline(&mut self) {
-        self.client.insert_newline()
+    fn record_edit(&mut self) {
+        self.last_edit = Some((self.cursor.line, self.cursor.column));
     }
 
-    pub fn insert_tab(&mut self) {
-        self.client.insert_tab()
+    /// Move the cursor back to where the buffer was last edited from
+    /// this view. Return `false` if it was not edited yet.
+    pub fn goto_last_edit(&mut self) -> bool {
+        match self.last_edit {
+            Some((line, column)) => {
+                self.goto_position(line, column);
+                true
+            }
+            None => false,
+        }
     }
 
-    pub fn save(&mut self) {
-        self.client.save(self.file.as_ref().unwrap())
+    pub fn jump_back(&mut self) {
+        match self.jumps.back((self.cursor.line, self.cursor.column)) {
+            Some((line, column)) => self.client.click(line, column),
+            None => info!("already at the oldest position of the jump list"),
+        }
     }
 
-    pub fn back(&mut self) {
-        self.client.backspace()
+    pub fn jump_forward(&mut self) {
+        match self.jumps.forward() {
+            Some((line, column)) => self.client.click(line, column),
+            None => info!("already at the newest position of the jump list"),
+        }
     }
 
-    pub fn delete(&mut self) {
-        self.client.delete()
+    /// Whether the file backing this view looks like a markdown
+    /// document, judging by its extension.
+    fn is_markdown(&self) -> bool {
+        match self.file {
+            Some(ref file) => {
+                let file = file.to_lowercase();
+                file.ends_with(".md") || file.ends_with(".markdown")
+            }
+            None => false,
+        }
     }
 
-    pub fn page_down(&mut self) {
-        self.client.page_down()
+    pub fn markdown_heading_move(&mut self, forward: bool, level: Option<u8>) {
+        if !self.is_markdown() {
+            warn!("not a markdown file, cannot move between headings");
+            return;
+        }
+        if self.cursor.line < self.cache.before() {
+            error!(
+                "cursor is on line {} which is not in cache",
+                self.cursor.line
+            );
+            return;
+        }
+        let headings = markdown::headings(self.cache.lines().iter().map(|l| l.text.as_str()));
+        let cursor_line = (self.cursor.line - self.cache.before()) as usize;
+        match markdown::find_heading(&headings, cursor_line, forward, level) {
+            Some(heading) => {
+                self.record_jump();
+                self.client
+                    .goto_line(heading.line as u64 + self.cache.before())
+            }
+            None => info!("no heading found"),
+        }
     }
 
-    pub fn page_up(&mut self) {
-        self.client.page_up()
+    /// Fold the section under each markdown heading into the heading
+    /// line.
+    pub fn fold_all_sections(&mut self) {
+        if !self.is_markdown() {
+            warn!("not a markdown file, cannot fold sections");
+            return;
+        }
+        let lines = self.cache.lines();
+        let headings = markdown::headings(lines.iter().map(|l| l.text.as_str()));
+        for (start, end) in markdown::sections(&headings, lines.len()) {
+            let fold = Fold::new(
+                start as u64 + self.cache.before(),
+                end as u64 + self.cache.before(),
+            );
+            if !self.folds.contains(&fold) {
+                self.folds.push(fold);
+            }
+        }
     }
 
-    pub fn move_left(&mut self) {
-        self.client.left()
+    /// Fold the lines under the cursor line that are more indented
+    /// than it, or unfold them if the cursor line is folded. Return
+    /// `false` if there is nothing to fold.
+    pub fn toggle_fold(&mut self) -> bool {
+        let line = self.cursor.line;
+        let nb_folds = self.folds.len();
+        self.folds
+            .retain(|fold| fold.start != line && !fold.hides(line));
+        if self.folds.len() != nb_folds {
+            return true;
+        }
+        if line < self.cache.before() {
+            error!("cursor is on line {} which is not in cache", line);
+            return false;
+        }
+        let lines: Vec<&str> = self.cache.lines().iter().map(|l| l.text.as_str()).collect();
+        let idx = (line - self.cache.before()) as usize;
+        match indent::block_end(&lines, idx, self.cfg.tab_size) {
+            Some(end) => {
+                let end = end as u64 + self.cache.before();
+                self.folds.push(Fold::new(line, end));
+                true
+            }
+            None => false,
+        }
     }
 
-    pub fn move_right(&mut self) {
-        self.client.right()
+    /// Fold every block of lines that are more indented than the line
+    /// above them. Nested blocks are folded too, so that they stay
+    /// folded once the block around them is unfolded.
+    pub fn fold_all(&mut self) {
+        let before = self.cache.before();
+        let lines: Vec<&str> = self.cache.lines().iter().map(|l| l.text.as_str()).collect();
+        for idx in 0..lines.len() {
+            if let Some(end) = indent::block_end(&lines, idx, self.cfg.tab_size) {
+                let fold = Fold::new(idx as u64 + before, end as u64 + before);
+                if !self.folds.contains(&fold) {
+                    self.folds.push(fold);
+                }
+            }
+        }
     }
 
-    pub fn move_up(&mut self) {
-        self.client.up()
+    pub fn unfold_all(&mut self) {
+        self.folds.clear();
     }
 
-    pub fn move_down(&mut self) {
-        self.client.down()
+    /// Whether the given line is hidden by a fold.
+    fn is_folded(&self, line: u64) -> bool {
+        self.folds.iter().any(|fold| fold.hides(line))
     }
 
-    pub fn toggle_line_numbers(&mut self) {
-        self.cfg.display_gutter = !self.cfg.display_gutter;
+    /// Return the number of lines folded into the given line.
+    fn folded_lines(&self, line: u64) -> u64 {
+        if self.is_folded(line) {
+            return 0;
+        }
+        self.folds
+            .iter()
+            .filter(|fold| fold.start == line)
+            .map(Fold::hidden_lines)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Return the cache indices of the lines that are not hidden by a
+    /// fold.
+    fn visible_lines(&self) -> Vec<usize> {
+        let before = self.cache.before();
+        (0..self.cache.lines().len())
+            .filter(|idx| !self.is_folded(*idx as u64 + before))
+            .collect()
+    }
+
+    /// Return the position of the given cache index among the visible
+    /// lines. A folded line is displayed at the position of the fold.
+    fn visible_position(visible: &[usize], idx: usize) -> usize {
+        match visible.binary_search(&idx) {
+            Ok(pos) => pos,
+            Err(pos) => pos.saturating_sub(1),
+        }
     }
 
     fn update_window(&mut self) {
@@ -136,50 +1836,194 @@ impl View {
             );
             return;
         }
-        let cursor_line = self.cursor.line - self.cache.before();
-        let nb_lines = self.cache.lines().len() as u64;
-        let gutter_size = (self.cache.before() + nb_lines + self.cache.after())
-            .to_string()
-            .len() as u16;
+        let visible = self.visible_lines();
+        let cursor_idx = (self.cursor.line - self.cache.before()) as usize;
+        let cursor_line = Self::visible_position(&visible, cursor_idx) as u64;
+        let nb_lines = visible.len() as u64;
+        let gutter_size = self.cache.height().to_string().len() as u16;
         let gutter_size = gutter_size + 1; // Space between line number and content
         self.cfg.gutter_size = max(gutter_size, 4); //  min gutter width 4
+        if self.detached {
+            // Leave the window where the mouse wheel put it
+            return;
+        }
         self.window.update(cursor_line, nb_lines);
+
+        // Wrapped lines take several rows, so the window may have to
+        // start further down for the cursor line to fit.
+        if self.cfg.wrap {
+            let size = u64::from(self.window.size());
+            let rows = |pos: u64| u64::from(self.line_rows(visible[pos as usize]));
+            let mut start = self.window.start().min(cursor_line);
+            let mut used: u64 = (start..cursor_line).map(rows).sum();
+            while start < cursor_line && used + rows(cursor_line).min(size) > size {
+                used -= rows(start);
+                start += 1;
+            }
+            self.window.set_start(start);
+        }
+        self.update_h_scroll();
+    }
+
+    /// Scroll horizontally so that the cursor column is visible, with a
+    /// margin on both sides.
+    fn update_h_scroll(&mut self) {
+        if self.cfg.wrap {
+            self.h_scroll = 0;
+            return;
+        }
+        let column = self.cursor_column();
+        let width = self.text_width();
+        let margin = HORIZONTAL_SCROLL_OFF.min(width.saturating_sub(1) / 2);
+        if column < self.h_scroll + margin {
+            self.h_scroll = column.saturating_sub(margin);
+        } else if column + margin >= self.h_scroll + width {
+            self.h_scroll = column + margin + 1 - width;
+        }
+    }
+
+    /// Return the column of the cursor in its line once rendered. The
+    /// trick is that we know the position within the string, but
+    /// characters may have various lengths. For the moment, we only
+    /// handle control characters and tabs. We assume control characters
+    /// (0x00-0x1f, excluding 0x09 == tab) are rendered in caret notation
+    /// and are thus two columns wide. Tabs are variable-width, rounding
+    /// up to the next tab stop. All other characters are assumed to be
+    /// one column wide.
+    fn cursor_column(&self) -> u16 {
+        if self.is_folded(self.cursor.line) {
+            return 0;
+        }
+        let line = self
+            .cursor
+            .line
+            .checked_sub(self.cache.before())
+            .and_then(|idx| self.cache.lines().get(idx as usize));
+        match line {
+            Some(line) => line
+                .text
+                .chars()
+                .take(self.cursor.column as usize)
+                .fold(0, |acc, c| acc + self.translate_char_width(acc, c)),
+            None => 0,
+        }
+    }
+
+    /// Width of the text area, right of the gutter.
+    fn text_width(&self) -> u16 {
+        max(self.cfg.width.saturating_sub(self.cfg.gutter_size), 1)
+    }
+
+    /// Return the number of columns `text` takes once rendered.
+    fn display_width(&self, text: &str) -> u16 {
+        text.chars()
+            .fold(0, |acc, c| acc + self.translate_char_width(acc, c))
+    }
+
+    /// Return the number of terminal rows taken by the line at the
+    /// given cache index. This is always 1 if wrapping is disabled.
+    fn line_rows(&self, idx: usize) -> u16 {
+        if !self.cfg.wrap {
+            return 1;
+        }
+        match self.cache.lines().get(idx) {
+            Some(line) => {
+                let width = self.display_width(line.text.trim_end_matches(&['\n', '\r'][..]));
+                let text_width = self.text_width();
+                max(width.div_ceil(text_width), 1)
+            }
+            None => 1,
+        }
     }
 
     fn get_click_location(&self, x: u64, y: u64) -> (u64, u64) {
-        let lineno = x + self.cache.before() + self.window.start();
-        if let Some(line) = self.cache.lines().get(x as usize) {
-            if y < u64::from(self.cfg.gutter_size) {
+        let visible = self.visible_lines();
+        // Find the line displayed on row `x`, and which of its rows
+        // that is if it's wrapped.
+        let mut row: u64 = 0;
+        let mut line = None;
+        for idx in visible.iter().skip(self.window.start() as usize) {
+            let rows = u64::from(self.line_rows(*idx));
+            if x < row + rows {
+                line = self
+                    .cache
+                    .lines()
+                    .get(*idx)
+                    .map(|line| (*idx, line, x - row));
+                break;
+            }
+            row += rows;
+        }
+        if let Some((idx, line, sub_row)) = line {
+            let lineno = idx as u64 + self.cache.before();
+            let gutter = u64::from(self.cfg.gutter_size);
+            if y < gutter {
                 return (lineno, 0);
             }
-            let mut text_len: u16 = 0;
-            for (idx, c) in line.text.chars().enumerate() {
-                let char_width = self.translate_char_width(text_len, c);
-                text_len += char_width;
-                if u64::from(text_len) >= y {
-                    // If the character at idx is wider than one column,
-                    // the click occurred within the character. Otherwise,
-                    // the click occurred on the character at idx + 1
-                    if char_width > 1 {
-                        return (lineno as u64, (idx - self.cfg.gutter_size as usize) as u64);
-                    } else {
-                        return (
-                            lineno as u64,
-                            (idx - self.cfg.gutter_size as usize) as u64 + 1,
-                        );
-                    }
+            // The column clicked in the rendered line, where tabs and
+            // control characters take several columns.
+            let target =
+                y - gutter + sub_row * u64::from(self.text_width()) + u64::from(self.h_scroll);
+            let text = line.text.trim_end_matches(&['\n', '\r'][..]);
+            let mut column: u16 = 0;
+            for (offset, c) in text.char_indices() {
+                column += self.translate_char_width(column, c);
+                if u64::from(column) > target {
+                    return (lineno, offset as u64);
                 }
             }
-            return (lineno, line.text.len() as u64 + 1);
+            return (lineno, text.len() as u64);
         } else {
             warn!("no line at index {} found in cache", x);
             return (x, y);
         }
     }
 
+    /// Convert the row and column of a mouse event, starting at 1, to
+    /// a row and column in the region of the view, starting at 0.
+    fn to_region(&self, row: u16, column: u16) -> (u64, u64) {
+        (
+            u64::from(row.saturating_sub(self.origin.1 + 1)),
+            u64::from(column.saturating_sub(self.origin.0 + 1)),
+        )
+    }
+
+    /// Move the cursor where the terminal was clicked. Clicking twice
+    /// in a row at the same place selects a word, and three times a
+    /// line.
     fn click(&mut self, x: u64, y: u64) {
-        let (line, column) = self.get_click_location(x, y);
-        self.client.click(line, column);
+        let position = self.get_click_location(x, y);
+        let now = Instant::now();
+        let count = match self.last_click {
+            Some((time, last, count))
+                if last == position && now.duration_since(time) < MULTI_CLICK_INTERVAL =>
+            {
+                count % 3 + 1
+            }
+            _ => 1,
+        };
+        self.last_click = Some((now, position, count));
+        match count {
+            1 => self.client.click(position.0, position.1),
+            _ => self.client.multi_click(position.0, position.1, count),
+        }
+    }
+
+    /// Scroll the view by `lines`, up if negative, without moving the
+    /// cursor.
+    fn scroll_by(&mut self, lines: i64) {
+        let nb_lines = self.visible_lines().len() as u64;
+        let last_start = nb_lines.saturating_sub(u64::from(self.window.size()));
+        let start = if lines < 0 {
+            self.window.start().saturating_sub(lines.unsigned_abs())
+        } else {
+            (self.window.start() + lines as u64).min(last_start)
+        };
+        self.window.set_start(start);
+        self.detached = true;
+        let top = self.cache.before() + self.window.start();
+        let bottom = self.cache.after() + self.window.end();
+        self.client.scroll(top, bottom);
     }
 
     fn drag(&mut self, x: u64, y: u64) {
@@ -187,7 +2031,24 @@ impl View {
         self.client.drag(line, column);
     }
 
+    /// Whether `handle_input` would modify the buffer (or save it)
+    /// in response to this event.
+    pub fn is_edit(event: &Event) -> bool {
+        match *event {
+            Event::Key(Key::Char(_)) | Event::Key(Key::Backspace) | Event::Key(Key::Delete) => true,
+            Event::Key(Key::Ctrl(c)) => {
+                c == 'w' || c == 'h' || c == 'd' || c == 'k' || c == 'u' || c == 't'
+            }
+            Event::Key(Key::Alt('d')) | Event::Key(Key::Alt('\x7f')) => true,
+            _ => false,
+        }
+    }
+
     pub fn handle_input(&mut self, event: Event) {
+        if let Event::Key(_) = event {
+            // Typing brings the cursor back into view
+            self.detached = false;
+        }
         match event {
             Event::Key(key) => match key {
                 Key::Char(c) => match c {
@@ -198,8 +2059,15 @@ impl View {
                 Key::Ctrl(c) => match c {
                     'w' => self.save(),
                     'h' => self.back(),
+                    'd' => self.duplicate_line(),
+                    'k' => self.delete_to_eol(),
+                    'u' => self.delete_to_bol(),
+                    't' => self.transpose(),
                     _ => error!("un-handled input ctrl+{}", c),
                 },
+                Key::Alt('d') => self.delete_word(true),
+                // Alt-Backspace
+                Key::Alt('\x7f') => self.delete_word(false),
                 Key::Backspace => self.back(),
                 Key::Delete => self.delete(),
                 Key::Left => self.client.left(),
@@ -214,13 +2082,19 @@ impl View {
             },
             Event::Mouse(mouse_event) => match mouse_event {
                 MouseEvent::Press(press_event, y, x) => match press_event {
-                    MouseButton::Left => self.click(u64::from(x) - 1, u64::from(y) - 1),
-                    MouseButton::WheelUp => self.client.up(),
-                    MouseButton::WheelDown => self.client.down(),
+                    MouseButton::Left => {
+                        let (x, y) = self.to_region(x, y);
+                        self.click(x, y)
+                    }
+                    MouseButton::WheelUp => self.scroll_by(-(WHEEL_SCROLL_LINES as i64)),
+                    MouseButton::WheelDown => self.scroll_by(WHEEL_SCROLL_LINES as i64),
                     button => error!("un-handled button {:?}", button),
                 },
                 MouseEvent::Release(..) => {}
-                MouseEvent::Hold(y, x) => self.drag(u64::from(x) - 1, u64::from(y) - 1),
+                MouseEvent::Hold(y, x) => {
+                    let (x, y) = self.to_region(x, y);
+                    self.drag(x, y)
+                }
             },
             ev => error!("un-handled event {:?}", ev),
         }
@@ -230,34 +2104,53 @@ impl View {
         debug!("rendering lines");
         trace!("current cache\n{:?}", self.cache);
 
-        // Get the lines that are within the displayed window
-        let lines = self
-            .cache
-            .lines()
+        // Get the lines that are within the displayed window, skipping
+        // the folded ones
+        let visible = self.visible_lines();
+        let lines = visible
             .iter()
             .skip(self.window.start() as usize)
             .take(self.window.size() as usize);
 
-        // Draw the valid lines within this range
+        // Draw the valid lines within this range. Wrapped lines take
+        // several rows, and only the first one gets a line number.
+        let win_size = self.window.size();
         let mut line_strings = String::new();
-        let mut line_no = self.cache.before() + self.window.start();
-        for (line_index, line) in lines.enumerate() {
-            line_strings.push_str(&self.render_line_str(line, Some(line_no), line_index, styles));
-            line_no += 1;
+        let mut line_count: u16 = 0;
+        for idx in lines {
+            if line_count >= win_size {
+                break;
+            }
+            let line = &self.cache.lines()[*idx];
+            let line_no = *idx as u64 + self.cache.before();
+            let mut text = self.escape_control_and_add_styles(styles, line);
+            if !self.cfg.wrap {
+                text = wrap::slice(&text, self.h_scroll, self.text_width());
+            }
+            let rows = if self.cfg.wrap {
+                wrap::split(&text, self.text_width())
+            } else {
+                vec![text.as_str()]
+            };
+            for (row, row_text) in rows.iter().enumerate() {
+                if line_count >= win_size {
+                    break;
+                }
+                let lineno = if row == 0 { Some(line_no) } else { None };
+                line_strings.push_str(&self.render_line_str(row_text, lineno, line_count as usize));
+                line_count += 1;
+            }
+            let folded = self.folded_lines(line_no);
+            if folded > 0 {
+                line_strings.push_str(&format!(" [+{} lines]", folded));
+            }
         }
 
         // If the number of lines is less than window height
         // render empty lines to fill the view window.
-        let line_count = self.cache.lines().len() as u16;
-        let win_size = self.window.size();
         if win_size > line_count {
             for num in line_count..win_size {
-                line_strings.push_str(&self.render_line_str(
-                    &Line::default(),
-                    None,
-                    num as usize,
-                    styles,
-                ));
+                line_strings.push_str(&self.render_line_str("", None, num as usize));
             }
         }
         w.write_all(line_strings.as_bytes())?;
@@ -270,34 +2163,32 @@ impl View {
         self.cfg.tab_size - (position % self.cfg.tab_size)
     }
 
-    fn render_line_str(
-        &self,
-        line: &Line,
-        lineno: Option<u64>,
-        line_index: usize,
-        styles: &HashMap<u64, Style>,
-    ) -> String {
-        let text = self.escape_control_and_add_styles(styles, line);
+    fn render_line_str(&self, text: &str, lineno: Option<u64>, line_index: usize) -> String {
+        let (left, top) = self.origin;
+        let row = top + line_index as u16 + 1;
+        // Only clear from the left of the view, to keep what is drawn
+        // on its left.
+        let clear = format!("{}{}", Goto(left + 1, row), UntilNewline);
         if let Some(line_no) = lineno {
-            if self.cfg.display_gutter {
-                let line_no = (line_no + 1).to_string();
+            if self.cfg.line_numbers != LineNumberMode::Off {
+                let line_no = self.displayed_line_number(line_no).to_string();
                 let line_no_offset = self.cfg.gutter_size - line_no.len() as u16;
                 format!(
                     "{}{}{}{}{}",
-                    Goto(line_no_offset, line_index as u16 + 1),
-                    ClearLine,
+                    clear,
+                    Goto(left + line_no_offset, row),
                     line_no,
-                    Goto(self.cfg.gutter_size + 1, line_index as u16 + 1),
+                    Goto(left + self.cfg.gutter_size + 1, row),
                     &text
                 )
             } else {
-                format!("{}{}{}", Goto(0, line_index as u16 + 1), ClearLine, &text)
+                format!("{}{}", clear, &text)
             }
         } else {
             format!(
                 "{}{}{}",
-                Goto(self.cfg.gutter_size + 1, line_index as u16 + 1),
-                ClearLine,
+                clear,
+                Goto(left + self.cfg.gutter_size + 1, row),
                 &text
             )
         }
@@ -306,8 +2197,39 @@ impl View {
     fn escape_control_and_add_styles(&self, styles: &HashMap<u64, Style>, line: &Line) -> String {
         let mut position: u16 = 0;
         let mut text = String::with_capacity(line.text.capacity());
-        for c in line.text.chars() {
+        // The offset in `text` of each byte offset in `line.text`,
+        // since the styles are given for the text before expansion
+        let mut offsets = vec![0; line.text.len() + 1];
+        // Where the trailing whitespace starts and ends, if it is shown
+        let trailing = if self.cfg.show_whitespace {
+            let content = line.text.trim_end_matches(&['\n', '\r'][..]);
+            let start = content.trim_end_matches(&[' ', '\t'][..]).len();
+            Some((start, content.len())).filter(|(start, end)| start < end)
+        } else {
+            None
+        };
+        for (idx, c) in line.text.char_indices() {
+            for offset in &mut offsets[idx..idx + c.len_utf8()] {
+                *offset = text.len();
+            }
+            match trailing {
+                Some((start, _)) if idx == start => text.push_str(TRAILING_WHITESPACE_START),
+                Some((_, end)) if idx == end => text.push_str(TRAILING_WHITESPACE_END),
+                _ => {}
+            }
             match c {
+                ' ' if self.cfg.show_whitespace => {
+                    text.push('\u{b7}');
+                    position += 1;
+                }
+                '\t' if self.cfg.show_whitespace => {
+                    // The arrow takes the first column of the tab, so
+                    // that the columns after it don't move
+                    let tab_width = self.tab_width_at_position(position);
+                    text.push('\u{2192}');
+                    text.push_str(&" ".repeat(tab_width as usize - 1));
+                    position += tab_width;
+                }
                 '\x00'..='\x08' | '\x0a'..='\x1f' | '\x7f' => {
                     // Render in caret notation, i.e. '\x02' is rendered as '^B'
                     text.push('^');
@@ -325,10 +2247,16 @@ impl View {
                 }
             }
         }
+        if let Some((_, end)) = trailing {
+            if end == line.text.len() {
+                text.push_str(TRAILING_WHITESPACE_END);
+            }
+        }
+        offsets[line.text.len()] = text.len();
         if line.styles.is_empty() {
             return text;
         }
-        let mut style_sequences = self.get_style_sequences(styles, line);
+        let mut style_sequences = self.get_style_sequences(styles, line, &offsets);
         for style in style_sequences.drain(..) {
             trace!("inserting style: {:?}", style);
             if style.0 >= text.len() {
@@ -341,10 +2269,14 @@ impl View {
         text
     }
 
+    /// Return the escape sequences setting and resetting the styles of
+    /// `line`, at their offset in the rendered text. `offsets` maps
+    /// the offsets in the line to the rendered text.
     fn get_style_sequences(
         &self,
         styles: &HashMap<u64, Style>,
         line: &Line,
+        offsets: &[usize],
     ) -> Vec<(usize, String)> {
         let mut style_sequences: Vec<(usize, String)> = Vec::new();
         let mut prev_style_end: usize = 0;
@@ -357,6 +2289,12 @@ impl View {
             };
             let end_idx = start_idx + style_def.length as usize;
             prev_style_end = end_idx;
+            if style_def.length == 0 {
+                // Nothing to draw, like the selection of a cursor
+                continue;
+            }
+            let last = offsets.len() - 1;
+            let (start_idx, end_idx) = (offsets[start_idx.min(last)], offsets[end_idx.min(last)]);
 
             if let Some(style) = styles.get(&style_def.style_id) {
                 let start_sequence = match set_style(style) {
@@ -393,11 +2331,13 @@ impl View {
         style_sequences
     }
 
-    fn render_cursor<W: Write>(&self, w: &mut W) {
+    /// Move the terminal cursor to the cursor of the view.
+    pub fn render_cursor<W: Write>(&self, w: &mut W) {
         info!("rendering cursor");
+        let (left, top) = self.origin;
         if self.cache.is_empty() {
             info!("cache is empty, rendering cursor at the top left corner");
-            if let Err(e) = write!(w, "{}", Goto(1, 1)) {
+            if let Err(e) = write!(w, "{}", Goto(left + 1, top + 1)) {
                 error!("failed to render cursor: {}", e);
             }
             return;
@@ -412,15 +2352,18 @@ impl View {
         }
         // Get the line that has the cursor
         let line_idx = self.cursor.line - self.cache.before();
-        let line = match self.cache.lines().get(line_idx as usize) {
-            Some(line) => line,
-            None => {
-                error!("no valid line at cursor index {}", self.cursor.line);
-                return;
-            }
-        };
+        if self.cache.lines().get(line_idx as usize).is_none() {
+            error!("no valid line at cursor index {}", self.cursor.line);
+            return;
+        }
 
-        if line_idx < self.window.start() {
+        // If the line is folded, the cursor is drawn on the fold.
+        let row = Self::visible_position(&self.visible_lines(), line_idx as usize) as u64;
+        if self.detached && (row < self.window.start() || row >= self.window.end()) {
+            info!("the cursor was scrolled out of view");
+            return;
+        }
+        if row < self.window.start() {
             error!(
                 "the line that has the cursor (nb={}, cache_idx={}) not within the displayed window ({:?})",
                 self.cursor.line,
@@ -429,23 +2372,31 @@ impl View {
             );
             return;
         }
-        // Get the line vertical offset so that we know where to draw it.
-        let line_pos = line_idx - self.window.start();
-
-        // Calculate the cursor position on the line. The trick is that we know the position within
-        // the string, but characters may have various lengths. For the moment, we only handle
-        // control characters and tabs. We assume control characters (0x00-0x1f, excluding 0x09 ==
-        // tab) are rendered in caret notation and are thus two columns wide. Tabs are
-        // variable-width, rounding up to the next tab stop. All other characters are assumed to be
-        // one column wide.
-        let column: u16 = line
-            .text
-            .chars()
-            .take(self.cursor.column as usize)
-            .fold(0, |acc, c| acc + self.translate_char_width(acc, c));
+        // Get the line vertical offset so that we know where to draw
+        // it, counting the rows taken by the wrapped lines above.
+        let visible = self.visible_lines();
+        let mut line_pos: u64 = visible
+            .iter()
+            .skip(self.window.start() as usize)
+            .take((row - self.window.start()) as usize)
+            .map(|idx| u64::from(self.line_rows(*idx)))
+            .sum();
+
+        let mut column = self.cursor_column();
+        if self.cfg.wrap {
+            let text_width = self.text_width();
+            let sub_row = (column / text_width).min(self.line_rows(line_idx as usize) - 1);
+            line_pos += u64::from(sub_row);
+            column -= sub_row * text_width;
+        } else {
+            column -= self.h_scroll;
+        }
 
         // Draw the cursor
-        let cursor_pos = Goto(self.cfg.gutter_size + column + 1, line_pos as u16 + 1);
+        let cursor_pos = Goto(
+            left + self.cfg.gutter_size + column + 1,
+            top + line_pos as u16 + 1,
+        );
         if let Err(e) = write!(w, "{}", cursor_pos) {
             error!("failed to render cursor: {}", e);
         }
@@ -461,3 +2412,62 @@ impl View {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::future;
+    use tokio::runtime::current_thread::Runtime;
+    use xrl::{self, ViewId};
+
+    use core::TuiServiceBuilder;
+
+    use super::*;
+
+    /// Run `test` with a view whose client talks to `cat` instead of
+    /// the core, which is enough as long as nothing waits for answers.
+    fn with_view<F: FnOnce(&mut View)>(test: F) {
+        let mut runtime = Runtime::new().unwrap();
+        runtime
+            .block_on(future::lazy(move || {
+                let (builder, _core_events) = TuiServiceBuilder::new();
+                let (client, _stderr) = xrl::spawn("cat", builder).unwrap();
+                let mut view = View::new(Client::new(client, ViewId(1)), None);
+                test(&mut view);
+                Ok::<(), ()>(())
+            }))
+            .unwrap();
+    }
+
+    #[test]
+    fn restores_the_scroll_position_after_switching_back() {
+        with_view(|view| {
+            view.resize(20, 80);
+            view.cursor.line = 45;
+            view.window.set_start(40);
+            view.save_scroll();
+            // Another view takes the focus, and this one is resized to
+            // a pane of another size while it is hidden
+            view.resize(10, 80);
+            assert_ne!(view.window.start(), 40);
+            view.resize(20, 80);
+            view.restore_scroll();
+            assert_eq!(view.window.start(), 40);
+            // The position is restored once only
+            view.window.set_start(0);
+            view.restore_scroll();
+            assert_eq!(view.window.start(), 0);
+        });
+    }
+
+    #[test]
+    fn keeps_the_cursor_visible_when_restoring() {
+        with_view(|view| {
+            view.resize(20, 80);
+            view.window.set_start(40);
+            view.save_scroll();
+            view.cursor.line = 70;
+            view.restore_scroll();
+            assert_eq!(view.window.start(), 51);
+        });
+    }
+}
@@ -4,11 +4,22 @@ use super::view::Cursor;
 pub struct Window {
     start: u64,
     size: u16,
+    /// Minimum number of lines to keep visible above and below the
+    /// cursor.
+    scroll_off: u16,
 }
 
 impl Window {
     pub fn new() -> Self {
-        Window { start: 0, size: 0 }
+        Window {
+            start: 0,
+            size: 0,
+            scroll_off: 0,
+        }
+    }
+
+    pub fn set_scroll_off(&mut self, scroll_off: u16) {
+        self.scroll_off = scroll_off;
     }
 
     pub fn set_cursor(&mut self, cursor: &Cursor) {
@@ -21,6 +32,11 @@ impl Window {
         info!("new window: {:?}", self);
     }
 
+    /// Scroll so that the window starts at `start`.
+    pub fn set_start(&mut self, start: u64) {
+        self.start = start;
+    }
+
     pub fn resize(&mut self, height: u16) {
         self.size = height;
     }
@@ -64,6 +80,19 @@ impl Window {
             new_start = cursor - u64::from(self.size);
         }
 
+        // Keep `scroll_off` lines around the cursor, without going
+        // past the end of the buffer. The margin can't be more than
+        // half of the window, or the cursor could not move at all.
+        let margin = u64::from(self.scroll_off.min(self.size.saturating_sub(1) / 2));
+        if margin > 0 {
+            if cursor < new_start + margin {
+                new_start = cursor.saturating_sub(margin);
+            } else if cursor + margin >= new_start + u64::from(self.size) {
+                let last_start = nb_line.saturating_sub(u64::from(self.size));
+                new_start = (cursor + margin + 1 - u64::from(self.size)).min(last_start);
+            }
+        }
+
         self.start = new_start;
         info!("done resizing the window: {:?}", self);
     }
@@ -0,0 +1,66 @@
+//! Fitting rendered lines in the width of the terminal, by wrapping
+//! them or by scrolling horizontally.
+
+/// Split `text`, a line as rendered (with control characters and tabs
+/// already expanded, and style escape sequences inserted), into rows
+/// of at most `width` columns. Escape sequences take no room.
+pub fn split(text: &str, width: u16) -> Vec<&str> {
+    let width = usize::from(width.max(1));
+    let mut rows = Vec::new();
+    let mut row_start = 0;
+    let mut columns = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        if c == '\x1b' {
+            // Skip a CSI sequence: ESC '[', parameters, and a final
+            // byte between '@' and '~'.
+            if let Some(&(_, '[')) = chars.peek() {
+                chars.next();
+                for (_, c) in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        if columns == width {
+            rows.push(&text[row_start..idx]);
+            row_start = idx;
+            columns = 0;
+        }
+        columns += 1;
+    }
+    rows.push(&text[row_start..]);
+    rows
+}
+
+/// Return the columns of `text`, a line as rendered, from `start` and
+/// at most `width` columns wide. The escape sequences of the columns
+/// left out are kept, so that styles are still set and reset.
+pub fn slice(text: &str, start: u16, width: u16) -> String {
+    let (start, end) = (usize::from(start), usize::from(start) + usize::from(width));
+    let mut sliced = String::with_capacity(text.len());
+    let mut column = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            sliced.push(c);
+            if let Some(&'[') = chars.peek() {
+                sliced.push(chars.next().unwrap());
+                for c in chars.by_ref() {
+                    sliced.push(c);
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        if column >= start && column < end {
+            sliced.push(c);
+        }
+        column += 1;
+    }
+    sliced
+}